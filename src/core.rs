@@ -2,10 +2,12 @@
 //!
 //! Organized into:
 //! - Data structures (`Database`, `Table`, `Value`)
+//! - Logical plan (expressions bound to tables and compiled)
 //! - SQL execution pipeline
 //! - Storage management
 
 pub mod data_structure;
 pub mod executor;
 pub mod parser;
+pub mod plan;
 pub mod storage;
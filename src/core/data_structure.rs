@@ -18,9 +18,13 @@
 pub mod column_info;
 pub mod database;
 pub mod table;
+pub mod trigger;
 pub mod value;
+pub mod view;
 
 pub use column_info::{ColumnInfo, ColumnTypeSpecific};
 pub use database::Database;
 pub use table::Table;
+pub use trigger::{Trigger, TriggerEvent, TriggerTiming};
 pub use value::{Value, ValueNotNull};
+pub use view::{MaterializedView, View};
@@ -41,7 +41,18 @@ impl ColumnTypeSpecific {
     /// # Returns
     /// ColumnTypeSpecific or error if type is unsupported
     pub fn from_column_def(def: &ast::ColumnDef) -> DBResult<Self> {
-        Ok(match def.data_type {
+        Self::from_data_type(&def.data_type)
+    }
+
+    /// Creates ColumnTypeSpecific from SQL parser data type.
+    ///
+    /// # Arguments
+    /// * `data_type` - SQL parser data type
+    ///
+    /// # Returns
+    /// ColumnTypeSpecific or error if type is unsupported
+    pub fn from_data_type(data_type: &ast::DataType) -> DBResult<Self> {
+        Ok(match *data_type {
             ast::DataType::Int(width) => ColumnTypeSpecific::Int {
                 display_width: width,
             },
@@ -53,10 +64,34 @@ impl ColumnTypeSpecific {
             },
             _ => Err(DBSingleError::UnsupportedOPError(format!(
                 "unsupported type {}",
-                def.data_type
+                data_type
             )))?,
         })
     }
+
+    /// Checks whether two types are of the same kind, ignoring their parameters
+    /// (display width, maximum length).
+    pub fn is_same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl std::fmt::Display for ColumnTypeSpecific {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColumnTypeSpecific::Int {
+                display_width: Some(width),
+            } => write!(f, "INT({})", width),
+            ColumnTypeSpecific::Int {
+                display_width: None,
+            } => write!(f, "INT"),
+            ColumnTypeSpecific::Varchar {
+                max_length: u64::MAX,
+            } => write!(f, "VARCHAR"),
+            ColumnTypeSpecific::Varchar { max_length } => write!(f, "VARCHAR({})", max_length),
+            ColumnTypeSpecific::Any => write!(f, "ANY"),
+        }
+    }
 }
 
 /// Metadata about a database column.
@@ -1,17 +1,24 @@
 //! Database structure and operations.
 //!
-//! Contains the main Database type that manages all tables.
+//! Contains the main Database type that manages all tables, views and
+//! triggers.
 
-use super::{ColumnInfo, Table};
+use super::{ColumnInfo, MaterializedView, Table, Trigger, View};
 use crate::error::{DBResult, DBSingleError};
 use bincode::{Decode, Encode};
 use std::collections::HashMap;
 
-/// Represents a database containing multiple tables.
+/// Represents a database containing multiple tables and views.
 #[derive(Debug, Clone, Default, Decode, Encode)]
 pub struct Database {
     /// Map of table names to Table instances
     pub tables: HashMap<String, Table>,
+    /// Map of view names to View definitions
+    pub views: HashMap<String, View>,
+    /// Map of materialized view names to MaterializedView instances
+    pub materialized_views: HashMap<String, MaterializedView>,
+    /// Map of trigger names to Trigger definitions
+    pub triggers: HashMap<String, Trigger>,
 }
 
 impl Database {
@@ -19,8 +26,39 @@ impl Database {
     pub fn new() -> Self {
         Database {
             tables: HashMap::new(),
+            views: HashMap::new(),
+            materialized_views: HashMap::new(),
+            triggers: HashMap::new(),
         }
     }
+
+    /// Copies the database without the rows of its tables and materialized
+    /// views.
+    pub fn empty_copy(&self) -> Database {
+        let empty = |table: &Table| Table::new(table.columns_info.clone());
+        Database {
+            tables: self
+                .tables
+                .iter()
+                .map(|(name, table)| (name.clone(), empty(table)))
+                .collect(),
+            views: self.views.clone(),
+            materialized_views: self
+                .materialized_views
+                .iter()
+                .map(|(name, materialized)| {
+                    let materialized = MaterializedView {
+                        view: materialized.view.clone(),
+                        table: empty(&materialized.table),
+                        dependency_states: materialized.dependency_states.clone(),
+                    };
+                    (name.clone(), materialized)
+                })
+                .collect(),
+            triggers: self.triggers.clone(),
+        }
+    }
+
     /// Creates a new table in the database.
     ///
     /// # Arguments
@@ -39,13 +77,17 @@ impl Database {
         }
     }
 
-    /// Removes a table from the database.
+    /// Removes a table from the database, with its triggers.
     ///
     /// # Arguments
     /// * `table_name` - Name of the table to remove
     pub fn drop_table(&mut self, table_name: &str) -> DBResult<()> {
         match self.tables.remove(table_name) {
-            Some(_) => Ok(()),
+            Some(_) => {
+                self.triggers
+                    .retain(|_, trigger| trigger.table_name != table_name);
+                Ok(())
+            }
             None => Err(DBSingleError::OtherError(format!(
                 "table {} not found",
                 table_name
@@ -53,6 +95,22 @@ impl Database {
         }
     }
 
+    /// Removes a view or a materialized view from the database.
+    ///
+    /// # Arguments
+    /// * `view_name` - Name of the view to remove
+    pub fn drop_view(&mut self, view_name: &str) -> DBResult<()> {
+        if self.views.remove(view_name).is_some()
+            || self.materialized_views.remove(view_name).is_some()
+        {
+            return Ok(());
+        }
+        Err(DBSingleError::OtherError(format!(
+            "view {} not found",
+            view_name
+        )))?
+    }
+
     /// Gets an immutable reference to a table.
     ///
     /// # Arguments
@@ -74,4 +132,81 @@ impl Database {
     pub fn get_table_mut(&mut self, table_name: &str) -> Option<&mut Table> {
         self.tables.get_mut(table_name)
     }
+
+    /// Checks whether a table, a view or a materialized view has the given
+    /// name.
+    ///
+    /// # Arguments
+    /// * `name` - Name to check
+    pub fn contains_relation(&self, name: &str) -> bool {
+        self.tables.contains_key(name)
+            || self.views.contains_key(name)
+            || self.materialized_views.contains_key(name)
+    }
+
+    /// Gets a view definition.
+    ///
+    /// # Arguments
+    /// * `view_name` - Name of the view to retrieve
+    ///
+    /// # Returns
+    /// The view if found, None otherwise
+    pub fn get_view(&self, view_name: &str) -> Option<&View> {
+        self.views.get(view_name)
+    }
+
+    /// Gets a materialized view.
+    ///
+    /// # Arguments
+    /// * `view_name` - Name of the materialized view to retrieve
+    ///
+    /// # Returns
+    /// The materialized view if found, None otherwise
+    pub fn get_materialized_view(&self, view_name: &str) -> Option<&MaterializedView> {
+        self.materialized_views.get(view_name)
+    }
+
+    /// Gets the views and materialized views depending on a table or view,
+    /// directly or through other views.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the table or view
+    ///
+    /// # Returns
+    /// Names of the dependent views, sorted
+    pub fn get_dependent_views(&self, name: &str) -> Vec<String> {
+        let views = self.views.iter().chain(
+            self.materialized_views
+                .iter()
+                .map(|(view_name, materialized)| (view_name, &materialized.view)),
+        );
+        let mut dependents = vec![];
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            for (view_name, view) in views.clone() {
+                if view.dependencies.iter().any(|dep| dep == name)
+                    && !dependents.contains(view_name)
+                {
+                    dependents.push(view_name.clone());
+                    pending.push(view_name);
+                }
+            }
+        }
+        dependents.sort();
+        dependents
+    }
+
+    /// Gets the triggers of a table, sorted by name.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the table
+    pub fn get_table_triggers(&self, table_name: &str) -> Vec<(&String, &Trigger)> {
+        let mut triggers = self
+            .triggers
+            .iter()
+            .filter(|(_, trigger)| trigger.table_name == table_name)
+            .collect::<Vec<_>>();
+        triggers.sort_by_key(|(name, _)| *name);
+        triggers
+    }
 }
@@ -2,8 +2,9 @@
 //!
 //! Contains the Table type that manages rows and columns of data.
 
-use super::{ColumnInfo, Value, ValueNotNull};
-use crate::error::{DBResult, DBSingleError};
+use super::{ColumnInfo, ColumnTypeSpecific, Value, ValueNotNull};
+use crate::core::plan::{self, NameResolution};
+use crate::error::DBResult;
 use bincode::{Decode, Encode};
 use lazy_static::lazy_static;
 use sqlparser::ast;
@@ -24,6 +25,9 @@ pub struct Table {
     pub columns_info: Vec<ColumnInfo>,
     /// Mapping from column names to their indices
     pub column_rmap: HashMap<String, usize>,
+    /// Number of changes to the existing rows or to the columns; appending
+    /// rows is not counted
+    pub modification_count: usize,
 }
 
 impl Table {
@@ -44,6 +48,7 @@ impl Table {
             columns_values: vec![HashSet::new(); columns_info.len()],
             columns_info,
             column_rmap,
+            modification_count: 0,
         }
     }
 
@@ -57,6 +62,7 @@ impl Table {
                 columns_values: vec![],
                 columns_info: vec![],
                 column_rmap: HashMap::new(),
+                modification_count: 0,
             };
         }
         &DUMMY
@@ -91,144 +97,198 @@ impl Table {
         &self.columns_info[column_index]
     }
 
-    /// Evaluates a SQL expression against a row of values.
-    /// In fact only `self.columns_rmap` is used to determine the column index,
+    /// Infers the type of each column.
+    ///
+    /// Declared types are kept; for columns of type [`ColumnTypeSpecific::Any`]
+    /// (e.g. computed columns), the type is inferred from the stored values.
+    /// Columns without non-null values, or with values of different types,
+    /// stay `Any`.
+    pub fn infer_column_types(&self) -> Vec<ColumnTypeSpecific> {
+        self.columns_info
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                if !matches!(column.type_specific, ColumnTypeSpecific::Any) {
+                    return column.type_specific;
+                }
+                let mut inferred = None;
+                for value in self.existed_rows().filter_map(|row| row[i].0.as_ref()) {
+                    let value_type = match value {
+                        ValueNotNull::Int(_) => ColumnTypeSpecific::Int {
+                            display_width: None,
+                        },
+                        ValueNotNull::Varchar(_) => ColumnTypeSpecific::Varchar {
+                            max_length: u64::MAX,
+                        },
+                    };
+                    match inferred {
+                        None => inferred = Some(value_type),
+                        Some(prev) if !prev.is_same_kind(&value_type) => {
+                            return ColumnTypeSpecific::Any;
+                        }
+                        Some(_) => {}
+                    }
+                }
+                inferred.unwrap_or(ColumnTypeSpecific::Any)
+            })
+            .collect()
+    }
+
+    /// Removes all columns after the first `column_num` ones.
+    ///
+    /// # Arguments
+    /// * `column_num` - Number of leading columns to keep
+    pub fn truncate_columns(&mut self, column_num: usize) {
+        self.columns_info.truncate(column_num);
+        self.columns_values.truncate(column_num);
+        self.column_rmap = self
+            .columns_info
+            .iter()
+            .enumerate()
+            .map(|(i, col)| (col.name.clone(), i))
+            .collect();
+        for row in self.existed_rows_mut() {
+            row.truncate(column_num);
+        }
+    }
+
+    /// Appends columns holding the given values, whose unique values are not
+    /// tracked; the inverse of [`truncate_columns`](Self::truncate_columns).
+    ///
+    /// # Arguments
+    /// * `columns_info` - Metadata of the new columns
+    /// * `values` - Values of the new columns, for each existing row in order
+    pub fn extend_columns(&mut self, columns_info: Vec<ColumnInfo>, values: Vec<Vec<Value>>) {
+        for column_info in columns_info {
+            self.column_rmap
+                .insert(column_info.name.clone(), self.columns_info.len());
+            self.columns_info.push(column_info);
+            self.columns_values.push(HashSet::new());
+        }
+        for (row, values) in self.existed_rows_mut().zip(values) {
+            row.extend(values);
+        }
+    }
+
+    /// Removes all rows, including the deleted ones, and resets the row
+    /// indices.
+    pub fn truncate(&mut self) {
+        self.rows = BTreeMap::new();
+        self.row_idx_acc = 0;
+        self.row_num = 0;
+        self.columns_values = vec![HashSet::new(); self.columns_info.len()];
+        self.modification_count += 1;
+    }
+
+    /// Gets the number of deleted rows still held by the table.
+    pub fn get_tombstone_num(&self) -> usize {
+        self.rows.len() - self.row_num
+    }
+
+    /// Removes the deleted rows, renumbering the remaining rows in order.
+    pub fn compact(&mut self) {
+        self.rows = std::mem::take(&mut self.rows)
+            .into_values()
+            .flatten()
+            .map(Some)
+            .enumerate()
+            .collect();
+        self.row_idx_acc = self.rows.len();
+        self.modification_count += 1;
+    }
+
+    /// Appends a column, holding the same value in every row.
+    ///
+    /// The unique values of the new column are not tracked.
+    ///
+    /// # Arguments
+    /// * `column_info` - Metadata of the new column
+    /// * `value` - Value of the new column in every row
+    pub fn add_column(&mut self, column_info: ColumnInfo, value: Value) {
+        self.column_rmap
+            .insert(column_info.name.clone(), self.columns_info.len());
+        self.columns_info.push(column_info);
+        self.columns_values.push(HashSet::new());
+        for row in self.existed_rows_mut() {
+            row.push(value.clone());
+        }
+        self.modification_count += 1;
+    }
+
+    /// Removes a column.
+    ///
+    /// # Arguments
+    /// * `column_index` - Index of the column
+    pub fn remove_column(&mut self, column_index: usize) {
+        self.columns_info.remove(column_index);
+        self.columns_values.remove(column_index);
+        self.column_rmap = self
+            .columns_info
+            .iter()
+            .enumerate()
+            .map(|(i, col)| (col.name.clone(), i))
+            .collect();
+        for row in self.existed_rows_mut() {
+            row.remove(column_index);
+        }
+        self.modification_count += 1;
+    }
+
+    /// Renames a column.
+    ///
+    /// # Arguments
+    /// * `column_index` - Index of the column
+    /// * `name` - New name of the column
+    pub fn rename_column(&mut self, column_index: usize, name: String) {
+        let column = &mut self.columns_info[column_index];
+        if self.column_rmap.get(&column.name) == Some(&column_index) {
+            self.column_rmap.remove(&column.name);
+        }
+        self.column_rmap.insert(name.clone(), column_index);
+        column.name = name;
+        self.modification_count += 1;
+    }
+
+    /// Evaluates a SQL expression against a row of values, binding it to
+    /// the columns of this table and compiling it first; see [`plan::compile`].
+    ///
+    /// To evaluate an expression against many rows, compile it once instead.
     ///
     /// # Arguments
     /// * `row` - Row values to evaluate against
     /// * `expr` - SQL expression to evaluate
+    /// * `resolution` - How identifiers that do not name a column are resolved
     ///
     /// # Returns
     /// The evaluated [`Value`].
-    pub fn calc_expr_for_row(&self, row: &[Value], expr: &ast::Expr) -> DBResult<Value> {
-        use ast::Expr;
-        Ok(match expr {
-            Expr::Nested(expr) => self.calc_expr_for_row(row, expr)?,
-            Expr::Identifier(name) => {
-                if name.quote_style.is_some() {
-                    Value::from_varchar(name.value.clone())
-                } else {
-                    match self.get_column_index(&name.value) {
-                        Some(index) => row[index].clone(),
-                        None => Value::from_varchar(name.value.clone()),
-                    }
-                }
-            }
-
-            Expr::Value(val) => match &val.value {
-                ast::Value::Number(num, ..) => {
-                    Value::from_int(num.parse::<i32>().map_err(|_| {
-                        DBSingleError::OtherError(format!("invalid number {}", num))
-                    })?)
-                }
-                ast::Value::Boolean(b) => Value::from_bool(*b),
-                ast::Value::Null => Value::from_null(),
-                ast::Value::SingleQuotedString(s) => Value::from_varchar(s.clone()),
-                ast::Value::DoubleQuotedString(s) => Value::from_varchar(s.clone()),
-                _ => Err(DBSingleError::UnsupportedOPError(format!(
-                    "unsupported value type {:?}",
-                    val
-                )))?,
-            },
-
-            Expr::IsFalse(expr) => Value::from_bool(
-                self.calc_expr_for_row(row, expr)?
-                    .try_to_bool()?
-                    .map(|b| !b)
-                    .unwrap_or(false),
-            ),
-            Expr::IsTrue(expr) => Value::from_bool(
-                self.calc_expr_for_row(row, expr)?
-                    .try_to_bool()?
-                    .unwrap_or(false),
-            ),
-            Expr::IsNotTrue(expr) => Value::from_bool(
-                self.calc_expr_for_row(row, expr)?
-                    .try_to_bool()?
-                    .map(|b| !b)
-                    .unwrap_or(true),
-            ),
-            Expr::IsNotFalse(expr) => Value::from_bool(
-                self.calc_expr_for_row(row, expr)?
-                    .try_to_bool()?
-                    .unwrap_or(true),
-            ),
-            Expr::IsNull(expr) => Value::from_bool(self.calc_expr_for_row(row, expr)?.is_null()),
-            Expr::IsNotNull(expr) => {
-                Value::from_bool(!self.calc_expr_for_row(row, expr)?.is_null())
-            }
-            Expr::BinaryOp { left, op, right } => {
-                let left = self.calc_expr_for_row(row, left)?.0;
-                let right = self.calc_expr_for_row(row, right)?.0;
-                match (left, right) {
-                    (Some(ValueNotNull::Int(left)), Some(ValueNotNull::Int(right))) => {
-                        use ast::BinaryOperator::*;
-                        match op {
-                            Plus => Value::from_int(left + right),
-                            Minus => Value::from_int(left - right),
-                            Multiply => Value::from_int(left * right),
-                            Divide => Value::from_int(left / right),
-                            Modulo => Value::from_int(left % right),
-                            Gt => Value::from_bool(left > right),
-                            Lt => Value::from_bool(left < right),
-                            GtEq => Value::from_bool(left >= right),
-                            LtEq => Value::from_bool(left <= right),
-                            Eq => Value::from_bool(left == right),
-                            NotEq => Value::from_bool(left != right),
-                            And => Value::from_bool(left != 0 && right != 0),
-                            Or => Value::from_bool(left != 0 || right != 0),
-                            _ => Err(DBSingleError::UnsupportedOPError(format!(
-                                "unsupported binary operator {:?}",
-                                op
-                            )))?,
-                        }
-                    }
-                    (
-                        Some(ValueNotNull::Varchar(ref left)),
-                        Some(ValueNotNull::Varchar(ref right)),
-                    ) => {
-                        use ast::BinaryOperator::*;
-                        match op {
-                            Eq => Value::from_bool(left == right),
-                            _ => Err(DBSingleError::UnsupportedOPError(format!(
-                                "unsupported binary operator {:?}",
-                                op
-                            )))?,
-                        }
-                    }
-                    (left, right) => Err(DBSingleError::UnsupportedOPError(format!(
-                        "unsupported binary operator {:?} {:?} {:?}",
-                        op, left, right
-                    )))?,
-                }
-            }
-
-            _ => Err(DBSingleError::UnsupportedOPError(format!(
-                "unsupported expression {:?}",
-                expr
-            )))?,
-        })
+    pub fn calc_expr_for_row(
+        &self,
+        row: &[Value],
+        expr: &ast::Expr,
+        resolution: NameResolution,
+    ) -> DBResult<Value> {
+        plan::compile(expr, self, resolution)?.eval(row)
     }
 
-    /// Checks if a row satisfies a given condition (SQL expression).
-    /// In fact only `self.columns_rmap` is used to determine the column index.
+    /// Checks if a row satisfies a given condition (SQL expression), compiling
+    /// it against the columns of this table first.
     ///
     /// # Arguments
     /// * `row` - Row values to check against the condition
     /// * `cond` - Optional SQL expression to evaluate as the condition
+    /// * `resolution` - How identifiers that do not name a column are resolved
     ///
     /// # Returns
     /// True if the row satisfies the condition, false otherwise.
     ///
     /// If `cond` is None, always returns true.
-    pub fn is_row_satisfy_cond(&self, row: &[Value], cond: Option<&ast::Expr>) -> DBResult<bool> {
-        Ok(match cond {
-            Some(expr) => self
-                .calc_expr_for_row(row, expr)?
-                .try_to_bool()?
-                .unwrap_or(false),
-            None => true,
-        })
+    pub fn is_row_satisfy_cond(
+        &self,
+        row: &[Value],
+        cond: Option<&ast::Expr>,
+        resolution: NameResolution,
+    ) -> DBResult<bool> {
+        plan::is_row_satisfy_cond(row, plan::compile_cond(cond, self, resolution)?.as_ref())
     }
 
     /// Iterates over existing rows (non-deleted).
@@ -0,0 +1,40 @@
+//! Trigger definitions.
+
+use bincode::{Decode, Encode};
+
+/// When a trigger fires relative to the change of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+pub enum TriggerTiming {
+    /// Before the statement changes the table
+    Before,
+    /// After the statement changed the table
+    After,
+}
+
+/// The kind of change firing a trigger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+pub enum TriggerEvent {
+    /// A row is inserted
+    Insert,
+    /// A row is updated
+    Update,
+    /// A row is deleted
+    Delete,
+}
+
+/// A row-level trigger, running SQL statements for each changed row of a
+/// table.
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct Trigger {
+    /// Name of the table the trigger is attached to
+    pub table_name: String,
+    /// When the trigger fires
+    pub timing: TriggerTiming,
+    /// Events firing the trigger
+    pub events: Vec<TriggerEvent>,
+    /// SQL text of the condition on the changed row for the trigger to fire
+    pub condition: Option<String>,
+    /// SQL text of the statements run for each changed row, which refer to
+    /// the row as NEW and OLD
+    pub body: String,
+}
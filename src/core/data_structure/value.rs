@@ -106,6 +106,22 @@ impl Value {
             None => None,
         })
     }
+    /// Attempts to convert the value to an integer.
+    ///
+    /// # Returns
+    /// - Some(i) for Int values
+    /// - None for NULL
+    /// - Error for Varchar values
+    pub fn try_to_int(&self) -> DBResult<Option<i32>> {
+        Ok(match &self.0 {
+            Some(ValueNotNull::Int(x)) => Some(*x),
+            Some(ValueNotNull::Varchar(s)) => Err(DBSingleError::OtherError(format!(
+                "Cannot convert {} to int",
+                s
+            )))?,
+            None => None,
+        })
+    }
 }
 
 impl From<Option<ValueNotNull>> for Value {
@@ -0,0 +1,28 @@
+//! View definitions.
+
+use super::Table;
+use bincode::{Decode, Encode};
+
+/// A named query stored in the database, evaluated whenever it is read.
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct View {
+    /// SQL text of the query defining the view
+    pub query: String,
+    /// Names of the columns of the view, renaming the leading columns of the
+    /// query result
+    pub column_names: Vec<String>,
+    /// Names of the tables and views the query reads from
+    pub dependencies: Vec<String>,
+}
+
+/// A view whose rows are stored as a table, and recomputed on refresh.
+#[derive(Debug, Clone, Decode, Encode)]
+pub struct MaterializedView {
+    /// Definition of the view
+    pub view: View,
+    /// Rows of the view as of the last refresh
+    pub table: Table,
+    /// Row index accumulator and modification count of each table the view
+    /// depends on as of the last refresh, None for views
+    pub dependency_states: Vec<Option<(usize, usize)>>,
+}
@@ -1,23 +1,44 @@
 //! SQL statement execution and table management.
 
+mod alter_table;
+mod catalog;
 mod create_table;
+mod cte;
 mod delete;
 mod drop_table;
+mod explain;
 mod insert;
 mod query;
+mod returning;
+mod set_operation;
+mod subquery;
+mod trigger;
+mod truncate;
 mod update;
+mod upsert;
 mod utils;
+mod vacuum;
+mod view;
 
+pub mod aggregate;
+pub mod change;
 pub mod table_manager;
+pub mod window;
 
-use crate::core::data_structure::Database;
-use crate::core::parser::SQLParser;
+use crate::core::data_structure::{Database, Table, Value};
+use crate::core::parser::{SQLParser, SQLStatement};
+use crate::core::plan::NameResolution;
 use crate::core::storage;
 use crate::error::join_result;
 use crate::error::{DBResult, DBSingleError};
 use crate::interface::SQLExecConfig;
+use aggregate::{Accumulator, AggregateRegistry};
+use change::{ChangeEvent, ChangeHook};
+use explain::ExplainRecorder;
 use sqlparser::ast;
+use std::collections::HashMap;
 use std::fmt::Write;
+use std::sync::Arc;
 use table_manager::{ParallelTableManager, SequentialTableManager, TableManager};
 
 /// SQLExecutor is responsible for executing SQL statements against a database.
@@ -49,6 +70,12 @@ pub struct SQLExecutor {
     config: SQLExecConfig,
     /// The table manager used for managing tables during execution.
     table_manager: Box<dyn TableManager>,
+    /// The aggregate functions available to queries.
+    aggregates: AggregateRegistry,
+    /// Number of rows affected by each statement of the last executed SQL.
+    affected_rows: Vec<Option<usize>>,
+    /// The functions called for each committed row change.
+    change_hooks: Vec<ChangeHook>,
 }
 
 /// State for SQLExecutor to track execution progress and output.
@@ -63,6 +90,15 @@ struct SQLExecutorState<'a> {
     output_count: usize,
     /// The buffer to accumulate output results.
     output_buffer: String,
+    /// The relations defined by the enclosing WITH clauses, visible by name.
+    ctes: HashMap<String, Arc<Table>>,
+    /// The rows referred to as NEW and OLD by a firing trigger, holding the
+    /// values by column name.
+    trigger_rows: HashMap<String, HashMap<String, Value>>,
+    /// Number of triggers firing, each from the body of the previous one.
+    trigger_depth: usize,
+    /// The recorder of the operators run, when the statement is explained.
+    explain: Option<&'a ExplainRecorder>,
 }
 
 impl SQLExecutor {
@@ -92,29 +128,125 @@ impl SQLExecutor {
             database,
             config,
             table_manager,
+            aggregates: AggregateRegistry::default(),
+            affected_rows: vec![],
+            change_hooks: vec![],
         })
     }
+
+    /// Registers a user-defined aggregate function.
+    ///
+    /// A registered aggregate replaces any existing one with the same name,
+    /// including the built-in ones.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the aggregate, case-insensitive
+    /// * `accumulator` - The [`Accumulator`] implementing the aggregate
+    pub fn register_aggregate<A: Accumulator>(&mut self, name: &str, accumulator: A) {
+        self.aggregates.register(name, accumulator);
+    }
+
+    /// Gets the number of rows affected by each statement of the last
+    /// [`execute_sql`](Self::execute_sql) call, in order.
+    ///
+    /// The number is `Some` for INSERT (inserted or updated rows), UPDATE
+    /// (matched rows) and DELETE (deleted rows) statements that succeeded,
+    /// and `None` for other or failed statements.
+    ///
+    /// # Example
+    /// ```
+    /// use helidb::SQLExecConfig;
+    ///
+    /// let mut executor = SQLExecConfig::new().connect().unwrap();
+    /// executor
+    ///     .execute_sql(
+    ///         "CREATE TABLE t (id INT);
+    ///          INSERT INTO t VALUES (1), (2), (3);
+    ///          DELETE FROM t WHERE id > 1;",
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(executor.affected_rows(), [None, Some(3), Some(2)]);
+    /// ```
+    pub fn affected_rows(&self) -> &[Option<usize>] {
+        &self.affected_rows
+    }
+
+    /// Registers a function called for each committed row change, with the
+    /// table name, the operation, and the rows before and after the change.
+    ///
+    /// Hooks are called in the order they were registered. See
+    /// [`change`] for when changes are reported.
+    ///
+    /// # Arguments
+    /// * `hook` - Function called with each [`ChangeEvent`]
+    pub fn on_change<F: FnMut(&ChangeEvent) + Send + Sync + 'static>(&mut self, hook: F) {
+        self.change_hooks.push(Box::new(hook));
+    }
 }
 
 impl SQLExecutor {
+    /// Gets how identifiers that do not name a column are resolved, as configured.
+    fn name_resolution(&self) -> NameResolution {
+        match self.config.lenient_identifiers {
+            true => NameResolution::Lenient,
+            false => NameResolution::Strict,
+        }
+    }
+
     /// Executes a single SQL statement.
     ///
     /// # Arguments
     /// * `statement` - Parsed SQL statement to execute
     /// * `executor_state` - Mutable state to track execution progress and output
+    ///
+    /// # Returns
+    /// Number of rows affected by an INSERT, UPDATE or DELETE statement
     fn execute_statement(
         &mut self,
-        statement: &ast::Statement,
+        statement: &SQLStatement,
         executor_state: &mut SQLExecutorState,
-    ) -> DBResult<()> {
+    ) -> DBResult<Option<usize>> {
+        let statement = match statement {
+            SQLStatement::Standard(statement) => statement.as_ref(),
+            SQLStatement::Vacuum { table_names } => {
+                return self.execute_vacuum(table_names).map(|_| None);
+            }
+            SQLStatement::RefreshMaterializedView { name } => {
+                return self.execute_refresh_materialized_view(name).map(|_| None);
+            }
+            SQLStatement::CreateTrigger(create_trigger) => {
+                return self.execute_create_trigger(create_trigger).map(|_| None);
+            }
+        };
         use ast::Statement::*;
         match statement {
-            CreateTable(create_table) => self.execute_create_table(create_table),
-            Drop { .. } => self.execute_drop_table(statement),
-            Insert(insert) => self.execute_insert(insert),
-            Query(query) => self.execute_query(query, executor_state),
-            Update { .. } => self.execute_update(statement),
-            Delete(delete) => self.execute_delete(delete),
+            CreateTable(create_table) => self
+                .execute_create_table(create_table, executor_state)
+                .map(|_| None),
+            CreateView { .. } => self.execute_create_view(statement).map(|_| None),
+            AlterTable { .. } => self.execute_alter_table(statement).map(|_| None),
+            Drop { .. } => self.execute_drop_table(statement).map(|_| None),
+            DropTrigger { .. } => self.execute_drop_trigger(statement).map(|_| None),
+            Insert(insert) => self.execute_insert(insert, executor_state).map(Some),
+            Query(query) => self.execute_query(query, executor_state).map(|_| None),
+            Update { .. } => self.execute_update(statement, executor_state).map(Some),
+            Delete(delete) => self.execute_delete(delete, executor_state).map(Some),
+            Truncate { .. } => self.execute_truncate(statement).map(|_| None),
+            ShowTables { .. } => self
+                .execute_show_tables(statement, executor_state)
+                .map(|_| None),
+            ShowCreate { .. } => self
+                .execute_show_create(statement, executor_state)
+                .map(|_| None),
+            ShowColumns { .. } => self
+                .execute_show_columns(statement, executor_state)
+                .map(|_| None),
+            Explain { .. } => self
+                .execute_explain(statement, executor_state)
+                .map(|_| None),
+            ExplainTable { table_name, .. } => self
+                .execute_describe(table_name, executor_state)
+                .map(|_| None),
             _ => Err(DBSingleError::UnsupportedOPError(format!(
                 "statement {:?}",
                 statement
@@ -122,6 +254,28 @@ impl SQLExecutor {
         }
     }
 
+    /// Outputs the number of rows affected by a statement.
+    ///
+    /// # Arguments
+    /// * `affected_rows` - Number of affected rows
+    /// * `executor_state` - Mutable state to track execution progress and output
+    fn output_affected_rows(
+        affected_rows: usize,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        if executor_state.output_count > 0 {
+            writeln!(executor_state.output_buffer)?;
+        }
+        let plural = if affected_rows == 1 { "" } else { "s" };
+        writeln!(
+            executor_state.output_buffer,
+            "{} row{} affected.",
+            affected_rows, plural
+        )?;
+        executor_state.output_count += 1;
+        Ok(())
+    }
+
     /// Executes a series of SQL statements and accumulates the output.
     ///
     /// # Arguments
@@ -134,16 +288,26 @@ impl SQLExecutor {
             sql_statements,
             output_count: 0,
             output_buffer: String::new(),
+            ctes: HashMap::new(),
+            trigger_rows: HashMap::new(),
+            trigger_depth: 0,
+            explain: None,
         };
 
+        self.affected_rows.clear();
         let statements = SQLParser::new().parse(sql_statements)?;
 
         let mut result = Ok(());
         for statement in statements.iter() {
-            result = join_result(
-                result,
-                self.execute_statement(statement, &mut execute_state),
-            );
+            let statement_result = self.execute_statement(statement, &mut execute_state);
+            let affected_rows = statement_result.as_ref().ok().copied().flatten();
+            self.affected_rows.push(affected_rows);
+            if let Some(affected_rows) = affected_rows
+                && self.config.show_affected_rows
+            {
+                Self::output_affected_rows(affected_rows, &mut execute_state)?;
+            }
+            result = join_result(result, statement_result.map(|_| ()));
         }
         if execute_state.output_count == 0 {
             writeln!(
@@ -0,0 +1,644 @@
+//! Aggregate functions and their accumulators.
+//!
+//! Aggregates are computed through the [`Accumulator`] trait: a state is
+//! initialized for every group, updated with the arguments of every row,
+//! and finalized into a single [`Value`]. States computed on different
+//! chunks of a table can be merged, which allows the
+//! [`ParallelTableManager`](super::table_manager::ParallelTableManager)
+//! to aggregate chunks independently.
+//!
+//! The built-in aggregates `COUNT`, `SUM`, `MIN`, `MAX` and `AVG` are
+//! implemented with the same trait as user-defined ones.
+//!
+//! # Example
+//! ```
+//! use helidb::core::data_structure::Value;
+//! use helidb::core::executor::aggregate::Accumulator;
+//! use helidb::error::DBResult;
+//! use helidb::SQLExecConfig;
+//!
+//! /// Product of all non-null integer arguments.
+//! struct Product;
+//!
+//! impl Accumulator for Product {
+//!     type State = Option<i32>;
+//!
+//!     fn init(&self) -> Self::State {
+//!         None
+//!     }
+//!
+//!     fn update(&self, state: &mut Self::State, args: &[Value]) -> DBResult<()> {
+//!         if let Some(x) = args[0].try_to_int()? {
+//!             *state = Some(state.unwrap_or(1) * x);
+//!         }
+//!         Ok(())
+//!     }
+//!
+//!     fn merge(&self, state: &mut Self::State, other: Self::State) -> DBResult<()> {
+//!         if let Some(x) = other {
+//!             *state = Some(state.unwrap_or(1) * x);
+//!         }
+//!         Ok(())
+//!     }
+//!
+//!     fn finalize(&self, state: Self::State) -> DBResult<Value> {
+//!         Ok(state.map_or_else(Value::from_null, Value::from_int))
+//!     }
+//! }
+//!
+//! let mut executor = SQLExecConfig::new().connect().unwrap();
+//! executor.register_aggregate("product", Product);
+//! let output = executor
+//!     .execute_sql(
+//!         "CREATE TABLE t (x INT);
+//!          INSERT INTO t VALUES (2), (3), (4);
+//!          SELECT PRODUCT(x) FROM t;",
+//!     )
+//!     .unwrap();
+//! assert_eq!(output, "| PRODUCT(x) |\n\
+//!                     | ---------- |\n\
+//!                     | 24         |\n");
+//!
+//! // without `evaluate`, each frame of a window is aggregated from scratch
+//! let output = executor
+//!     .execute_sql("SELECT x, PRODUCT(x) OVER (ORDER BY x) AS p FROM t;")
+//!     .unwrap();
+//! assert_eq!(output, "| x   | p   |\n\
+//!                     | --- | --- |\n\
+//!                     | 2   | 2   |\n\
+//!                     | 3   | 6   |\n\
+//!                     | 4   | 24  |\n");
+//! ```
+
+use crate::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table, Value};
+use crate::core::plan::{self, CompiledExpr, NameResolution};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// An aggregate function described by its accumulator state.
+///
+/// The state of a group starts from [`init`](Accumulator::init), is fed the
+/// evaluated arguments of each row by [`update`](Accumulator::update), and is
+/// turned into the result by [`finalize`](Accumulator::finalize).
+/// [`merge`](Accumulator::merge) combines two partial states of the same group,
+/// e.g. computed on different chunks in parallel.
+///
+/// Accumulators also compute aggregates used as window functions
+/// (`SUM(x) OVER (...)`). A frame that keeps growing is computed by updating
+/// one state if the accumulator can [`evaluate`](Accumulator::evaluate) it,
+/// and from scratch otherwise.
+pub trait Accumulator: Send + Sync + 'static {
+    /// The intermediate state of one group.
+    type State: Send + 'static;
+
+    /// Creates the initial state of a group.
+    fn init(&self) -> Self::State;
+
+    /// Updates the state with the arguments of one row.
+    ///
+    /// # Arguments
+    /// * `state` - State of the group the row belongs to
+    /// * `args` - Evaluated arguments; empty for `*` (e.g. `COUNT(*)`)
+    fn update(&self, state: &mut Self::State, args: &[Value]) -> DBResult<()>;
+
+    /// Merges another partial state of the same group into `state`.
+    fn merge(&self, state: &mut Self::State, other: Self::State) -> DBResult<()>;
+
+    /// Produces the final value of a group.
+    fn finalize(&self, state: Self::State) -> DBResult<Value>;
+
+    /// Produces the value of a group so far, leaving its state to be updated
+    /// further.
+    ///
+    /// # Returns
+    /// None if the accumulator does not support it, which is the default
+    fn evaluate(&self, _state: &Self::State) -> Option<DBResult<Value>> {
+        None
+    }
+}
+
+/// Type-erased state of an aggregate.
+pub type AggregateState = Box<dyn Any + Send>;
+
+/// Type-erased version of [`Accumulator`], used to store aggregates of
+/// different state types in one registry.
+pub trait DynAccumulator: Send + Sync {
+    /// See [`Accumulator::init`].
+    fn init(&self) -> AggregateState;
+    /// See [`Accumulator::update`].
+    fn update(&self, state: &mut AggregateState, args: &[Value]) -> DBResult<()>;
+    /// See [`Accumulator::merge`].
+    fn merge(&self, state: &mut AggregateState, other: AggregateState) -> DBResult<()>;
+    /// See [`Accumulator::finalize`].
+    fn finalize(&self, state: AggregateState) -> DBResult<Value>;
+    /// See [`Accumulator::evaluate`].
+    fn evaluate(&self, state: &AggregateState) -> Option<DBResult<Value>>;
+}
+
+/// Downcasts a type-erased state back to the state of its accumulator.
+fn downcast_state<T: 'static>(state: AggregateState) -> DBResult<T> {
+    Ok(*state
+        .downcast::<T>()
+        .map_err(|_| DBSingleError::OtherError("aggregate state type mismatch".into()))?)
+}
+
+impl<A: Accumulator> DynAccumulator for A {
+    fn init(&self) -> AggregateState {
+        Box::new(Accumulator::init(self))
+    }
+
+    fn update(&self, state: &mut AggregateState, args: &[Value]) -> DBResult<()> {
+        let state = state
+            .downcast_mut::<A::State>()
+            .ok_or_else(|| DBSingleError::OtherError("aggregate state type mismatch".into()))?;
+        Accumulator::update(self, state, args)
+    }
+
+    fn merge(&self, state: &mut AggregateState, other: AggregateState) -> DBResult<()> {
+        let other = downcast_state::<A::State>(other)?;
+        let state = state
+            .downcast_mut::<A::State>()
+            .ok_or_else(|| DBSingleError::OtherError("aggregate state type mismatch".into()))?;
+        Accumulator::merge(self, state, other)
+    }
+
+    fn finalize(&self, state: AggregateState) -> DBResult<Value> {
+        Accumulator::finalize(self, downcast_state::<A::State>(state)?)
+    }
+
+    fn evaluate(&self, state: &AggregateState) -> Option<DBResult<Value>> {
+        match state.downcast_ref::<A::State>() {
+            Some(state) => Accumulator::evaluate(self, state),
+            None => Some(Err(DBSingleError::OtherError(
+                "aggregate state type mismatch".into(),
+            )
+            .into())),
+        }
+    }
+}
+
+/// Gets the only argument of an aggregate.
+fn single_arg<'a>(name: &str, args: &'a [Value]) -> DBResult<&'a Value> {
+    match args {
+        [arg] => Ok(arg),
+        _ => Err(DBSingleError::OtherError(format!(
+            "{} expects exactly one argument",
+            name
+        )))?,
+    }
+}
+
+/// Adds two integers, reporting overflow as an error.
+fn checked_add(a: i32, b: i32) -> DBResult<i32> {
+    Ok(a.checked_add(b)
+        .ok_or_else(|| DBSingleError::OtherError("integer overflow in aggregate".into()))?)
+}
+
+/// `COUNT(*)` counts rows; `COUNT(expr)` counts non-null values.
+struct Count;
+
+impl Accumulator for Count {
+    type State = i32;
+
+    fn init(&self) -> i32 {
+        0
+    }
+
+    fn update(&self, state: &mut i32, args: &[Value]) -> DBResult<()> {
+        if args.is_empty() || !single_arg("COUNT", args)?.is_null() {
+            *state += 1;
+        }
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut i32, other: i32) -> DBResult<()> {
+        *state += other;
+        Ok(())
+    }
+
+    fn finalize(&self, state: i32) -> DBResult<Value> {
+        Ok(Value::from_int(state))
+    }
+
+    fn evaluate(&self, state: &i32) -> Option<DBResult<Value>> {
+        Some(Accumulator::finalize(self, *state))
+    }
+}
+
+/// `SUM(expr)` sums non-null integers; NULL if there are none.
+struct Sum;
+
+impl Accumulator for Sum {
+    type State = Option<i32>;
+
+    fn init(&self) -> Option<i32> {
+        None
+    }
+
+    fn update(&self, state: &mut Option<i32>, args: &[Value]) -> DBResult<()> {
+        if let Some(x) = single_arg("SUM", args)?.try_to_int()? {
+            *state = Some(checked_add(state.unwrap_or(0), x)?);
+        }
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut Option<i32>, other: Option<i32>) -> DBResult<()> {
+        if let Some(x) = other {
+            *state = Some(checked_add(state.unwrap_or(0), x)?);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, state: Option<i32>) -> DBResult<Value> {
+        Ok(state.map_or_else(Value::from_null, Value::from_int))
+    }
+
+    fn evaluate(&self, state: &Option<i32>) -> Option<DBResult<Value>> {
+        Some(Accumulator::finalize(self, *state))
+    }
+}
+
+/// `AVG(expr)` averages non-null integers with integer division; NULL if there are none.
+struct Avg;
+
+impl Accumulator for Avg {
+    type State = (i64, i64);
+
+    fn init(&self) -> (i64, i64) {
+        (0, 0)
+    }
+
+    fn update(&self, state: &mut (i64, i64), args: &[Value]) -> DBResult<()> {
+        if let Some(x) = single_arg("AVG", args)?.try_to_int()? {
+            state.0 += x as i64;
+            state.1 += 1;
+        }
+        Ok(())
+    }
+
+    fn merge(&self, state: &mut (i64, i64), other: (i64, i64)) -> DBResult<()> {
+        state.0 += other.0;
+        state.1 += other.1;
+        Ok(())
+    }
+
+    fn finalize(&self, (sum, count): (i64, i64)) -> DBResult<Value> {
+        Ok(match count {
+            0 => Value::from_null(),
+            _ => Value::from_int((sum / count) as i32),
+        })
+    }
+
+    fn evaluate(&self, state: &(i64, i64)) -> Option<DBResult<Value>> {
+        Some(Accumulator::finalize(self, *state))
+    }
+}
+
+/// `MIN(expr)` / `MAX(expr)` over non-null values.
+struct Extremum {
+    /// Name used in error messages
+    name: &'static str,
+    /// The ordering a new value must have against the current one to replace it
+    replace_on: std::cmp::Ordering,
+}
+
+impl Extremum {
+    fn combine(&self, state: &mut Value, value: Value) -> DBResult<()> {
+        if value.is_null() {
+            return Ok(());
+        }
+        if state.is_null() {
+            *state = value;
+            return Ok(());
+        }
+        let ord = value.partial_cmp(state).ok_or_else(|| {
+            DBSingleError::OtherError(format!("invalid value type for {}: {:?}", self.name, value))
+        })?;
+        if ord == self.replace_on {
+            *state = value;
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for Extremum {
+    type State = Value;
+
+    fn init(&self) -> Value {
+        Value::from_null()
+    }
+
+    fn update(&self, state: &mut Value, args: &[Value]) -> DBResult<()> {
+        self.combine(state, single_arg(self.name, args)?.clone())
+    }
+
+    fn merge(&self, state: &mut Value, other: Value) -> DBResult<()> {
+        self.combine(state, other)
+    }
+
+    fn finalize(&self, state: Value) -> DBResult<Value> {
+        Ok(state)
+    }
+
+    fn evaluate(&self, state: &Value) -> Option<DBResult<Value>> {
+        Some(Ok(state.clone()))
+    }
+}
+
+/// Registry of aggregate functions, keyed by upper-case name.
+#[derive(Clone)]
+pub struct AggregateRegistry {
+    functions: HashMap<String, Arc<dyn DynAccumulator>>,
+}
+
+impl Default for AggregateRegistry {
+    fn default() -> Self {
+        let mut registry = AggregateRegistry {
+            functions: HashMap::new(),
+        };
+        registry.register("COUNT", Count);
+        registry.register("SUM", Sum);
+        registry.register("AVG", Avg);
+        registry.register(
+            "MIN",
+            Extremum {
+                name: "MIN",
+                replace_on: std::cmp::Ordering::Less,
+            },
+        );
+        registry.register(
+            "MAX",
+            Extremum {
+                name: "MAX",
+                replace_on: std::cmp::Ordering::Greater,
+            },
+        );
+        registry
+    }
+}
+
+impl AggregateRegistry {
+    /// Registers an aggregate, replacing any aggregate with the same name.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the aggregate (case-insensitive)
+    /// * `accumulator` - Accumulator implementing the aggregate
+    pub fn register<A: Accumulator>(&mut self, name: &str, accumulator: A) {
+        self.functions
+            .insert(name.to_uppercase(), Arc::new(accumulator));
+    }
+
+    /// Looks up an aggregate by its (case-insensitive) name.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn DynAccumulator>> {
+        self.functions.get(&name.to_uppercase())
+    }
+
+    /// Collects the aggregate calls in an expression.
+    ///
+    /// Calls nested inside an aggregate's arguments are not collected; they
+    /// are rejected when the arguments are evaluated. Window function calls
+    /// are not aggregate calls, but their arguments and window definitions
+    /// are searched.
+    ///
+    /// # Arguments
+    /// * `expr` - Expression to search
+    /// * `resolution` - How identifiers in the arguments of the calls are resolved
+    /// * `calls` - Collected calls; duplicates are skipped
+    pub fn collect_calls<'a>(
+        &self,
+        expr: &'a ast::Expr,
+        resolution: NameResolution,
+        calls: &mut Vec<AggregateCall<'a>>,
+    ) -> DBResult<()> {
+        use ast::Expr;
+        match expr {
+            Expr::Function(function) if function.over.is_some() => {
+                if let ast::FunctionArguments::List(list) = &function.args {
+                    for arg in &list.args {
+                        if let ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg)) = arg {
+                            self.collect_calls(arg, resolution, calls)?;
+                        }
+                    }
+                }
+                if let Some(ast::WindowType::WindowSpec(spec)) = &function.over {
+                    for expr in &spec.partition_by {
+                        self.collect_calls(expr, resolution, calls)?;
+                    }
+                    for order_by_expr in &spec.order_by {
+                        self.collect_calls(&order_by_expr.expr, resolution, calls)?;
+                    }
+                }
+            }
+            Expr::Function(function) => {
+                let Some(func) = self.get(&function.name.to_string()) else {
+                    return Ok(());
+                };
+                if calls.iter().any(|call| call.expr == expr) {
+                    return Ok(());
+                }
+                calls.push(AggregateCall::new(
+                    expr,
+                    function,
+                    func.clone(),
+                    resolution,
+                )?);
+            }
+            Expr::Nested(expr)
+            | Expr::UnaryOp { expr, .. }
+            | Expr::IsFalse(expr)
+            | Expr::IsTrue(expr)
+            | Expr::IsNotTrue(expr)
+            | Expr::IsNotFalse(expr)
+            | Expr::IsNull(expr)
+            | Expr::IsNotNull(expr) => self.collect_calls(expr, resolution, calls)?,
+            Expr::BinaryOp { left, right, .. } => {
+                self.collect_calls(left, resolution, calls)?;
+                self.collect_calls(right, resolution, calls)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// An aggregate call found in a query, e.g. `SUM(price * qty)`.
+pub struct AggregateCall<'a> {
+    /// The whole call expression; its text names the result column
+    pub expr: &'a ast::Expr,
+    /// The aggregate implementation
+    pub func: Arc<dyn DynAccumulator>,
+    /// Argument expressions; empty for `*`
+    pub args: Vec<&'a ast::Expr>,
+    /// How identifiers in the arguments are resolved
+    pub resolution: NameResolution,
+}
+
+impl<'a> AggregateCall<'a> {
+    /// Creates an aggregate call from a parsed function call.
+    fn new(
+        expr: &'a ast::Expr,
+        function: &'a ast::Function,
+        func: Arc<dyn DynAccumulator>,
+        resolution: NameResolution,
+    ) -> DBResult<Self> {
+        let unsupported =
+            || DBSingleError::UnsupportedOPError(format!("unsupported aggregate call {}", expr));
+        if function.filter.is_some() || function.over.is_some() {
+            Err(unsupported())?
+        }
+        let mut args = vec![];
+        match &function.args {
+            ast::FunctionArguments::None => {}
+            ast::FunctionArguments::List(list) => {
+                if list.duplicate_treatment == Some(ast::DuplicateTreatment::Distinct)
+                    || !list.clauses.is_empty()
+                {
+                    Err(unsupported())?
+                }
+                for arg in &list.args {
+                    match arg {
+                        ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg)) => {
+                            args.push(arg)
+                        }
+                        ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Wildcard)
+                            if list.args.len() == 1 => {}
+                        _ => Err(unsupported())?,
+                    }
+                }
+            }
+            ast::FunctionArguments::Subquery(_) => Err(unsupported())?,
+        }
+        Ok(AggregateCall {
+            expr,
+            func,
+            args,
+            resolution,
+        })
+    }
+
+    /// Gets the name of the result column of this call.
+    pub fn column_name(&self) -> String {
+        self.expr.to_string()
+    }
+
+    /// Compiles the arguments of this call against the columns of the
+    /// source table.
+    ///
+    /// # Arguments
+    /// * `table` - Source table
+    pub fn compile_args(&self, table: &Table) -> DBResult<Vec<CompiledExpr>> {
+        self.args
+            .iter()
+            .map(|arg| plan::compile(arg, table, self.resolution))
+            .collect()
+    }
+
+    /// Updates a state with a row of the source table.
+    ///
+    /// # Arguments
+    /// * `args` - Arguments of this call, as compiled by [`compile_args`](Self::compile_args)
+    /// * `row` - Row to aggregate
+    /// * `state` - State of the group the row belongs to
+    pub fn update(
+        &self,
+        args: &[CompiledExpr],
+        row: &[Value],
+        state: &mut AggregateState,
+    ) -> DBResult<()> {
+        let args = args
+            .iter()
+            .map(|arg| arg.eval(row))
+            .collect::<DBResult<Vec<_>>>()?;
+        self.func.update(state, &args)
+    }
+}
+
+/// A group of rows being aggregated.
+pub struct AggregateGroup {
+    /// Index of the first row of the group in the source table
+    pub first_row_idx: usize,
+    /// The first row of the group, representing the group in the result
+    pub row: Vec<Value>,
+    /// One state per aggregate call
+    pub states: Vec<AggregateState>,
+}
+
+impl AggregateGroup {
+    /// Creates a group starting from the given row.
+    pub fn new(first_row_idx: usize, row: Vec<Value>, calls: &[AggregateCall]) -> Self {
+        AggregateGroup {
+            first_row_idx,
+            row,
+            states: calls.iter().map(|call| call.func.init()).collect(),
+        }
+    }
+
+    /// Merges another partial group with the same key into this one.
+    pub fn merge(&mut self, other: AggregateGroup, calls: &[AggregateCall]) -> DBResult<()> {
+        if other.first_row_idx < self.first_row_idx {
+            self.first_row_idx = other.first_row_idx;
+            self.row = other.row;
+        }
+        for ((call, state), other_state) in calls.iter().zip(&mut self.states).zip(other.states) {
+            call.func.merge(state, other_state)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the result table of an aggregation.
+///
+/// The result has the columns of the source table, holding the first row of
+/// each group, followed by one column per aggregate call named after the call.
+/// Groups are emitted in the order of their first row. Without `GROUP BY`
+/// keys, an empty input still produces one group of NULLs.
+///
+/// # Arguments
+/// * `table` - Source table
+/// * `calls` - Aggregate calls
+/// * `groups` - Aggregated groups
+/// * `has_group_keys` - Whether the query has `GROUP BY` keys
+pub fn construct_aggregate_table(
+    table: &Table,
+    calls: &[AggregateCall],
+    mut groups: Vec<AggregateGroup>,
+    has_group_keys: bool,
+) -> DBResult<Table> {
+    let mut columns_info = table.columns_info.clone();
+    for column in columns_info.iter_mut() {
+        column.unique = false;
+        column.nullable = true;
+    }
+    for call in calls {
+        columns_info.push(ColumnInfo {
+            name: call.column_name(),
+            nullable: true,
+            unique: false,
+            type_specific: ColumnTypeSpecific::Any,
+        });
+    }
+
+    if groups.is_empty() && !has_group_keys {
+        groups.push(AggregateGroup::new(
+            0,
+            vec![Value::from_null(); table.get_column_num()],
+            calls,
+        ));
+    }
+    groups.sort_by_key(|group| group.first_row_idx);
+
+    let mut new_table = Table::new(columns_info);
+    for (idx, group) in groups.into_iter().enumerate() {
+        let mut row = group.row;
+        for (call, state) in calls.iter().zip(group.states) {
+            row.push(call.func.finalize(state)?);
+        }
+        new_table.rows.insert(idx, Some(row));
+    }
+    new_table.row_idx_acc = new_table.rows.len();
+    new_table.row_num = new_table.rows.len();
+    Ok(new_table)
+}
@@ -0,0 +1,355 @@
+//! ALTER TABLE statement execution.
+//!
+//! Handles adding, dropping, renaming and altering columns, and renaming
+//! tables.
+
+use super::SQLExecutor;
+use super::create_table::extract_column_info;
+use crate::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table, Value, ValueNotNull};
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::core::plan::{self, NameResolution};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::collections::HashSet;
+
+/// Gets the index of a column, failing if it does not exist.
+fn get_column_index(table: &Table, column_name: &ast::Ident) -> DBResult<usize> {
+    Ok(table
+        .get_column_index(&normalize_ident(column_name))
+        .ok_or_else(|| DBSingleError::OtherError(format!("column not found: {}", column_name)))?)
+}
+
+/// Converts a value to a column type.
+///
+/// Integers convert to strings, and strings holding an integer to integers.
+///
+/// # Arguments
+/// * `value` - Value to convert
+/// * `type_specific` - Type of the column
+/// * `column_name` - Name of the column, for error messages
+fn convert_value(
+    value: Value,
+    type_specific: &ColumnTypeSpecific,
+    column_name: &str,
+) -> DBResult<Value> {
+    let invalid = || {
+        DBSingleError::OtherError(format!(
+            "invalid value '{}' for column {} of type {}",
+            value.to_string(),
+            column_name,
+            type_specific
+        ))
+    };
+    let converted = match (type_specific, &value.0) {
+        (ColumnTypeSpecific::Int { .. }, Some(ValueNotNull::Varchar(s))) => {
+            Value::from_int(s.parse().map_err(|_| invalid())?)
+        }
+        (ColumnTypeSpecific::Varchar { .. }, Some(ValueNotNull::Int(i))) => {
+            Value::from_varchar(i.to_string())
+        }
+        _ => value.clone(),
+    };
+    if let (ColumnTypeSpecific::Varchar { max_length }, Some(ValueNotNull::Varchar(s))) =
+        (type_specific, &converted.0)
+        && s.chars().count() as u64 > *max_length
+    {
+        Err(invalid())?
+    }
+    Ok(converted)
+}
+
+/// Checks the constraints of a column against all rows, and rebuilds the
+/// tracked unique values of the column.
+///
+/// # Arguments
+/// * `table` - Table containing the column
+/// * `column_index` - Index of the column
+fn validate_column(table: &mut Table, column_index: usize) -> DBResult<()> {
+    let column_info = &table.columns_info[column_index];
+    let mut column_values = HashSet::new();
+    for row in table.existed_rows() {
+        let value = &row[column_index];
+        if !column_info.nullable && value.is_null() {
+            Err(DBSingleError::RequiredError(format!(
+                "column {} contains null values",
+                column_info.name
+            )))?
+        }
+        if column_info.unique && !column_values.insert(value.clone()) {
+            Err(DBSingleError::RequiredError(format!(
+                "Duplicate entry '{}' for key 'PRIMARY'",
+                value.to_string(),
+            )))?
+        }
+    }
+    table.columns_values[column_index] = column_values;
+    Ok(())
+}
+
+/// Adds a column to a table, holding its default value (or NULL) in the
+/// existing rows.
+///
+/// # Arguments
+/// * `table` - Table to add the column to
+/// * `column_def` - Definition of the column
+/// * `resolution` - How identifiers in the default value are resolved
+fn add_column(
+    table: &mut Table,
+    column_def: &ast::ColumnDef,
+    resolution: NameResolution,
+) -> DBResult<()> {
+    let name = normalize_ident(&column_def.name);
+    let mut default = None;
+    let mut options = vec![];
+    for opt in &column_def.options {
+        match &opt.option {
+            ast::ColumnOption::Default(expr) => default = Some(expr),
+            _ => options.push(opt.clone()),
+        }
+    }
+    let (nullable, unique) = extract_column_info(&options)?;
+    let type_specific = ColumnTypeSpecific::from_column_def(column_def)?;
+    let value = match default {
+        Some(expr) => convert_value(
+            Table::get_dummy().calc_expr_for_row(&[], expr, resolution)?,
+            &type_specific,
+            &name,
+        )?,
+        None => Value::from_null(),
+    };
+
+    table.add_column(
+        ColumnInfo {
+            name,
+            nullable,
+            unique,
+            type_specific,
+        },
+        value,
+    );
+    validate_column(table, table.get_column_num() - 1)
+}
+
+/// Changes the type of a column, converting the values of the existing rows.
+///
+/// # Arguments
+/// * `table` - Table containing the column
+/// * `column_index` - Index of the column
+/// * `data_type` - New type of the column
+/// * `using` - Optional expression computing the new values from the rows
+/// * `resolution` - How identifiers in `using` are resolved
+fn set_column_type(
+    table: &mut Table,
+    column_index: usize,
+    data_type: &ast::DataType,
+    using: Option<&ast::Expr>,
+    resolution: NameResolution,
+) -> DBResult<()> {
+    let type_specific = ColumnTypeSpecific::from_data_type(data_type)?;
+    let column_name = table.columns_info[column_index].name.clone();
+    let using = using
+        .map(|expr| plan::compile(expr, table, resolution))
+        .transpose()?;
+    let values = table
+        .existed_rows()
+        .map(|row| {
+            let value = match &using {
+                Some(expr) => expr.eval(row)?,
+                None => row[column_index].clone(),
+            };
+            convert_value(value, &type_specific, &column_name)
+        })
+        .collect::<DBResult<Vec<_>>>()?;
+    for (row, value) in table.existed_rows_mut().zip(values) {
+        row[column_index] = value;
+    }
+    table.columns_info[column_index].type_specific = type_specific;
+    table.modification_count += 1;
+    validate_column(table, column_index)
+}
+
+impl SQLExecutor {
+    /// Checks that no view depends on a table whose columns or name change.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the altered table
+    /// * `action` - The change, for error messages
+    fn check_no_dependent_view(&self, table_name: &str, action: &str) -> DBResult<()> {
+        if let Some(view_name) = self.database.get_dependent_views(table_name).first() {
+            Err(DBSingleError::OtherError(format!(
+                "cannot {} because view {} depends on it",
+                action, view_name
+            )))?
+        }
+        Ok(())
+    }
+
+    /// Executes an ALTER TABLE statement.
+    ///
+    /// The operations are applied in order to a copy of the table, which
+    /// replaces the table only if all of them succeed.
+    ///
+    /// # Arguments
+    /// * `alter_statement` - Parsed ALTER TABLE statement
+    pub(super) fn execute_alter_table(&mut self, alter_statement: &ast::Statement) -> DBResult<()> {
+        let ast::Statement::AlterTable {
+            name,
+            if_exists,
+            operations,
+            ..
+        } = alter_statement
+        else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+        let table_name = normalize_object_name(name);
+        let Some(table) = self.database.get_table(&table_name) else {
+            if *if_exists {
+                return Ok(());
+            }
+            Err(DBSingleError::OtherError(format!(
+                "table not found: {}",
+                table_name
+            )))?
+        };
+
+        let mut table = table.clone();
+        let mut new_name = None;
+        for operation in operations {
+            use ast::AlterTableOperation::*;
+            match operation {
+                AddColumn {
+                    if_not_exists,
+                    column_def,
+                    column_position,
+                    ..
+                } => {
+                    if table
+                        .get_column_index(&normalize_ident(&column_def.name))
+                        .is_some()
+                    {
+                        if *if_not_exists {
+                            continue;
+                        }
+                        Err(DBSingleError::OtherError(format!(
+                            "column {} already exists",
+                            column_def.name
+                        )))?
+                    }
+                    if column_position.is_some() {
+                        Err(DBSingleError::UnsupportedOPError(
+                            "column position is not supported".into(),
+                        ))?
+                    }
+                    add_column(&mut table, column_def, self.name_resolution())?;
+                }
+                DropColumn {
+                    column_name,
+                    if_exists,
+                    ..
+                } => {
+                    let Some(column_index) = table.get_column_index(&normalize_ident(column_name))
+                    else {
+                        if *if_exists {
+                            continue;
+                        }
+                        Err(DBSingleError::OtherError(format!(
+                            "column not found: {}",
+                            column_name
+                        )))?
+                    };
+                    if table.get_column_num() == 1 {
+                        Err(DBSingleError::OtherError(format!(
+                            "cannot drop the only column of table {}",
+                            table_name
+                        )))?
+                    }
+                    self.check_no_dependent_view(
+                        &table_name,
+                        &format!("drop column {} of table {}", column_name, table_name),
+                    )?;
+                    table.remove_column(column_index);
+                }
+                RenameColumn {
+                    old_column_name,
+                    new_column_name,
+                } => {
+                    let column_index = get_column_index(&table, old_column_name)?;
+                    if table
+                        .get_column_index(&normalize_ident(new_column_name))
+                        .is_some()
+                    {
+                        Err(DBSingleError::OtherError(format!(
+                            "column {} already exists",
+                            new_column_name
+                        )))?
+                    }
+                    self.check_no_dependent_view(
+                        &table_name,
+                        &format!("rename column {} of table {}", old_column_name, table_name),
+                    )?;
+                    table.rename_column(column_index, normalize_ident(new_column_name));
+                }
+                RenameTable {
+                    table_name: new_table_name,
+                } => {
+                    let new_table_name = normalize_object_name(new_table_name);
+                    if self.database.contains_relation(&new_table_name) {
+                        Err(DBSingleError::OtherError(format!(
+                            "table name {} already exists",
+                            new_table_name
+                        )))?
+                    }
+                    self.check_no_dependent_view(
+                        &table_name,
+                        &format!("rename table {}", table_name),
+                    )?;
+                    new_name = Some(new_table_name);
+                }
+                AlterColumn { column_name, op } => {
+                    let column_index = get_column_index(&table, column_name)?;
+                    match op {
+                        ast::AlterColumnOperation::SetNotNull => {
+                            table.columns_info[column_index].nullable = false;
+                            validate_column(&mut table, column_index)?;
+                        }
+                        ast::AlterColumnOperation::DropNotNull => {
+                            table.columns_info[column_index].nullable = true;
+                        }
+                        ast::AlterColumnOperation::SetDataType { data_type, using } => {
+                            set_column_type(
+                                &mut table,
+                                column_index,
+                                data_type,
+                                using.as_ref(),
+                                self.name_resolution(),
+                            )?;
+                        }
+                        _ => Err(DBSingleError::UnsupportedOPError(format!(
+                            "unsupported alter column operation {}",
+                            op
+                        )))?,
+                    }
+                }
+                _ => Err(DBSingleError::UnsupportedOPError(format!(
+                    "unsupported alter table operation {}",
+                    operation
+                )))?,
+            }
+        }
+
+        match new_name {
+            Some(new_name) => {
+                self.database.tables.remove(&table_name);
+                for trigger in self.database.triggers.values_mut() {
+                    if trigger.table_name == table_name {
+                        trigger.table_name = new_name.clone();
+                    }
+                }
+                self.database.tables.insert(new_name, table);
+            }
+            None => *self.database.get_table_mut(&table_name).unwrap() = table,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,350 @@
+//! Catalog introspection: SHOW TABLES, SHOW CREATE, DESCRIBE and the
+//! information schema.
+//!
+//! The virtual tables `information_schema.tables` and
+//! `information_schema.columns` are generated from the catalog whenever
+//! they are read, and can be queried like any other table. Plain views are
+//! listed in `information_schema.tables` only, as their columns are known
+//! by evaluating them; `DESCRIBE` evaluates a view to list its columns.
+
+use super::cte::table_with_rows;
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table, Value};
+use crate::core::parser::normalize_object_name;
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+
+/// Creates a table of VARCHAR and INT columns holding the given rows.
+///
+/// # Arguments
+/// * `columns` - Name of each column, and whether it is an INT column
+/// * `rows` - Rows of the table
+fn catalog_table(columns: &[(&str, bool)], rows: Vec<Vec<Value>>) -> Table {
+    let columns_info = columns
+        .iter()
+        .map(|&(name, is_int)| ColumnInfo {
+            name: name.to_string(),
+            nullable: true,
+            unique: false,
+            type_specific: match is_int {
+                true => ColumnTypeSpecific::Int {
+                    display_width: None,
+                },
+                false => ColumnTypeSpecific::Varchar {
+                    max_length: u64::MAX,
+                },
+            },
+        })
+        .collect();
+    table_with_rows(&Table::new(columns_info), rows)
+}
+
+/// Formats a flag of the catalog as `YES` or `NO`.
+fn yes_no(flag: bool) -> Value {
+    Value::from_varchar(if flag { "YES" } else { "NO" }.into())
+}
+
+/// Gets the rows describing the columns of a table, as
+/// `[column_name, data_type, is_nullable, is_unique]`.
+///
+/// Columns without a declared type are described with the type inferred
+/// from their values.
+fn describe_columns(table: &Table) -> Vec<Vec<Value>> {
+    table
+        .columns_info
+        .iter()
+        .zip(table.infer_column_types())
+        .map(|(column, type_specific)| {
+            vec![
+                Value::from_varchar(column.name.clone()),
+                Value::from_varchar(type_specific.to_string()),
+                yes_no(column.nullable),
+                yes_no(column.unique),
+            ]
+        })
+        .collect()
+}
+
+impl SQLExecutor {
+    /// Gets the names of all tables, views and materialized views with
+    /// their kinds, sorted by name.
+    fn get_relations(&self) -> Vec<(&String, &'static str)> {
+        let mut relations = self
+            .database
+            .tables
+            .keys()
+            .map(|name| (name, "BASE TABLE"))
+            .chain(self.database.views.keys().map(|name| (name, "VIEW")))
+            .chain(
+                self.database
+                    .materialized_views
+                    .keys()
+                    .map(|name| (name, "MATERIALIZED VIEW")),
+            )
+            .collect::<Vec<_>>();
+        relations.sort();
+        relations
+    }
+
+    /// Generates a virtual table of the information schema.
+    ///
+    /// - `information_schema.tables` has the columns `table_name`,
+    ///   `table_type` and `row_count`, which is NULL for plain views.
+    /// - `information_schema.columns` has the columns `table_name`,
+    ///   `column_name`, `ordinal_position` (from 1), `data_type`,
+    ///   `is_nullable` and `is_unique`.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the relation, case-insensitive
+    ///
+    /// # Returns
+    /// The virtual table, or None if the name is not one of the information
+    /// schema
+    pub(super) fn get_information_schema_table(&self, name: &str) -> Option<Table> {
+        if name.eq_ignore_ascii_case("information_schema.tables") {
+            let rows = self
+                .get_relations()
+                .into_iter()
+                .map(|(name, table_type)| {
+                    let table = self.database.get_table(name).or_else(|| {
+                        self.database
+                            .get_materialized_view(name)
+                            .map(|materialized| &materialized.table)
+                    });
+                    vec![
+                        Value::from_varchar(name.clone()),
+                        Value::from_varchar(table_type.into()),
+                        table.map_or_else(Value::from_null, |table| {
+                            Value::from_int(table.get_row_num() as i32)
+                        }),
+                    ]
+                })
+                .collect();
+            return Some(catalog_table(
+                &[
+                    ("table_name", false),
+                    ("table_type", false),
+                    ("row_count", true),
+                ],
+                rows,
+            ));
+        }
+        if name.eq_ignore_ascii_case("information_schema.columns") {
+            let mut rows = vec![];
+            for (name, _) in self.get_relations() {
+                let table = match self.database.get_table(name) {
+                    Some(table) => table,
+                    None => match self.database.get_materialized_view(name) {
+                        Some(materialized) => &materialized.table,
+                        None => continue,
+                    },
+                };
+                for (i, mut row) in describe_columns(table).into_iter().enumerate() {
+                    row.insert(0, Value::from_varchar(name.clone()));
+                    row.insert(2, Value::from_int(i as i32 + 1));
+                    rows.push(row);
+                }
+            }
+            return Some(catalog_table(
+                &[
+                    ("table_name", false),
+                    ("column_name", false),
+                    ("ordinal_position", true),
+                    ("data_type", false),
+                    ("is_nullable", false),
+                    ("is_unique", false),
+                ],
+                rows,
+            ));
+        }
+        None
+    }
+
+    /// Executes a SHOW TABLES statement, listing the tables, views and
+    /// materialized views by name; `SHOW FULL TABLES` also outputs their
+    /// kinds.
+    ///
+    /// # Arguments
+    /// * `show_tables` - Parsed SHOW TABLES statement
+    /// * `executor_state` - Current executor state holding the output
+    pub(super) fn execute_show_tables(
+        &self,
+        show_tables: &ast::Statement,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        let ast::Statement::ShowTables {
+            full, show_options, ..
+        } = show_tables
+        else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+        if !show_options.to_string().is_empty() {
+            Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported SHOW TABLES options{}",
+                show_options
+            )))?
+        }
+
+        let rows = self
+            .get_relations()
+            .into_iter()
+            .map(|(name, table_type)| {
+                let mut row = vec![Value::from_varchar(name.clone())];
+                if *full {
+                    row.push(Value::from_varchar(table_type.into()));
+                }
+                row
+            })
+            .collect();
+        let columns: &[_] = match full {
+            true => &[("table_name", false), ("table_type", false)],
+            false => &[("table_name", false)],
+        };
+        Self::output_table(&catalog_table(columns, rows), executor_state)
+    }
+
+    /// Executes a SHOW CREATE TABLE or SHOW CREATE VIEW statement,
+    /// outputting a statement creating the table or view as it is now.
+    ///
+    /// # Arguments
+    /// * `show_create` - Parsed SHOW CREATE statement
+    /// * `executor_state` - Current executor state holding the output
+    pub(super) fn execute_show_create(
+        &self,
+        show_create: &ast::Statement,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        let ast::Statement::ShowCreate { obj_type, obj_name } = show_create else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+        let name = normalize_object_name(obj_name);
+        let statement = match obj_type {
+            ast::ShowCreateObject::Table => {
+                let table = self.database.get_table(&name).ok_or_else(|| {
+                    DBSingleError::OtherError(format!("table not found: {}", name))
+                })?;
+                let columns = table
+                    .columns_info
+                    .iter()
+                    .map(|column| {
+                        let mut definition = format!("{} {}", column.name, column.type_specific);
+                        if !column.nullable {
+                            definition.push_str(" NOT NULL");
+                        }
+                        if column.unique {
+                            definition.push_str(" UNIQUE");
+                        }
+                        definition
+                    })
+                    .collect::<Vec<_>>();
+                format!("CREATE TABLE {} ({})", name, columns.join(", "))
+            }
+            ast::ShowCreateObject::View => {
+                let (kind, view) = match (
+                    self.database.get_view(&name),
+                    self.database.get_materialized_view(&name),
+                ) {
+                    (Some(view), _) => ("VIEW", view),
+                    (None, Some(materialized)) => ("MATERIALIZED VIEW", &materialized.view),
+                    (None, None) => Err(DBSingleError::OtherError(format!(
+                        "view {} not found",
+                        name
+                    )))?,
+                };
+                let columns = match view.column_names.is_empty() {
+                    true => String::new(),
+                    false => format!(" ({})", view.column_names.join(", ")),
+                };
+                format!("CREATE {} {}{} AS {}", kind, name, columns, view.query)
+            }
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "SHOW CREATE {} is not supported",
+                obj_type
+            )))?,
+        };
+
+        let table = catalog_table(
+            &[("name", false), ("create_statement", false)],
+            vec![vec![
+                Value::from_varchar(name),
+                Value::from_varchar(statement),
+            ]],
+        );
+        Self::output_table(&table, executor_state)
+    }
+
+    /// Executes a DESCRIBE or SHOW COLUMNS statement, outputting the name,
+    /// type, nullability and uniqueness of each column of a table or view.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the table or view
+    /// * `executor_state` - Current executor state holding the output
+    pub(super) fn execute_describe(
+        &self,
+        name: &ast::ObjectName,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        let name = normalize_object_name(name);
+        let rows = match (
+            self.database.get_table(&name),
+            self.database.get_materialized_view(&name),
+            self.database.get_view(&name),
+        ) {
+            (Some(table), _, _) => describe_columns(table),
+            (None, Some(materialized), _) => describe_columns(&materialized.table),
+            (None, None, Some(view)) => {
+                describe_columns(&self.evaluate_view(&name, view, Default::default())?)
+            }
+            (None, None, None) => Err(DBSingleError::OtherError(format!(
+                "table not found: {}",
+                name
+            )))?,
+        };
+        let table = catalog_table(
+            &[
+                ("column_name", false),
+                ("data_type", false),
+                ("is_nullable", false),
+                ("is_unique", false),
+            ],
+            rows,
+        );
+        Self::output_table(&table, executor_state)
+    }
+
+    /// Executes a SHOW COLUMNS FROM statement, like DESCRIBE.
+    ///
+    /// # Arguments
+    /// * `show_columns` - Parsed SHOW COLUMNS statement
+    /// * `executor_state` - Current executor state holding the output
+    pub(super) fn execute_show_columns(
+        &self,
+        show_columns: &ast::Statement,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        let ast::Statement::ShowColumns { show_options, .. } = show_columns else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+        match show_options {
+            ast::ShowStatementOptions {
+                show_in:
+                    Some(ast::ShowStatementIn {
+                        parent_type: None,
+                        parent_name: Some(name),
+                        ..
+                    }),
+                starts_with: None,
+                limit: None,
+                limit_from: None,
+                filter_position: None,
+            } => self.execute_describe(name, executor_state),
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported SHOW COLUMNS options{}",
+                show_options
+            )))?,
+        }
+    }
+}
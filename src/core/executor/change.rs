@@ -0,0 +1,133 @@
+//! Change data capture: hooks notified of the committed row changes.
+//!
+//! Hooks are registered with [`SQLExecutor::on_change`] and called once per
+//! changed row, in the order the rows were changed. A change is committed,
+//! and reported, once the statement changed the table and its BEFORE
+//! triggers succeeded; the changes made by the triggers themselves are
+//! reported as well. A statement failing to change a table leaves it as it
+//! was, and reports nothing.
+//!
+//! # Example
+//! ```
+//! use helidb::SQLExecConfig;
+//! use std::sync::{Arc, Mutex};
+//!
+//! let events = Arc::new(Mutex::new(vec![]));
+//! let mut executor = SQLExecConfig::new().connect().unwrap();
+//! let recorder = events.clone();
+//! executor.on_change(move |event| {
+//!     recorder.lock().unwrap().push(format!(
+//!         "{} {:?} {:?} {:?}",
+//!         event.table_name,
+//!         event.operation,
+//!         event.old_row.map(|row| row[0].to_string()),
+//!         event.new_row.map(|row| row[0].to_string()),
+//!     ));
+//! });
+//! executor
+//!     .execute_sql(
+//!         "CREATE TABLE t (x INT);
+//!          INSERT INTO t VALUES (1);
+//!          UPDATE t SET x = 2;
+//!          DELETE FROM t;",
+//!     )
+//!     .unwrap();
+//! assert_eq!(
+//!     *events.lock().unwrap(),
+//!     [
+//!         r#"t Insert None Some("1")"#,
+//!         r#"t Update Some("1") Some("2")"#,
+//!         r#"t Delete Some("2") None"#,
+//!     ]
+//! );
+//! ```
+
+use super::SQLExecutor;
+use super::table_manager::RowChange;
+use crate::core::data_structure::Value;
+
+/// The kind of a committed change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeOperation {
+    /// A row was inserted
+    Insert,
+    /// A row was updated
+    Update,
+    /// A row was deleted
+    Delete,
+    /// All the rows of the table were removed at once, without reporting
+    /// them one by one
+    Truncate,
+}
+
+/// A committed change to a table, as passed to the change hooks.
+#[derive(Debug, Clone, Copy)]
+pub struct ChangeEvent<'a> {
+    /// Name of the changed table
+    pub table_name: &'a str,
+    /// The kind of change
+    pub operation: ChangeOperation,
+    /// The row before the change, None for an insertion or a truncation
+    pub old_row: Option<&'a [Value]>,
+    /// The row after the change, None for a deletion or a truncation
+    pub new_row: Option<&'a [Value]>,
+}
+
+/// A function called for each committed change.
+pub(super) type ChangeHook = Box<dyn FnMut(&ChangeEvent) + Send + Sync>;
+
+impl SQLExecutor {
+    /// Reports the committed changes of a table to the change hooks.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the changed table
+    /// * `changes` - The changes made to the table, in order
+    pub(super) fn notify_changes(&mut self, table_name: &str, changes: &[RowChange]) {
+        if self.change_hooks.is_empty() {
+            return;
+        }
+        for change in changes {
+            let event = match change {
+                RowChange::Insert { new_row, .. } => ChangeEvent {
+                    table_name,
+                    operation: ChangeOperation::Insert,
+                    old_row: None,
+                    new_row: Some(new_row),
+                },
+                RowChange::Update {
+                    old_row, new_row, ..
+                } => ChangeEvent {
+                    table_name,
+                    operation: ChangeOperation::Update,
+                    old_row: Some(old_row),
+                    new_row: Some(new_row),
+                },
+                RowChange::Delete { old_row, .. } => ChangeEvent {
+                    table_name,
+                    operation: ChangeOperation::Delete,
+                    old_row: Some(old_row),
+                    new_row: None,
+                },
+            };
+            for hook in &mut self.change_hooks {
+                hook(&event);
+            }
+        }
+    }
+
+    /// Reports the truncation of a table to the change hooks.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the truncated table
+    pub(super) fn notify_truncate(&mut self, table_name: &str) {
+        let event = ChangeEvent {
+            table_name,
+            operation: ChangeOperation::Truncate,
+            old_row: None,
+            new_row: None,
+        };
+        for hook in &mut self.change_hooks {
+            hook(&event);
+        }
+    }
+}
@@ -2,8 +2,9 @@
 //!
 //! Handles parsing and execution of CREATE TABLE statements.
 
-use super::SQLExecutor;
+use super::{SQLExecutor, SQLExecutorState};
 use crate::core::data_structure::{ColumnInfo, ColumnTypeSpecific};
+use crate::core::parser::{normalize_ident, normalize_object_name};
 use crate::error::{DBResult, DBSingleError};
 use sqlparser::ast;
 
@@ -14,7 +15,7 @@ use sqlparser::ast;
 ///
 /// # Returns
 /// Tuple of `(nullable, unique)` flags
-fn extract_column_info(opts: &[ast::ColumnOptionDef]) -> DBResult<(bool, bool)> {
+pub(super) fn extract_column_info(opts: &[ast::ColumnOptionDef]) -> DBResult<(bool, bool)> {
     let mut nullable = true;
     let mut unique = false;
     for opt in opts {
@@ -41,12 +42,25 @@ fn extract_column_info(opts: &[ast::ColumnOptionDef]) -> DBResult<(bool, bool)>
 impl SQLExecutor {
     /// Executes a CREATE TABLE statement.
     ///
+    /// With `IF NOT EXISTS`, nothing is done if the table already exists.
+    /// With `AS <query>`, the table is filled with the query result. Columns
+    /// not declared take their names from the query, and their types from the
+    /// columns they read as is, or else are inferred from the query result.
+    ///
     /// # Arguments
     /// * `create_table` - Parsed CREATE TABLE statement
-    pub(super) fn execute_create_table(&mut self, create_table: &ast::CreateTable) -> DBResult<()> {
-        let table_name = create_table.name.to_string();
+    /// * `executor_state` - Current executor state for evaluation context
+    pub(super) fn execute_create_table(
+        &mut self,
+        create_table: &ast::CreateTable,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<()> {
+        let table_name = normalize_object_name(&create_table.name);
 
-        if self.database.tables.contains_key(&table_name) {
+        if self.database.contains_relation(&table_name) {
+            if create_table.if_not_exists {
+                return Ok(());
+            }
             Err(DBSingleError::OtherError(format!(
                 "table name {} already exists",
                 table_name
@@ -55,7 +69,7 @@ impl SQLExecutor {
 
         let mut column_info = vec![];
         for col in &create_table.columns {
-            let name = col.name.to_string();
+            let name = normalize_ident(&col.name);
             let type_specific = ColumnTypeSpecific::from_column_def(col)?;
             let (nullable, unique) = extract_column_info(&col.options)?;
             column_info.push(ColumnInfo {
@@ -66,7 +80,44 @@ impl SQLExecutor {
             });
         }
 
-        self.database.create_table(table_name, column_info);
+        let Some(query) = &create_table.query else {
+            self.database.create_table(table_name, column_info);
+            return Ok(());
+        };
+
+        let result = self.evaluate_query(query, executor_state)?;
+        if column_info.len() > result.get_column_num() {
+            Err(DBSingleError::OtherError(format!(
+                "CREATE TABLE AS specifies {} columns but the query has {} columns",
+                column_info.len(),
+                result.get_column_num()
+            )))?
+        }
+        let inferred_types = result.infer_column_types();
+        for (i, type_specific) in inferred_types
+            .into_iter()
+            .enumerate()
+            .skip(column_info.len())
+        {
+            column_info.push(ColumnInfo {
+                name: result.get_column_info(i).name.clone(),
+                nullable: true,
+                unique: false,
+                type_specific,
+            });
+        }
+
+        self.database.create_table(table_name.clone(), column_info);
+        let table = self
+            .database
+            .get_table_mut(&table_name)
+            .expect("table should have been created");
+        let rows = result.rows.into_values().flatten().collect();
+        if let Err(err) = self.table_manager.insert_values(table, rows) {
+            // the statement fails as a whole
+            self.database.drop_table(&table_name)?;
+            return Err(err);
+        }
         Ok(())
     }
 }
@@ -0,0 +1,259 @@
+//! Common table expression evaluation.
+//!
+//! Handles WITH clauses, including WITH RECURSIVE evaluated by fixpoint
+//! iteration.
+
+use super::explain::Operator;
+use super::utils::{expr_children_mut, select_exprs_mut};
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{ColumnTypeSpecific, Table, Value};
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Collects the names of the relations a query reads from, in its FROM
+/// clauses, its subqueries and its WITH clause.
+///
+/// # Arguments
+/// * `query` - Query to collect from
+/// * `names` - Set the names are added to
+pub(super) fn collect_relations(query: &mut ast::Query, names: &mut HashSet<String>) {
+    if let Some(with) = &mut query.with {
+        for cte in with.cte_tables.iter_mut() {
+            collect_relations(&mut cte.query, names);
+        }
+    }
+    collect_body_relations(&mut query.body, names);
+}
+
+/// Same as [`collect_relations`], for a query body.
+fn collect_body_relations(body: &mut ast::SetExpr, names: &mut HashSet<String>) {
+    match body {
+        ast::SetExpr::Select(select) => {
+            for table in select.from.iter_mut() {
+                for relation in std::iter::once(&mut table.relation)
+                    .chain(table.joins.iter_mut().map(|join| &mut join.relation))
+                {
+                    match relation {
+                        ast::TableFactor::Table { name, .. } => {
+                            names.insert(normalize_object_name(name));
+                        }
+                        ast::TableFactor::Derived { subquery, .. } => {
+                            collect_relations(subquery, names)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            for expr in select_exprs_mut(select) {
+                collect_expr_relations(expr, names);
+            }
+        }
+        ast::SetExpr::Query(query) => collect_relations(query, names),
+        ast::SetExpr::SetOperation { left, right, .. } => {
+            collect_body_relations(left, names);
+            collect_body_relations(right, names);
+        }
+        _ => {}
+    }
+}
+
+/// Same as [`collect_relations`], for the subqueries of an expression.
+fn collect_expr_relations(expr: &mut ast::Expr, names: &mut HashSet<String>) {
+    match expr {
+        ast::Expr::Subquery(query)
+        | ast::Expr::Exists {
+            subquery: query, ..
+        } => collect_relations(query, names),
+        ast::Expr::InSubquery { subquery, .. } => collect_body_relations(subquery, names),
+        _ => {}
+    }
+    for child in expr_children_mut(expr) {
+        collect_expr_relations(child, names);
+    }
+}
+
+/// Checks whether a query body reads from a relation.
+///
+/// # Arguments
+/// * `body` - Query body to check
+/// * `name` - Name of the relation
+fn references_relation(body: &mut ast::SetExpr, name: &str) -> bool {
+    let mut names = HashSet::new();
+    collect_body_relations(body, &mut names);
+    names.contains(name)
+}
+
+/// Renames the columns of a CTE result after the column aliases of the CTE.
+///
+/// # Arguments
+/// * `table` - Result of the CTE query
+/// * `alias` - Name and column aliases of the CTE
+fn rename_columns(table: &mut Table, alias: &ast::TableAlias) -> DBResult<()> {
+    if alias.columns.len() > table.get_column_num() {
+        Err(DBSingleError::OtherError(format!(
+            "table {} has {} columns available but {} columns specified",
+            alias.name,
+            table.get_column_num(),
+            alias.columns.len()
+        )))?
+    }
+    for (i, column) in alias.columns.iter().enumerate() {
+        table.rename_column(i, normalize_ident(&column.name));
+    }
+    Ok(())
+}
+
+/// Creates a table with the columns of `template` holding the given rows.
+pub(super) fn table_with_rows(template: &Table, rows: Vec<Vec<Value>>) -> Table {
+    let mut table = Table::new(template.columns_info.clone());
+    table.row_num = rows.len();
+    table.row_idx_acc = rows.len();
+    table.rows = rows.into_iter().map(Some).enumerate().collect();
+    table
+}
+
+impl SQLExecutor {
+    /// Evaluates the relations of a WITH clause.
+    ///
+    /// Each relation is visible to the relations following it, and to itself
+    /// for a recursive one.
+    ///
+    /// # Arguments
+    /// * `with` - Parsed WITH clause
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// A copy of the executor state where the relations are visible
+    pub(super) fn evaluate_with<'a>(
+        &self,
+        with: &ast::With,
+        executor_state: &SQLExecutorState<'a>,
+    ) -> DBResult<SQLExecutorState<'a>> {
+        let mut new_state = SQLExecutorState {
+            sql_statements: executor_state.sql_statements,
+            ctes: executor_state.ctes.clone(),
+            trigger_rows: executor_state.trigger_rows.clone(),
+            trigger_depth: executor_state.trigger_depth,
+            explain: executor_state.explain,
+            ..Default::default()
+        };
+        for cte in &with.cte_tables {
+            let name = normalize_ident(&cte.alias.name);
+            let evaluate_cte = || match cte.query.body.as_ref() {
+                ast::SetExpr::SetOperation {
+                    op: ast::SetOperator::Union,
+                    set_quantifier,
+                    left,
+                    right,
+                } if with.recursive && references_relation(&mut right.clone(), &name) => {
+                    if cte.query.order_by.is_some() || cte.query.limit_clause.is_some() {
+                        Err(DBSingleError::UnsupportedOPError(
+                            "ORDER BY or LIMIT in a recursive query is not supported".into(),
+                        ))?
+                    }
+                    self.evaluate_recursive_cte(&cte.alias, set_quantifier, left, right, &new_state)
+                }
+                _ => self.evaluate_query(&cte.query, &new_state),
+            };
+            let mut table = new_state.explain(false, evaluate_cte, |table| {
+                Operator::new(format!("CTE {}", name), table.get_row_num())
+            })?;
+            rename_columns(&mut table, &cte.alias)?;
+            new_state.ctes.insert(name, Arc::new(table));
+        }
+        Ok(new_state)
+    }
+
+    /// Evaluates a recursive CTE `<left> UNION [ALL] <right>`.
+    ///
+    /// The rows of `<left>` are produced first. `<right>` is then evaluated
+    /// repeatedly, with the CTE referring to the rows produced by the
+    /// previous iteration, until no new row is produced. Without ALL,
+    /// rows already produced are discarded.
+    ///
+    /// # Arguments
+    /// * `alias` - Name and column aliases of the CTE
+    /// * `set_quantifier` - ALL or DISTINCT
+    /// * `left` - The non-recursive term
+    /// * `right` - The recursive term
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// New table containing all the produced rows
+    fn evaluate_recursive_cte(
+        &self,
+        alias: &ast::TableAlias,
+        set_quantifier: &ast::SetQuantifier,
+        left: &ast::SetExpr,
+        right: &ast::SetExpr,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        let is_all = match set_quantifier {
+            ast::SetQuantifier::All => true,
+            ast::SetQuantifier::Distinct | ast::SetQuantifier::None => false,
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported set quantifier {}",
+                set_quantifier
+            )))?,
+        };
+
+        let mut template = self.evaluate_set_expr(left, executor_state)?;
+        rename_columns(&mut template, alias)?;
+        // the recursive term may produce any values; their types are
+        // inferred from them
+        for column in template.columns_info.iter_mut() {
+            column.nullable = true;
+            column.unique = false;
+            column.type_specific = ColumnTypeSpecific::Any;
+        }
+
+        let mut seen = HashSet::new();
+        let mut produce = |rows: Vec<Vec<Value>>| -> Vec<Vec<Value>> {
+            if is_all {
+                return rows;
+            }
+            rows.into_iter()
+                .filter(|row| seen.insert(row.clone()))
+                .collect()
+        };
+
+        let mut rows = produce(template.existed_rows().cloned().collect());
+        let mut working = rows.clone();
+        let mut depth = 0;
+        while !working.is_empty() {
+            if depth == self.config.max_recursion_depth {
+                Err(DBSingleError::OtherError(format!(
+                    "recursive query {} exceeded the maximum recursion depth {}",
+                    alias.name, self.config.max_recursion_depth
+                )))?
+            }
+            depth += 1;
+
+            let mut iteration_state = SQLExecutorState {
+                sql_statements: executor_state.sql_statements,
+                ctes: executor_state.ctes.clone(),
+                trigger_rows: executor_state.trigger_rows.clone(),
+                trigger_depth: executor_state.trigger_depth,
+                ..Default::default()
+            };
+            iteration_state.ctes.insert(
+                normalize_ident(&alias.name),
+                Arc::new(table_with_rows(&template, working)),
+            );
+            let new_rows = self.evaluate_set_expr(right, &iteration_state)?;
+            if new_rows.get_column_num() != template.get_column_num() {
+                Err(DBSingleError::OtherError(format!(
+                    "each UNION query must have the same number of columns: {} and {}",
+                    template.get_column_num(),
+                    new_rows.get_column_num()
+                )))?
+            }
+            working = produce(new_rows.rows.into_values().flatten().collect());
+            rows.extend(working.iter().cloned());
+        }
+        Ok(table_with_rows(&template, rows))
+    }
+}
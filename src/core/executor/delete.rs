@@ -2,23 +2,42 @@
 //!
 //! Handles parsing and execution of DELETE statements.
 
-use super::SQLExecutor;
+use super::explain::Operator;
+use super::query::SelectSource;
+use super::subquery::CorrelatedColumns;
+use super::table_manager::RowChange;
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::TriggerEvent;
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::core::plan;
 use crate::error::{DBResult, DBSingleError};
 use sqlparser::ast;
+use std::borrow::Cow;
 
 impl SQLExecutor {
-    /// Executes a DELETE statement.
+    /// Executes a DELETE statement, and outputs the deleted rows with
+    /// RETURNING.
     ///
     /// # Arguments
     /// * `delete` - Parsed DELETE statement
-    pub(super) fn execute_delete(&mut self, delete: &ast::Delete) -> DBResult<()> {
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// Number of deleted rows
+    pub(super) fn execute_delete(
+        &mut self,
+        delete: &ast::Delete,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<usize> {
         let tables = match &delete.from {
             ast::FromTable::WithFromKeyword(tables) => tables,
             ast::FromTable::WithoutKeyword(tables) => tables,
         };
+        let mut deleted_num = 0;
         for table in tables {
             let ast::TableFactor::Table {
                 name: ref table_name,
+                ref alias,
                 ..
             } = table.relation
             else {
@@ -26,14 +45,89 @@ impl SQLExecutor {
                     "only support table".into(),
                 ))?
             };
-            let table_name = table_name.to_string();
-            let table = self.database.get_table_mut(&table_name).ok_or_else(|| {
-                DBSingleError::OtherError(format!("table not found: {}", table_name))
-            })?;
-            self.table_manager
-                .delete_rows(table, delete.selection.as_ref())?;
+            let table_name = normalize_object_name(table_name);
+            let source_name = alias
+                .as_ref()
+                .map_or(table_name.clone(), |a| normalize_ident(&a.name));
+
+            let mut selection = delete.selection.clone();
+            let source = SelectSource {
+                table: Cow::Borrowed(self.database.get_table(&table_name).ok_or_else(|| {
+                    DBSingleError::OtherError(format!("table not found: {}", table_name))
+                })?),
+                name: Some(source_name.clone()),
+            };
+            let mut correlated = vec![];
+            let mut cond = None;
+            if let Some(selection) = &mut selection {
+                self.bind_expr(
+                    selection,
+                    &source,
+                    &mut Some(&mut correlated),
+                    executor_state,
+                )?;
+                cond = Some(plan::compile(
+                    selection,
+                    &CorrelatedColumns::header(&source.table, &correlated),
+                    self.name_resolution(),
+                )?);
+            }
+            let correlated_columns =
+                self.evaluate_correlated_columns(&source, &correlated, executor_state)?;
+
+            let changes = executor_state.explain(
+                true,
+                || {
+                    self.change_table(
+                        &table_name,
+                        &[TriggerEvent::Delete],
+                        executor_state,
+                        |this| {
+                            let table =
+                                this.database.get_table_mut(&table_name).ok_or_else(|| {
+                                    DBSingleError::OtherError(format!(
+                                        "table not found: {}",
+                                        table_name
+                                    ))
+                                })?;
+                            correlated_columns.change(table, |table| {
+                                Ok(this
+                                    .table_manager
+                                    .delete_rows(table, cond.as_ref())?
+                                    .into_iter()
+                                    .map(|(row_idx, old_row)| RowChange::Delete {
+                                        row_idx,
+                                        old_row,
+                                    })
+                                    .collect())
+                            })
+                        },
+                    )
+                },
+                |changes| {
+                    Operator::new(format!("Delete on {}", table_name), changes.len())
+                        .detail_opt("Filter", selection.as_ref())
+                },
+            )?;
+            deleted_num += changes.len();
+
+            if let Some(returning) = &delete.returning {
+                let table = self
+                    .database
+                    .get_table(&table_name)
+                    .expect("table should exist");
+                let rows = changes
+                    .into_iter()
+                    .filter_map(|change| match change {
+                        RowChange::Delete { old_row, .. } => Some(old_row),
+                        _ => None,
+                    })
+                    .collect();
+                self.output_returning(returning, table, source_name, rows, executor_state)?;
+            }
+            self.auto_vacuum(&table_name);
         }
 
-        Ok(())
+        Ok(deleted_num)
     }
 }
@@ -1,33 +1,91 @@
-//! DROP TABLE statement execution.
+//! DROP TABLE and DROP [MATERIALIZED] VIEW statement execution.
 //!
-//! Handles parsing and execution of DROP TABLE statements.
+//! Handles parsing and execution of DROP TABLE and DROP [MATERIALIZED] VIEW
+//! statements.
 
 use super::SQLExecutor;
+use crate::core::parser::normalize_object_name;
 use crate::error::{DBResult, DBSingleError};
 use sqlparser::ast;
 
 impl SQLExecutor {
-    /// Executes a DROP TABLE statement.
+    /// Executes a DROP TABLE or DROP [MATERIALIZED] VIEW statement.
+    ///
+    /// All the tables or views are checked to exist before any is dropped.
+    /// With `IF EXISTS`, missing ones are skipped. The views depending on
+    /// them are dropped as well with `CASCADE`, and make the statement fail
+    /// otherwise.
     ///
     /// # Arguments
     /// * `drop_statement` - Parsed DROP statement
     pub(super) fn execute_drop_table(&mut self, drop_statement: &ast::Statement) -> DBResult<()> {
         let ast::Statement::Drop {
-            object_type, names, ..
+            object_type,
+            if_exists,
+            names,
+            cascade,
+            ..
         } = drop_statement
         else {
             // This should never happen, as we have entered into this function
             panic!("Should not reach here");
         };
 
-        if *object_type != ast::ObjectType::Table {
-            Err(DBSingleError::OtherError(
-                "only table drop is supported".into(),
-            ))?;
-        }
+        let kind = match object_type {
+            ast::ObjectType::Table => "table",
+            ast::ObjectType::View => "view",
+            ast::ObjectType::MaterializedView => "materialized view",
+            _ => Err(DBSingleError::OtherError(
+                "only table and view drop is supported".into(),
+            ))?,
+        };
 
+        let mut dropped_names = vec![];
         for name in names {
-            self.database.drop_table(&name.to_string())?;
+            let name = normalize_object_name(name);
+            let exists = match object_type {
+                ast::ObjectType::Table => self.database.tables.contains_key(&name),
+                ast::ObjectType::View => self.database.views.contains_key(&name),
+                _ => self.database.materialized_views.contains_key(&name),
+            };
+            if !exists {
+                if *if_exists {
+                    continue;
+                }
+                Err(DBSingleError::OtherError(format!(
+                    "{} {} not found",
+                    kind, name
+                )))?
+            }
+            if !dropped_names.contains(&name) {
+                dropped_names.push(name);
+            }
+        }
+
+        let mut dependent_views = vec![];
+        for name in &dropped_names {
+            for view_name in self.database.get_dependent_views(name) {
+                if dropped_names.contains(&view_name) || dependent_views.contains(&view_name) {
+                    continue;
+                }
+                if !*cascade {
+                    Err(DBSingleError::OtherError(format!(
+                        "cannot drop {} {} because view {} depends on it",
+                        kind, name, view_name
+                    )))?
+                }
+                dependent_views.push(view_name);
+            }
+        }
+
+        for name in dropped_names {
+            match object_type {
+                ast::ObjectType::Table => self.database.drop_table(&name)?,
+                _ => self.database.drop_view(&name)?,
+            }
+        }
+        for view_name in dependent_views {
+            self.database.drop_view(&view_name)?;
         }
         Ok(())
     }
@@ -0,0 +1,324 @@
+//! EXPLAIN and EXPLAIN ANALYZE statement execution.
+//!
+//! The operators of a statement are recorded while it executes. The inputs
+//! of an operator are the operators run while it runs, and the operators
+//! run before it on the same level, e.g. the scan before a projection.
+//! Sources (scans, subqueries and CTEs) take no inputs from their level,
+//! and each SELECT and query starts a level of its own.
+//! Work repeated for each row, like correlated subqueries, is recorded as
+//! part of the operator repeating it.
+//!
+//! EXPLAIN executes the statement against an empty copy of the database,
+//! so that nothing is changed, and shows the operators. EXPLAIN ANALYZE
+//! executes the statement, discarding its output, and also shows the number
+//! of rows produced by each operator and the time spent in it and its
+//! inputs.
+
+use super::cte::table_with_rows;
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table, Value};
+use crate::core::parser::SQLStatement;
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// An operator of an explained statement.
+pub(super) struct Operator {
+    /// Name of the operator, e.g. `Scan users`
+    name: String,
+    /// Details of the operator, e.g. its filter
+    details: Vec<String>,
+    /// Number of rows produced by the operator
+    rows: usize,
+}
+
+impl Operator {
+    /// Creates an operator without details.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the operator
+    /// * `rows` - Number of rows produced by the operator
+    pub(super) fn new(name: impl Into<String>, rows: usize) -> Self {
+        Operator {
+            name: name.into(),
+            details: vec![],
+            rows,
+        }
+    }
+
+    /// Adds a detail to the operator.
+    ///
+    /// # Arguments
+    /// * `label` - Label of the detail, e.g. `Filter`
+    /// * `value` - Value of the detail
+    pub(super) fn detail(mut self, label: &str, value: impl Display) -> Self {
+        self.details.push(format!("{}: {}", label, value));
+        self
+    }
+
+    /// Adds a detail to the operator if it has a value.
+    pub(super) fn detail_opt(self, label: &str, value: Option<impl Display>) -> Self {
+        match value {
+            Some(value) => self.detail(label, value),
+            None => self,
+        }
+    }
+}
+
+/// A recorded operator with its inputs.
+struct ExplainNode {
+    /// The operator
+    operator: Operator,
+    /// Time spent in the operator and its inputs
+    time: Duration,
+    /// The inputs of the operator, in the order they ran
+    inputs: Vec<ExplainNode>,
+}
+
+/// A level of operators being recorded.
+#[derive(Default)]
+struct Level {
+    /// The operators recorded on the level, in the order they ran
+    nodes: Vec<ExplainNode>,
+    /// Time spent in the operators taken as inputs from the enclosing level
+    input_time: Duration,
+}
+
+/// Records the operators run by an explained statement.
+pub(super) struct ExplainRecorder {
+    /// The levels being recorded, the innermost last
+    levels: Mutex<Vec<Level>>,
+}
+
+impl ExplainRecorder {
+    /// Creates a recorder with an empty top level.
+    fn new() -> Self {
+        ExplainRecorder {
+            levels: Mutex::new(vec![Level::default()]),
+        }
+    }
+
+    /// Starts recording the inputs of an operator.
+    ///
+    /// # Arguments
+    /// * `takes_inputs` - Whether the operators of the current level are inputs of the operator
+    fn begin(&self, takes_inputs: bool) {
+        let mut levels = self.levels.lock().unwrap();
+        let level = match takes_inputs {
+            true => {
+                let nodes = std::mem::take(&mut levels.last_mut().unwrap().nodes);
+                Level {
+                    input_time: nodes.iter().map(|node| node.time).sum(),
+                    nodes,
+                }
+            }
+            false => Level::default(),
+        };
+        levels.push(level);
+    }
+
+    /// Finishes recording an operator started by [`begin`](Self::begin).
+    ///
+    /// # Arguments
+    /// * `operator` - The operator, None if it failed
+    /// * `elapsed` - Time spent running the operator
+    fn end(&self, operator: Option<Operator>, elapsed: Duration) {
+        let mut levels = self.levels.lock().unwrap();
+        let level = levels.pop().unwrap();
+        if let Some(operator) = operator {
+            levels.last_mut().unwrap().nodes.push(ExplainNode {
+                operator,
+                time: level.input_time + elapsed,
+                inputs: level.nodes,
+            });
+        }
+    }
+
+    /// Finishes a level started by [`begin`](Self::begin) without an
+    /// operator, moving its operators to the enclosing level.
+    fn end_group(&self) {
+        let mut levels = self.levels.lock().unwrap();
+        let level = levels.pop().unwrap();
+        levels.last_mut().unwrap().nodes.extend(level.nodes);
+    }
+
+    /// Gets the recorded operators of the top level.
+    fn finish(self) -> Vec<ExplainNode> {
+        let mut levels = self.levels.into_inner().unwrap();
+        levels.pop().unwrap().nodes
+    }
+}
+
+impl SQLExecutorState<'_> {
+    /// Runs an operator, recording it if the statement is explained.
+    ///
+    /// # Arguments
+    /// * `takes_inputs` - Whether the operators run before on the same level are inputs of the operator
+    /// * `run` - Function running the operator
+    /// * `describe` - Function describing the operator from its result
+    pub(super) fn explain<T>(
+        &self,
+        takes_inputs: bool,
+        run: impl FnOnce() -> DBResult<T>,
+        describe: impl FnOnce(&T) -> Operator,
+    ) -> DBResult<T> {
+        let Some(recorder) = self.explain else {
+            return run();
+        };
+        recorder.begin(takes_inputs);
+        let start = Instant::now();
+        let result = run();
+        let elapsed = start.elapsed();
+        recorder.end(result.as_ref().ok().map(describe), elapsed);
+        result
+    }
+
+    /// Runs a group of operators on a level of their own, so that they do
+    /// not take the operators run before as inputs.
+    ///
+    /// # Arguments
+    /// * `run` - Function running the operators
+    pub(super) fn explain_group<T>(&self, run: impl FnOnce() -> DBResult<T>) -> DBResult<T> {
+        let Some(recorder) = self.explain else {
+            return run();
+        };
+        recorder.begin(false);
+        let result = run();
+        recorder.end_group();
+        result
+    }
+
+    /// Gets a copy of the state whose evaluations are not recorded, for
+    /// work repeated for each row.
+    pub(super) fn unexplained(&self) -> Option<Self> {
+        self.explain?;
+        Some(SQLExecutorState {
+            sql_statements: self.sql_statements,
+            ctes: self.ctes.clone(),
+            trigger_rows: self.trigger_rows.clone(),
+            trigger_depth: self.trigger_depth,
+            ..Default::default()
+        })
+    }
+}
+
+/// Formats an explained operator and its inputs into lines.
+///
+/// # Arguments
+/// * `node` - The operator
+/// * `indent` - Column at which the operator starts
+/// * `analyze` - Whether the rows and times are shown
+/// * `lines` - Lines to append to
+fn format_node(node: &ExplainNode, indent: usize, analyze: bool, lines: &mut Vec<String>) {
+    let (arrow, name_indent) = match indent {
+        0 => ("", 0),
+        _ => ("-> ", indent + 3),
+    };
+    let mut line = format!("{}{}{}", " ".repeat(indent), arrow, node.operator.name);
+    if analyze {
+        line.push_str(&format!(
+            " (rows={} time={:.3}ms)",
+            node.operator.rows,
+            node.time.as_secs_f64() * 1000.0
+        ));
+    }
+    lines.push(line);
+    for detail in &node.operator.details {
+        lines.push(format!("{}{}", " ".repeat(name_indent + 2), detail));
+    }
+    for input in &node.inputs {
+        format_node(input, name_indent + 2, analyze, lines);
+    }
+}
+
+impl SQLExecutor {
+    /// Executes an EXPLAIN [ANALYZE] statement, outputting the operators of
+    /// a query, INSERT, UPDATE or DELETE statement as a tree, along with the
+    /// table manager executing them.
+    ///
+    /// # Arguments
+    /// * `explain` - Parsed EXPLAIN statement
+    /// * `executor_state` - Current executor state for evaluation context
+    pub(super) fn execute_explain(
+        &mut self,
+        explain: &ast::Statement,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        let ast::Statement::Explain {
+            analyze,
+            statement,
+            format,
+            options,
+            ..
+        } = explain
+        else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+        if format.is_some() || options.is_some() {
+            Err(DBSingleError::UnsupportedOPError(
+                "EXPLAIN options are not supported".into(),
+            ))?
+        }
+        use ast::Statement::*;
+        if !matches!(
+            statement.as_ref(),
+            Query(_) | Insert(_) | Update { .. } | Delete(_)
+        ) {
+            Err(DBSingleError::UnsupportedOPError(format!(
+                "EXPLAIN is not supported for statement {}",
+                statement
+            )))?
+        }
+
+        let recorder = ExplainRecorder::new();
+        let mut explained_state = SQLExecutorState {
+            sql_statements: executor_state.sql_statements,
+            ctes: executor_state.ctes.clone(),
+            trigger_rows: executor_state.trigger_rows.clone(),
+            trigger_depth: executor_state.trigger_depth,
+            explain: Some(&recorder),
+            ..Default::default()
+        };
+        let statement = SQLStatement::Standard(statement.clone());
+        let start = Instant::now();
+        if *analyze {
+            self.execute_statement(&statement, &mut explained_state)?;
+        } else {
+            let empty = self.database.empty_copy();
+            let database = std::mem::replace(&mut self.database, empty);
+            let change_hooks = std::mem::take(&mut self.change_hooks);
+            let result = self.execute_statement(&statement, &mut explained_state);
+            self.database = database;
+            self.change_hooks = change_hooks;
+            result?;
+        }
+        let elapsed = start.elapsed();
+
+        let mut lines = vec![format!("Table manager: {}", self.table_manager.name())];
+        for node in recorder.finish() {
+            format_node(&node, 0, *analyze, &mut lines);
+        }
+        if *analyze {
+            lines.push(format!(
+                "Execution time: {:.3}ms",
+                elapsed.as_secs_f64() * 1000.0
+            ));
+        }
+        let template = Table::new(vec![ColumnInfo {
+            name: "QUERY PLAN".into(),
+            nullable: false,
+            unique: false,
+            type_specific: ColumnTypeSpecific::Varchar {
+                max_length: u64::MAX,
+            },
+        }]);
+        let rows = lines
+            .into_iter()
+            .map(|line| vec![Value::from_varchar(line)])
+            .collect();
+        Self::output_table(&table_with_rows(&template, rows), executor_state)
+    }
+}
@@ -3,47 +3,59 @@
 //! Handles parsing and execution of INSERT statements, including
 //! column reordering and value validation.
 
-use super::SQLExecutor;
-use crate::core::data_structure::{Table, Value};
+use super::explain::Operator;
+use super::query::SelectSource;
+use super::table_manager::{RowChange, inserted};
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{Table, TriggerEvent, Value};
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::core::plan::{self, CompiledExpr};
 use crate::error::{DBResult, DBSingleError};
 use sqlparser::ast;
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
 
-/// Parses an expression and evaluates it against a dummy row.
+/// Evaluates a raw row of constant expressions and rearranges them according to the provided column indicators.
 ///
 /// # Arguments
-/// * `expr` - The expression to parse
+/// * `table` - The table structure containing column definitions
+/// * `raw_row` - The raw row of expressions to evaluate
+/// * `columns_indicator` - The list of column names indicating the order of values
 ///
 /// # Returns
-/// Evaluated value of the expression
+/// A vector of values representing the parsed row, rearranged according to column indicators.
 ///
 /// # Errors
-/// Returns an error if the expression cannot be parsed or evaluated.
-fn parse_expr(expr: &ast::Expr) -> DBResult<Value> {
-    Table::get_dummy().calc_expr_for_row(&[], expr)
+/// Returns an error if the number of values does not match the number of columns, or if a column is not found.
+pub(super) fn parse_raw_row_and_rearrange(
+    table: &Table,
+    raw_row: &[CompiledExpr],
+    columns_indicator: &[String],
+) -> DBResult<Vec<Value>> {
+    let mut insert_values = vec![];
+    for expr in raw_row {
+        insert_values.push(expr.eval(&[])?);
+    }
+    rearrange_row(table, insert_values, columns_indicator)
 }
 
-/// Parses a raw row of expressions and rearranges them according to the provided column indicators.
+/// Rearranges a row of values according to the provided column indicators.
 ///
 /// # Arguments
 /// * `table` - The table structure containing column definitions
-/// * `raw_row` - The raw row of expressions to parse
+/// * `insert_values` - The values to insert, in the order of `columns_indicator`
 /// * `columns_indicator` - The list of column names indicating the order of values
 ///
 /// # Returns
-/// A vector of values representing the parsed row, rearranged according to column indicators.
+/// A vector of values in the column order of the table; columns not given are NULL.
 ///
 /// # Errors
 /// Returns an error if the number of values does not match the number of columns, or if a column is not found.
-pub(super) fn parse_raw_row_and_rearrange(
+pub(super) fn rearrange_row(
     table: &Table,
-    raw_row: &[ast::Expr],
+    mut insert_values: Vec<Value>,
     columns_indicator: &[String],
 ) -> DBResult<Vec<Value>> {
-    let mut insert_values = vec![];
-    for expr in raw_row {
-        insert_values.push(parse_expr(expr)?);
-    }
     if columns_indicator.is_empty() {
         Ok(insert_values)
     } else {
@@ -77,22 +89,76 @@ pub(super) fn parse_raw_row_and_rearrange(
 }
 
 impl SQLExecutor {
-    /// Executes an INSERT statement.
+    /// Executes an INSERT statement, and outputs the inserted or updated
+    /// rows with RETURNING.
     ///
     /// # Arguments
     /// * `insert` - Parsed INSERT statement
-    pub(super) fn execute_insert(&mut self, insert: &ast::Insert) -> DBResult<()> {
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// Number of inserted or updated rows
+    pub(super) fn execute_insert(
+        &mut self,
+        insert: &ast::Insert,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<usize> {
         let table_object = &insert.table;
         let ast::TableObject::TableName(table_name) = table_object else {
             Err(DBSingleError::UnsupportedOPError(
                 "only support TableName".into(),
             ))?
         };
-        let table_name = table_name.to_string();
-        let table = self
-            .database
-            .get_table_mut(&table_name)
-            .ok_or_else(|| DBSingleError::OtherError(format!("table not found: {}", table_name)))?;
+        let table_name = normalize_object_name(table_name);
+        let changes = executor_state.explain(
+            true,
+            || {
+                self.change_table(
+                    &table_name,
+                    &[TriggerEvent::Insert, TriggerEvent::Update],
+                    executor_state,
+                    |this| this.insert_into(insert, &table_name, executor_state),
+                )
+            },
+            |changes| Operator::new(format!("Insert on {}", table_name), changes.len()),
+        )?;
+        // a row may be updated more than once, and its last values are
+        // returned even if an AFTER trigger changed it since
+        let mut rows = BTreeMap::new();
+        for change in changes {
+            let row_idx = change.row_idx();
+            if let Some(row) = change.into_new_row() {
+                rows.insert(row_idx, row);
+            }
+        }
+        let changed_num = rows.len();
+
+        if let Some(returning) = &insert.returning {
+            let table = self
+                .database
+                .get_table(&table_name)
+                .expect("table should exist");
+            let rows = rows.into_values().collect();
+            self.output_returning(returning, table, table_name, rows, executor_state)?;
+        }
+        Ok(changed_num)
+    }
+
+    /// Inserts the rows of an INSERT statement.
+    ///
+    /// # Arguments
+    /// * `insert` - Parsed INSERT statement
+    /// * `table_name` - Name of the table to insert into
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// The changes made to the table, in order
+    fn insert_into(
+        &mut self,
+        insert: &ast::Insert,
+        table_name: &str,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Vec<RowChange>> {
         let query = insert
             .source
             .as_ref()
@@ -101,16 +167,58 @@ impl SQLExecutor {
         let columns_indicator = insert
             .columns
             .iter()
-            .map(|c| c.to_string())
+            .map(normalize_ident)
             .collect::<Vec<_>>();
+        if let Some(on_insert) = &insert.on {
+            return self.execute_upsert(
+                insert,
+                on_insert,
+                table_name,
+                query,
+                &columns_indicator,
+                executor_state,
+            );
+        }
         let ast::SetExpr::Values(values) = query.body.as_ref() else {
-            Err(DBSingleError::UnsupportedOPError(
-                "only support values".into(),
-            ))?
+            // INSERT ... SELECT: insert the rows of the query result
+            let result = self.evaluate_query(query, executor_state)?;
+            let table = self.database.get_table_mut(table_name).ok_or_else(|| {
+                DBSingleError::OtherError(format!("table not found: {}", table_name))
+            })?;
+            let rows = result
+                .rows
+                .into_values()
+                .flatten()
+                .map(|row| rearrange_row(table, row, &columns_indicator))
+                .collect::<DBResult<Vec<_>>>()?;
+            let row_indices = self.table_manager.insert_values(table, rows)?;
+            return Ok(inserted(table, row_indices));
         };
-        let raw_rows = &values.rows;
-        self.table_manager
-            .insert_rows(table, raw_rows, columns_indicator)?;
-        Ok(())
+        let mut raw_rows = values.rows.clone();
+        let source = SelectSource {
+            table: Cow::Borrowed(Table::get_dummy()),
+            name: None,
+        };
+        for expr in raw_rows.iter_mut().flatten() {
+            self.bind_expr(expr, &source, &mut None, executor_state)?;
+        }
+        let raw_rows = raw_rows
+            .iter()
+            .map(|raw_row| {
+                raw_row
+                    .iter()
+                    .map(|expr| plan::compile(expr, Table::get_dummy(), self.name_resolution()))
+                    .collect::<DBResult<Vec<_>>>()
+            })
+            .collect::<DBResult<Vec<_>>>()?;
+
+        let table = self
+            .database
+            .get_table_mut(table_name)
+            .ok_or_else(|| DBSingleError::OtherError(format!("table not found: {}", table_name)))?;
+        let row_indices = self
+            .table_manager
+            .insert_rows(table, &raw_rows, columns_indicator)?;
+        Ok(inserted(table, row_indices))
     }
 }
@@ -3,92 +3,480 @@
 //! Handles parsing and execution of SELECT queries including:
 //! - Projection
 //! - Filtering
+//! - Aggregation
+//! - Window functions
 //! - Ordering
+//! - Deduplication (DISTINCT)
+//! - Limiting (LIMIT/OFFSET)
 //! - Result output
 
+use super::explain::Operator;
 use super::{SQLExecutor, SQLExecutorState};
 use crate::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table};
 use crate::core::executor::table_manager::CalcFunc;
+use crate::core::executor::window::collect_window_calls;
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::core::plan::{self, CompiledExpr, NameResolution, compile_cond};
 use crate::error::{DBResult, DBSingleError};
-use sqlparser::ast::{self, Spanned};
+use sqlparser::ast;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Write;
 
+/// The relation a SELECT query reads from.
+pub(super) struct SelectSource<'a> {
+    /// The source table, owned if computed by a derived table
+    pub table: Cow<'a, Table>,
+    /// Name the table is referred to by in the query, i.e. its alias if given
+    pub name: Option<String>,
+}
+
+impl SelectSource<'_> {
+    /// Borrows the source, without copying an owned table.
+    pub(super) fn reborrow(&self) -> SelectSource<'_> {
+        SelectSource {
+            table: Cow::Borrowed(&self.table),
+            name: self.name.clone(),
+        }
+    }
+}
+
 impl SQLExecutor {
-    /// Applies ORDER BY clauses to a table.
+    /// Gets the ORDER BY expressions of a query.
     ///
     /// # Arguments
-    /// * `table` - Table to sort
-    /// * `order_by` - Optional ORDER BY clauses
-    fn execute_order_by(&self, table: &mut Table, order_by: &Option<ast::OrderBy>) -> DBResult<()> {
-        let order_by = match order_by.as_ref().map(|x| &x.kind) {
-            Some(x) => x,
-            None => return Ok(()),
-        };
-
-        let ast::OrderByKind::Expressions(order_by_exprs) = order_by else {
-            Err(DBSingleError::UnsupportedOPError(
+    /// * `order_by` - Optional ORDER BY clause
+    ///
+    /// # Returns
+    /// The ORDER BY expressions, empty if there is no ORDER BY clause
+    fn get_order_by_exprs(order_by: &Option<ast::OrderBy>) -> DBResult<&[ast::OrderByExpr]> {
+        match order_by.as_ref().map(|x| &x.kind) {
+            None => Ok(&[]),
+            Some(ast::OrderByKind::Expressions(order_by_exprs)) => Ok(order_by_exprs),
+            Some(_) => Err(DBSingleError::UnsupportedOPError(
                 "only support order by expressions".into(),
-            ))?
-        };
-
-        let keys = order_by_exprs
-            .iter()
-            .map(|order_by_expr| {
-                let expr = &order_by_expr.expr;
-                let is_asc = order_by_expr.options.asc.unwrap_or(true);
-                (expr, is_asc)
-            })
-            .collect::<Vec<_>>();
-
-        self.table_manager.convert_order_by(table, &keys)?;
-        Ok(())
+            ))?,
+        }
     }
+
     /// Gets the source table for a SELECT query.
     ///
     /// # Arguments
     /// * `select` - Parsed SELECT statement
+    /// * `executor_state` - Current executor state for evaluation context
     ///
     /// # Returns
-    /// Reference to source table
-    fn parse_table_from_select(&self, select: &ast::Select) -> DBResult<&Table> {
+    /// The source table and the name it is referred to by
+    pub(super) fn parse_table_from_select<'a>(
+        &'a self,
+        select: &ast::Select,
+        executor_state: &'a SQLExecutorState,
+    ) -> DBResult<SelectSource<'a>> {
         match select.from.len() {
-            0 => Ok(Table::get_dummy()),
+            0 => Ok(SelectSource {
+                table: Cow::Borrowed(Table::get_dummy()),
+                name: None,
+            }),
             1 => {
-                let table = &select.from[0];
-                let ast::TableFactor::Table {
-                    name: ref table_name,
-                    ..
-                } = table.relation
-                else {
+                if !select.from[0].joins.is_empty() {
                     Err(DBSingleError::UnsupportedOPError(
-                        "only support table in relation".into(),
+                        "joins are not supported".into(),
                     ))?
-                };
-                let table_name = table_name.to_string();
+                }
+                self.parse_table_factor(&select.from[0].relation, executor_state)
+            }
+            _ => Err(DBSingleError::UnsupportedOPError(
+                "only support zero or one table".into(),
+            ))?,
+        }
+    }
+
+    /// Gets the relation a table name refers to: the relation defined by an
+    /// enclosing WITH clause if any, and the table, view or virtual table of
+    /// the information schema otherwise.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the relation
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// The kind of the relation, as shown by EXPLAIN, and its table
+    fn get_relation<'a>(
+        &'a self,
+        table_name: &str,
+        executor_state: &'a SQLExecutorState,
+    ) -> DBResult<(&'static str, Cow<'a, Table>)> {
+        if let Some(table) = executor_state.ctes.get(table_name) {
+            return Ok(("CTE Scan", Cow::Borrowed(table.as_ref())));
+        }
+        if let Some(table) = self.database.get_table(table_name) {
+            return Ok(("Scan", Cow::Borrowed(table)));
+        }
+        if let Some(materialized) = self.database.get_materialized_view(table_name) {
+            return Ok(("Materialized View Scan", Cow::Borrowed(&materialized.table)));
+        }
+        if let Some(table) = self.get_information_schema_table(table_name) {
+            return Ok(("Catalog Scan", Cow::Owned(table)));
+        }
+        let view = self
+            .database
+            .get_view(table_name)
+            .ok_or_else(|| DBSingleError::OtherError(format!("table not found: {}", table_name)))?;
+        let table = self.evaluate_view(table_name, view, HashMap::new())?;
+        Ok(("View Scan", Cow::Owned(table)))
+    }
 
-                self.database.get_table(&table_name).ok_or_else(|| {
-                    DBSingleError::OtherError(format!("table not found: {}", table_name)).into()
+    /// Gets the table a relation of a FROM clause refers to.
+    ///
+    /// A table name refers to the relation given by
+    /// [`get_relation`](Self::get_relation). A derived table (`(<subquery>) AS <alias>`) is evaluated, and its
+    /// columns renamed after the column aliases if given.
+    ///
+    /// # Arguments
+    /// * `relation` - Parsed relation
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// The table and the name it is referred to by
+    fn parse_table_factor<'a>(
+        &'a self,
+        relation: &ast::TableFactor,
+        executor_state: &'a SQLExecutorState,
+    ) -> DBResult<SelectSource<'a>> {
+        match relation {
+            ast::TableFactor::Table {
+                name: table_name,
+                alias,
+                ..
+            } => {
+                let table_name = normalize_object_name(table_name);
+                let (_, table) = executor_state.explain(
+                    false,
+                    || self.get_relation(&table_name, executor_state),
+                    |(kind, table)| {
+                        Operator::new(format!("{} {}", kind, table_name), table.get_row_num())
+                            .detail_opt("Alias", alias.as_ref().map(|alias| &alias.name))
+                    },
+                )?;
+                Ok(SelectSource {
+                    table,
+                    name: Some(
+                        alias
+                            .as_ref()
+                            .map_or(table_name, |a| normalize_ident(&a.name)),
+                    ),
+                })
+            }
+            ast::TableFactor::Derived {
+                lateral,
+                subquery,
+                alias,
+            } => {
+                if *lateral {
+                    Err(DBSingleError::UnsupportedOPError(
+                        "lateral derived tables are not supported".into(),
+                    ))?
+                }
+                let mut table = executor_state.explain(
+                    false,
+                    || self.evaluate_query(subquery, executor_state),
+                    |table| {
+                        let name = alias.as_ref().map(|alias| format!(" {}", alias.name));
+                        Operator::new(
+                            format!("Subquery Scan{}", name.unwrap_or_default()),
+                            table.get_row_num(),
+                        )
+                    },
+                )?;
+                if let Some(alias) = alias {
+                    if alias.columns.len() > table.get_column_num() {
+                        Err(DBSingleError::OtherError(format!(
+                            "table {} has {} columns available but {} columns specified",
+                            alias.name,
+                            table.get_column_num(),
+                            alias.columns.len()
+                        )))?
+                    }
+                    for (i, column) in alias.columns.iter().enumerate() {
+                        table.rename_column(i, normalize_ident(&column.name));
+                    }
+                }
+                Ok(SelectSource {
+                    table: Cow::Owned(table),
+                    name: alias.as_ref().map(|a| normalize_ident(&a.name)),
                 })
             }
             _ => Err(DBSingleError::UnsupportedOPError(
-                "only support zero or one table".into(),
+                "only support table or subquery in relation".into(),
             ))?,
         }
     }
 
+    /// Evaluates a SELECT against its source, sorted by the ORDER BY expressions.
+    ///
+    /// The subqueries of the SELECT are bound first; correlated subqueries
+    /// are computed for each source row as hidden columns appended to the
+    /// source table.
+    ///
+    /// # Arguments
+    /// * `select` - Parsed SELECT statement
+    /// * `order_by` - ORDER BY expressions to sort the result by
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// New table containing query results
+    fn evaluate_select(
+        &self,
+        select: &ast::Select,
+        order_by: &[ast::OrderByExpr],
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        executor_state.explain_group(|| {
+            let source = self.parse_table_from_select(select, executor_state)?;
+            self.evaluate_select_from(&source, select, order_by, executor_state)
+        })
+    }
+
+    /// Evaluates a SELECT against a given source, see
+    /// [`evaluate_select`](Self::evaluate_select). The FROM clause of the
+    /// SELECT is ignored.
+    ///
+    /// # Arguments
+    /// * `source` - Source of the SELECT
+    /// * `select` - Parsed SELECT statement
+    /// * `order_by` - ORDER BY expressions to sort the result by
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// New table containing query results
+    pub(super) fn evaluate_select_from(
+        &self,
+        source: &SelectSource,
+        select: &ast::Select,
+        order_by: &[ast::OrderByExpr],
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        let mut select = select.clone();
+        let mut order_by = order_by.to_vec();
+        let correlated = self.bind_select(&mut select, &mut order_by, source, executor_state)?;
+        if correlated.is_empty() {
+            return self.get_query_table(source, &source.table, &select, &order_by, executor_state);
+        }
+
+        // the subqueries are evaluated for each row, as part of this operator
+        let unexplained = executor_state.unexplained();
+        let subquery_state = unexplained.as_ref().unwrap_or(executor_state);
+        let mut columns_info = source.table.columns_info.clone();
+        let mut calc_funcs: Vec<CalcFunc> = vec![];
+        for i in 0..columns_info.len() {
+            calc_funcs.push(Box::new(move |row| Ok(row[i].clone())));
+        }
+        for subquery in &correlated {
+            columns_info.push(expr_column_info(subquery.column_name.clone()));
+            calc_funcs.push(Box::new(move |row| {
+                self.evaluate_correlated_subquery(subquery, source, row, subquery_state)
+            }));
+        }
+        let table = executor_state.explain(
+            true,
+            || {
+                self.table_manager.construct_table_from_calc_func(
+                    &source.table,
+                    columns_info,
+                    calc_funcs,
+                    None,
+                )
+            },
+            |table| {
+                Operator::new("Correlated Subqueries", table.get_row_num()).detail(
+                    "Subqueries",
+                    ast::display_comma_separated(
+                        &correlated
+                            .iter()
+                            .map(|subquery| &subquery.expr)
+                            .collect::<Vec<_>>(),
+                    ),
+                )
+            },
+        )?;
+        self.get_query_table(source, &table, &select, &order_by, executor_state)
+    }
+
     /// Constructs result table from SELECT query.
     ///
+    /// Queries with aggregates, `GROUP BY` or `HAVING` are first aggregated,
+    /// and the projection is then computed over the groups. Window functions
+    /// are computed over the rows left after filtering (WHERE, or HAVING for
+    /// aggregated queries), before the projection.
+    ///
     /// # Arguments
-    /// * `table` - Source table
+    /// * `source` - Source table
+    /// * `table` - Table to query, whose leading columns are the source columns
     /// * `select` - Parsed SELECT statement
+    /// * `order_by` - ORDER BY expressions to sort the result by
     /// * `executor_state` - Current executor state for evaluation context
     ///
     /// # Returns
     /// New table containing query results
     fn get_query_table(
         &self,
+        source: &SelectSource,
         table: &Table,
         select: &ast::Select,
+        order_by: &[ast::OrderByExpr],
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        let mut calls = vec![];
+        for select_item in &select.projection {
+            use ast::SelectItem::*;
+            if let UnnamedExpr(expr) | ExprWithAlias { expr, .. } = select_item {
+                self.aggregates
+                    .collect_calls(expr, self.name_resolution(), &mut calls)?;
+            }
+        }
+        if let Some(having) = &select.having {
+            self.aggregates
+                .collect_calls(having, self.name_resolution(), &mut calls)?;
+        }
+        for order_by_expr in order_by {
+            self.aggregates.collect_calls(
+                &order_by_expr.expr,
+                self.name_resolution(),
+                &mut calls,
+            )?;
+        }
+        if let Some(ast::Distinct::On(exprs)) = &select.distinct {
+            for expr in exprs {
+                self.aggregates
+                    .collect_calls(expr, self.name_resolution(), &mut calls)?;
+            }
+        }
+        let ast::GroupByExpr::Expressions(group_by, modifiers) = &select.group_by else {
+            Err(DBSingleError::UnsupportedOPError(
+                "only support group by expressions".into(),
+            ))?
+        };
+        if !modifiers.is_empty() {
+            Err(DBSingleError::UnsupportedOPError(
+                "group by modifiers are not supported".into(),
+            ))?
+        }
+
+        let mut window_calls = vec![];
+        for select_item in &select.projection {
+            use ast::SelectItem::*;
+            if let UnnamedExpr(expr) | ExprWithAlias { expr, .. } = select_item {
+                collect_window_calls(
+                    &self.aggregates,
+                    expr,
+                    self.name_resolution(),
+                    &mut window_calls,
+                )?;
+            }
+        }
+        for order_by_expr in order_by {
+            collect_window_calls(
+                &self.aggregates,
+                &order_by_expr.expr,
+                self.name_resolution(),
+                &mut window_calls,
+            )?;
+        }
+
+        let aggregated_table;
+        let (table, cond) = if calls.is_empty() && group_by.is_empty() && select.having.is_none() {
+            (table, select.selection.as_ref())
+        } else {
+            let compiled_group_by = group_by
+                .iter()
+                .map(|expr| plan::compile(expr, table, self.name_resolution()))
+                .collect::<DBResult<Vec<_>>>()?;
+            let compiled_selection =
+                compile_cond(select.selection.as_ref(), table, self.name_resolution())?;
+            aggregated_table = executor_state.explain(
+                true,
+                || {
+                    self.table_manager.aggregate(
+                        table,
+                        &compiled_group_by,
+                        &calls,
+                        compiled_selection.as_ref(),
+                    )
+                },
+                |table| {
+                    Operator::new("Aggregate", table.get_row_num())
+                        .detail_opt(
+                            "Group Key",
+                            (!group_by.is_empty()).then(|| ast::display_comma_separated(group_by)),
+                        )
+                        .detail_opt("Filter", select.selection.as_ref())
+                },
+            )?;
+            (&aggregated_table, select.having.as_ref())
+        };
+        if window_calls.is_empty() {
+            return self.project_table(table, source, select, cond, order_by, executor_state);
+        }
+
+        let compiled_cond = compile_cond(cond, table, self.name_resolution())?;
+        let windowed_table = executor_state.explain(
+            true,
+            || {
+                self.table_manager
+                    .window(table, &window_calls, compiled_cond.as_ref())
+            },
+            |table| {
+                Operator::new("Window", table.get_row_num())
+                    .detail(
+                        "Functions",
+                        ast::display_comma_separated(
+                            &window_calls
+                                .iter()
+                                .map(|call| &call.expr)
+                                .collect::<Vec<_>>(),
+                        ),
+                    )
+                    .detail_opt("Filter", cond)
+            },
+        )?;
+        self.project_table(
+            &windowed_table,
+            source,
+            select,
+            None,
+            order_by,
+            executor_state,
+        )
+    }
+
+    /// Computes the projection of a SELECT query over a table, sorted by the
+    /// ORDER BY expressions.
+    ///
+    /// An ORDER BY identifier naming an alias of the select list sorts by the
+    /// aliased expression; other ORDER BY expressions are evaluated against
+    /// the rows of `table`, so they need not appear in the select list.
+    ///
+    /// `DISTINCT` keeps the first of the rows with equal results, and
+    /// `DISTINCT ON (<exprs>)` the first of the rows with equal `<exprs>`,
+    /// in the sorted order.
+    ///
+    /// # Arguments
+    /// * `table` - Table to project, whose leading columns are the source columns
+    /// * `source` - Source table, whose columns are expanded by wildcards
+    /// * `select` - Parsed SELECT statement
+    /// * `cond` - Optional condition to filter which rows to project
+    /// * `order_by` - ORDER BY expressions to sort the result by
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// New table containing the projected rows
+    fn project_table(
+        &self,
+        table: &Table,
+        source: &SelectSource,
+        select: &ast::Select,
+        cond: Option<&ast::Expr>,
+        order_by: &[ast::OrderByExpr],
         executor_state: &SQLExecutorState,
     ) -> DBResult<Table> {
         let mut columns_info = vec![];
@@ -98,22 +486,37 @@ impl SQLExecutor {
             use ast::SelectItem::*;
             match select_item {
                 Wildcard(_) => {
-                    for (i, column) in table.columns_info.iter().enumerate() {
+                    for (i, column) in source.table.columns_info.iter().enumerate() {
+                        columns_info.push(column.clone());
+                        calc_funcs.push(Box::new(move |row| Ok(row[i].clone())));
+                    }
+                }
+                QualifiedWildcard(ast::SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                    let name = normalize_object_name(name);
+                    if source.name.as_ref() != Some(&name) {
+                        Err(DBSingleError::OtherError(format!(
+                            "table not found: {}",
+                            name
+                        )))?
+                    }
+                    for (i, column) in source.table.columns_info.iter().enumerate() {
                         columns_info.push(column.clone());
                         calc_funcs.push(Box::new(move |row| Ok(row[i].clone())));
                     }
                 }
-                UnnamedExpr(expr) => {
-                    let column_name = self
-                        .get_content_from_span(expr.span(), executor_state)
-                        .unwrap_or_else(|| expr.to_string());
-                    columns_info.push(ColumnInfo {
-                        name: column_name,
-                        nullable: true,                         // dummy setting
-                        unique: false,                          // dummy setting
-                        type_specific: ColumnTypeSpecific::Any, // dummy setting
-                    });
-                    calc_funcs.push(Box::new(|row| table.calc_expr_for_row(row, expr)));
+                UnnamedExpr(expr) | ExprWithAlias { expr, .. } => {
+                    let name = match select_item {
+                        ExprWithAlias { alias, .. } => normalize_ident(alias),
+                        _ => self.get_expr_name(expr, executor_state),
+                    };
+                    let expr = plan::compile(expr, table, self.name_resolution())?;
+                    let mut column_info = expr_column_info(name);
+                    // a column read as is keeps its type
+                    if let Some(i) = expr.column_index() {
+                        column_info.type_specific = table.get_column_info(i).type_specific;
+                    }
+                    columns_info.push(column_info);
+                    calc_funcs.push(Box::new(move |row| expr.eval(row)));
                 }
                 _ => Err(DBSingleError::UnsupportedOPError(format!(
                     "Not support select item {:?}",
@@ -121,16 +524,281 @@ impl SQLExecutor {
                 )))?,
             }
         }
-        let new_table = self.table_manager.construct_table_from_calc_func(
-            table,
-            columns_info,
-            calc_funcs,
-            select.selection.as_ref(),
+
+        // ORDER BY and DISTINCT ON keys are computed as hidden trailing columns,
+        // removed after sorting and deduplication
+        let column_num = columns_info.len();
+        let mut keys = vec![];
+        for (i, order_by_expr) in order_by.iter().enumerate() {
+            let expr = match &order_by_expr.expr {
+                ast::Expr::Identifier(ident) => select
+                    .projection
+                    .iter()
+                    .find_map(|select_item| match select_item {
+                        ast::SelectItem::ExprWithAlias { expr, alias }
+                            if normalize_ident(alias) == normalize_ident(ident) =>
+                        {
+                            Some(expr)
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or(&order_by_expr.expr),
+                expr => expr,
+            };
+            keys.push((
+                CompiledExpr::column(columns_info.len()),
+                order_by_expr.options.asc.unwrap_or(true),
+            ));
+            columns_info.push(expr_column_info(format!("#order_key{}", i)));
+            let expr = plan::compile(expr, table, self.name_resolution())?;
+            calc_funcs.push(Box::new(move |row| expr.eval(row)));
+        }
+
+        let distinct_columns = match &select.distinct {
+            None => None,
+            Some(ast::Distinct::Distinct) => Some((0..column_num).collect::<Vec<_>>()),
+            Some(ast::Distinct::On(exprs)) => {
+                let mut distinct_columns = vec![];
+                for (i, expr) in exprs.iter().enumerate() {
+                    distinct_columns.push(columns_info.len());
+                    columns_info.push(expr_column_info(format!("#distinct_key{}", i)));
+                    let expr = plan::compile(expr, table, self.name_resolution())?;
+                    calc_funcs.push(Box::new(move |row| expr.eval(row)));
+                }
+                Some(distinct_columns)
+            }
+        };
+
+        let output = ast::display_comma_separated(&select.projection).to_string();
+        let compiled_cond = compile_cond(cond, table, self.name_resolution())?;
+        let mut new_table = executor_state.explain(
+            true,
+            || {
+                self.table_manager.construct_table_from_calc_func(
+                    table,
+                    columns_info,
+                    calc_funcs,
+                    compiled_cond.as_ref(),
+                )
+            },
+            |table| {
+                Operator::new("Project", table.get_row_num())
+                    .detail("Output", output)
+                    .detail_opt("Filter", cond)
+            },
         )?;
+        if !keys.is_empty() {
+            new_table = executor_state.explain(
+                true,
+                || {
+                    self.table_manager.convert_order_by(&mut new_table, &keys)?;
+                    Ok(new_table)
+                },
+                |table| {
+                    Operator::new("Sort", table.get_row_num())
+                        .detail("Sort Key", ast::display_comma_separated(order_by))
+                },
+            )?;
+        }
+        if let Some(distinct_columns) = distinct_columns {
+            new_table = executor_state.explain(
+                true,
+                || {
+                    self.table_manager
+                        .dedup_rows(&mut new_table, &distinct_columns)?;
+                    Ok(new_table)
+                },
+                |table| match &select.distinct {
+                    Some(ast::Distinct::On(exprs)) => {
+                        Operator::new("Distinct", table.get_row_num())
+                            .detail("Distinct On", ast::display_comma_separated(exprs))
+                    }
+                    _ => Operator::new("Distinct", table.get_row_num()),
+                },
+            )?;
+        }
+        if new_table.get_column_num() > column_num {
+            new_table.truncate_columns(column_num);
+        }
         Ok(new_table)
     }
 
-    /// Executes a SELECT query.
+    /// Evaluates a query body without ORDER BY or LIMIT of its own.
+    ///
+    /// # Arguments
+    /// * `body` - Query body to evaluate
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// New table containing the results of the body
+    pub(super) fn evaluate_set_expr(
+        &self,
+        body: &ast::SetExpr,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        match body {
+            ast::SetExpr::Select(select) => self.evaluate_select(select, &[], executor_state),
+            ast::SetExpr::Query(query) => self.evaluate_query(query, executor_state),
+            ast::SetExpr::SetOperation {
+                op,
+                set_quantifier,
+                left,
+                right,
+            } => executor_state.explain(
+                true,
+                || self.evaluate_set_operation(op, set_quantifier, left, right, executor_state),
+                |table| {
+                    let name = match op {
+                        ast::SetOperator::Union => "Union",
+                        ast::SetOperator::Intersect => "Intersect",
+                        ast::SetOperator::Except | ast::SetOperator::Minus => "Except",
+                    };
+                    let all = match set_quantifier {
+                        ast::SetQuantifier::All => " All",
+                        _ => "",
+                    };
+                    Operator::new(format!("{}{}", name, all), table.get_row_num())
+                },
+            ),
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported query body {}",
+                body
+            )))?,
+        }
+    }
+
+    /// Evaluates a query into its result table.
+    ///
+    /// The relations defined by its WITH clause are visible to the query.
+    /// The ORDER BY of a single SELECT may refer to its source columns;
+    /// the ORDER BY of a set operation refers to the columns of the combined
+    /// result. LIMIT and OFFSET apply last.
+    ///
+    /// # Arguments
+    /// * `query` - Parsed query to evaluate
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// New table containing query results
+    pub(super) fn evaluate_query(
+        &self,
+        query: &ast::Query,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        executor_state.explain_group(|| {
+            if let Some(with) = &query.with {
+                let executor_state = self.evaluate_with(with, executor_state)?;
+                return self.evaluate_query_without_with(query, &executor_state);
+            }
+            self.evaluate_query_without_with(query, executor_state)
+        })
+    }
+
+    /// Evaluates a query ignoring its WITH clause, see [`evaluate_query`](Self::evaluate_query).
+    fn evaluate_query_without_with(
+        &self,
+        query: &ast::Query,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        let order_by = Self::get_order_by_exprs(&query.order_by)?;
+        let mut new_table = match query.body.as_ref() {
+            ast::SetExpr::Select(select) => {
+                self.evaluate_select(select, order_by, executor_state)?
+            }
+            body => {
+                let mut new_table = self.evaluate_set_expr(body, executor_state)?;
+                let keys = order_by
+                    .iter()
+                    .map(|order_by_expr| {
+                        Ok((
+                            plan::compile(&order_by_expr.expr, &new_table, self.name_resolution())?,
+                            order_by_expr.options.asc.unwrap_or(true),
+                        ))
+                    })
+                    .collect::<DBResult<Vec<_>>>()?;
+                if !keys.is_empty() {
+                    new_table = executor_state.explain(
+                        true,
+                        || {
+                            self.table_manager.convert_order_by(&mut new_table, &keys)?;
+                            Ok(new_table)
+                        },
+                        |table| {
+                            Operator::new("Sort", table.get_row_num())
+                                .detail("Sort Key", ast::display_comma_separated(order_by))
+                        },
+                    )?;
+                }
+                new_table
+            }
+        };
+        if let Some(limit_clause) = &query.limit_clause {
+            new_table = executor_state.explain(
+                true,
+                || {
+                    Self::execute_limit(&mut new_table, limit_clause)?;
+                    Ok(new_table)
+                },
+                |table| {
+                    Operator::new("Limit", table.get_row_num())
+                        .detail("Clause", limit_clause.to_string().trim())
+                },
+            )?;
+        }
+        Ok(new_table)
+    }
+
+    /// Applies a LIMIT clause to a table, keeping the rows in the window.
+    ///
+    /// # Arguments
+    /// * `table` - Table to limit
+    /// * `limit_clause` - Parsed LIMIT clause
+    fn execute_limit(table: &mut Table, limit_clause: &ast::LimitClause) -> DBResult<()> {
+        let (limit, offset) = match limit_clause {
+            ast::LimitClause::LimitOffset {
+                limit,
+                offset,
+                limit_by,
+            } => {
+                if !limit_by.is_empty() {
+                    Err(DBSingleError::UnsupportedOPError(
+                        "limit by is not supported".into(),
+                    ))?
+                }
+                (limit.as_ref(), offset.as_ref().map(|offset| &offset.value))
+            }
+            ast::LimitClause::OffsetCommaLimit { offset, limit } => (Some(limit), Some(offset)),
+        };
+        let eval_count = |expr: Option<&ast::Expr>| -> DBResult<Option<usize>> {
+            let Some(expr) = expr else {
+                return Ok(None);
+            };
+            let count = Table::get_dummy()
+                .calc_expr_for_row(&[], expr, NameResolution::Strict)?
+                .try_to_int()?
+                .and_then(|count| usize::try_from(count).ok())
+                .ok_or_else(|| {
+                    DBSingleError::OtherError(format!("invalid limit or offset {}", expr))
+                })?;
+            Ok(Some(count))
+        };
+        let limit = eval_count(limit)?.unwrap_or(usize::MAX);
+        let offset = eval_count(offset)?.unwrap_or(0);
+
+        table.rows = std::mem::take(&mut table.rows)
+            .into_values()
+            .flatten()
+            .skip(offset)
+            .take(limit)
+            .map(Some)
+            .enumerate()
+            .collect();
+        table.row_num = table.rows.len();
+        table.row_idx_acc = table.rows.len();
+        Ok(())
+    }
+
+    /// Executes a query and outputs its results.
     ///
     /// # Arguments
     /// * `query` - Parsed query to execute
@@ -140,26 +808,40 @@ impl SQLExecutor {
         query: &ast::Query,
         executor_state: &mut SQLExecutorState,
     ) -> DBResult<()> {
-        let ast::SetExpr::Select(select) = query.body.as_ref() else {
-            Err(DBSingleError::UnsupportedOPError(
-                "only support select".into(),
-            ))?
-        };
-
-        let table = self.parse_table_from_select(select)?;
-        let mut new_table = self.get_query_table(table, select, executor_state)?;
-        self.execute_order_by(&mut new_table, &query.order_by)?;
+        let new_table = self.evaluate_query(query, executor_state)?;
+        Self::output_table(&new_table, executor_state)
+    }
 
-        // output
-        if new_table.get_row_num() > 0 {
-            // output the new_table
+    /// Outputs a result table, unless it is empty.
+    ///
+    /// # Arguments
+    /// * `table` - Result table to output
+    /// * `executor_state` - Current executor state holding the output
+    pub(super) fn output_table(
+        table: &Table,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        if table.get_row_num() > 0 {
             if executor_state.output_count > 0 {
                 writeln!(executor_state.output_buffer)?;
             }
-            write!(executor_state.output_buffer, "{}", new_table)?;
+            write!(executor_state.output_buffer, "{}", table)?;
             executor_state.output_count += 1;
         }
 
         Ok(())
     }
 }
+
+/// Creates the metadata of a computed result column.
+///
+/// # Arguments
+/// * `name` - Name of the column
+pub(super) fn expr_column_info(name: String) -> ColumnInfo {
+    ColumnInfo {
+        name,
+        nullable: true,                         // dummy setting
+        unique: false,                          // dummy setting
+        type_specific: ColumnTypeSpecific::Any, // dummy setting
+    }
+}
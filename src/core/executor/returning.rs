@@ -0,0 +1,64 @@
+//! RETURNING clause evaluation.
+//!
+//! Outputs the rows affected by INSERT, UPDATE and DELETE statements.
+
+use super::cte::table_with_rows;
+use super::query::SelectSource;
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{Table, Value};
+use crate::error::DBResult;
+use sqlparser::ast;
+use std::borrow::Cow;
+
+impl SQLExecutor {
+    /// Evaluates a RETURNING clause over the affected rows, and outputs the
+    /// result like a query.
+    ///
+    /// The select items are evaluated as a SELECT reading from the affected
+    /// rows, so they may use expressions, aliases, wildcards and subqueries.
+    ///
+    /// # Arguments
+    /// * `returning` - Select items of the RETURNING clause
+    /// * `table` - The table written to
+    /// * `name` - Name the table is referred to by in the statement
+    /// * `rows` - The affected rows: inserted or updated rows with their new values, deleted rows with their old values
+    /// * `executor_state` - Current executor state for evaluation context
+    pub(super) fn output_returning(
+        &self,
+        returning: &[ast::SelectItem],
+        table: &Table,
+        name: String,
+        rows: Vec<Vec<Value>>,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<()> {
+        let source = SelectSource {
+            table: Cow::Owned(table_with_rows(table, rows)),
+            name: Some(name),
+        };
+        let select = ast::Select {
+            select_token: ast::helpers::attached_token::AttachedToken::empty(),
+            distinct: None,
+            top: None,
+            top_before_distinct: false,
+            projection: returning.to_vec(),
+            into: None,
+            from: vec![],
+            lateral_views: vec![],
+            prewhere: None,
+            selection: None,
+            group_by: ast::GroupByExpr::Expressions(vec![], vec![]),
+            cluster_by: vec![],
+            distribute_by: vec![],
+            sort_by: vec![],
+            having: None,
+            named_window: vec![],
+            qualify: None,
+            window_before_qualify: false,
+            value_table_mode: None,
+            connect_by: None,
+            flavor: ast::SelectFlavor::Standard,
+        };
+        let result = self.evaluate_select_from(&source, &select, &[], executor_state)?;
+        Self::output_table(&result, executor_state)
+    }
+}
@@ -0,0 +1,159 @@
+//! Set operation execution.
+//!
+//! Handles UNION, INTERSECT and EXCEPT between query results, with or
+//! without the ALL quantifier.
+
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{ColumnTypeSpecific, Table, Value};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::collections::HashMap;
+
+/// Checks that two operands of a set operation have compatible columns.
+///
+/// # Arguments
+/// * `op` - The set operator, used in error messages
+/// * `left` - Result of the left operand
+/// * `right` - Result of the right operand
+///
+/// # Returns
+/// The column types of the combined result
+fn check_compatible(
+    op: &ast::SetOperator,
+    left: &Table,
+    right: &Table,
+) -> DBResult<Vec<ColumnTypeSpecific>> {
+    if left.get_column_num() != right.get_column_num() {
+        Err(DBSingleError::OtherError(format!(
+            "each {} query must have the same number of columns: {} and {}",
+            op,
+            left.get_column_num(),
+            right.get_column_num()
+        )))?
+    }
+    left.infer_column_types()
+        .into_iter()
+        .zip(right.infer_column_types())
+        .map(|(left_type, right_type)| match (left_type, right_type) {
+            (ColumnTypeSpecific::Any, t) | (t, ColumnTypeSpecific::Any) => Ok(t),
+            // wide enough for the values of both operands
+            (
+                ColumnTypeSpecific::Varchar { max_length: l },
+                ColumnTypeSpecific::Varchar { max_length: r },
+            ) => Ok(ColumnTypeSpecific::Varchar {
+                max_length: l.max(r),
+            }),
+            (l, r) if l.is_same_kind(&r) => Ok(l),
+            (l, r) => Err(DBSingleError::OtherError(format!(
+                "{} types {} and {} cannot be matched",
+                op, l, r
+            )))?,
+        })
+        .collect()
+}
+
+/// Counts the occurrences of each row of a table.
+fn count_rows(table: &Table) -> HashMap<&Vec<Value>, usize> {
+    let mut counts = HashMap::new();
+    for row in table.existed_rows() {
+        *counts.entry(row).or_insert(0) += 1;
+    }
+    counts
+}
+
+impl SQLExecutor {
+    /// Evaluates a set operation between two query bodies.
+    ///
+    /// The result takes its column names from the left operand. Rows keep
+    /// the order of the left operand, followed by the right one for UNION.
+    /// Without ALL, duplicate rows are removed from the result.
+    ///
+    /// # Arguments
+    /// * `op` - The set operator
+    /// * `set_quantifier` - ALL or DISTINCT
+    /// * `left` - Left operand
+    /// * `right` - Right operand
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// New table containing the combined rows
+    pub(super) fn evaluate_set_operation(
+        &self,
+        op: &ast::SetOperator,
+        set_quantifier: &ast::SetQuantifier,
+        left: &ast::SetExpr,
+        right: &ast::SetExpr,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Table> {
+        let is_all = match set_quantifier {
+            ast::SetQuantifier::All => true,
+            ast::SetQuantifier::Distinct | ast::SetQuantifier::None => false,
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported set quantifier {}",
+                set_quantifier
+            )))?,
+        };
+
+        let left = self.evaluate_set_expr(left, executor_state)?;
+        let right = self.evaluate_set_expr(right, executor_state)?;
+        let column_types = check_compatible(op, &left, &right)?;
+
+        let rows: Vec<Vec<Value>> = match op {
+            ast::SetOperator::Union => left
+                .existed_rows()
+                .chain(right.existed_rows())
+                .cloned()
+                .collect(),
+            ast::SetOperator::Intersect => {
+                let mut right_counts = count_rows(&right);
+                left.existed_rows()
+                    .filter(|row| {
+                        right_counts.get_mut(row).is_some_and(|count| {
+                            if *count == 0 {
+                                return false;
+                            }
+                            if is_all {
+                                *count -= 1;
+                            }
+                            true
+                        })
+                    })
+                    .cloned()
+                    .collect()
+            }
+            ast::SetOperator::Except | ast::SetOperator::Minus => {
+                let mut right_counts = count_rows(&right);
+                left.existed_rows()
+                    .filter(|row| match right_counts.get_mut(row) {
+                        Some(count) if *count > 0 => {
+                            if is_all {
+                                *count -= 1;
+                            }
+                            false
+                        }
+                        _ => true,
+                    })
+                    .cloned()
+                    .collect()
+            }
+        };
+
+        let mut columns_info = left.columns_info.clone();
+        for (column, type_specific) in columns_info.iter_mut().zip(column_types) {
+            column.nullable = true;
+            column.unique = false;
+            column.type_specific = type_specific;
+        }
+        let mut new_table = Table::new(columns_info);
+        new_table.row_num = rows.len();
+        new_table.row_idx_acc = rows.len();
+        new_table.rows = rows.into_iter().map(Some).enumerate().collect();
+
+        if !is_all {
+            let key_columns = (0..new_table.get_column_num()).collect::<Vec<_>>();
+            self.table_manager
+                .dedup_rows(&mut new_table, &key_columns)?;
+        }
+        Ok(new_table)
+    }
+}
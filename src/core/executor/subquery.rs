@@ -0,0 +1,644 @@
+//! Subquery evaluation.
+//!
+//! Handles scalar subqueries, `[NOT] IN (<subquery>)` and `[NOT] EXISTS`.
+//!
+//! Before a statement is executed, its subqueries are bound:
+//! - An uncorrelated subquery is evaluated once and replaced by its result
+//!   (a literal, a boolean, or an `IN` list of literals).
+//! - A correlated subquery, referring to columns of the outer query, is
+//!   replaced by a hidden column of the source table, computed for each
+//!   source row by substituting the outer references with the row's values.
+//!   For UPDATE and DELETE, the hidden columns are computed before the
+//!   change, and appended to the changed table while it changes.
+
+use super::explain::Operator;
+use super::query::{SelectSource, expr_column_info};
+use super::table_manager::{CalcFunc, RowChange};
+use super::utils::{expr_children_mut, select_exprs_mut, value_to_expr};
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{ColumnInfo, Table, Value};
+use crate::core::parser::normalize_ident;
+use crate::core::plan::NameResolution;
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::borrow::Cow;
+
+/// A correlated subquery, evaluated for each row of the outer query.
+pub(super) struct CorrelatedSubquery {
+    /// Name of the hidden column holding the results
+    pub column_name: String,
+    /// The subquery expression (scalar, EXISTS or IN), still referring to the outer row
+    pub expr: ast::Expr,
+}
+
+/// The results of the correlated subqueries of an UPDATE or DELETE, for
+/// each existing row of the changed table.
+pub(super) struct CorrelatedColumns {
+    /// Metadata of the hidden columns, one per subquery
+    columns_info: Vec<ColumnInfo>,
+    /// Values of the hidden columns, for each existing row in order
+    values: Vec<Vec<Value>>,
+}
+
+impl CorrelatedColumns {
+    /// Gets the columns an expression with correlated subqueries is bound
+    /// to: the columns of the table, followed by the hidden columns.
+    ///
+    /// # Arguments
+    /// * `table` - The changed table
+    /// * `correlated` - The correlated subqueries of the statement
+    pub(super) fn header<'a>(
+        table: &'a Table,
+        correlated: &[CorrelatedSubquery],
+    ) -> Cow<'a, Table> {
+        if correlated.is_empty() {
+            return Cow::Borrowed(table);
+        }
+        let mut columns_info = table.columns_info.clone();
+        columns_info.extend(
+            correlated
+                .iter()
+                .map(|subquery| expr_column_info(subquery.column_name.clone())),
+        );
+        Cow::Owned(Table::new(columns_info))
+    }
+
+    /// Changes a table whose rows are extended with the hidden columns while
+    /// it changes. The hidden columns are removed afterwards, also on error,
+    /// and from the values of the changed rows.
+    ///
+    /// # Arguments
+    /// * `table` - The changed table, whose rows did not change since the subqueries were computed
+    /// * `change` - Function changing the table, and returning the changes in order
+    pub(super) fn change(
+        self,
+        table: &mut Table,
+        change: impl FnOnce(&mut Table) -> DBResult<Vec<RowChange>>,
+    ) -> DBResult<Vec<RowChange>> {
+        if self.columns_info.is_empty() {
+            return change(table);
+        }
+        let column_num = table.get_column_num();
+        table.extend_columns(self.columns_info, self.values);
+        let result = change(table);
+        table.truncate_columns(column_num);
+        let mut changes = result?;
+        for change in &mut changes {
+            match change {
+                RowChange::Insert { new_row, .. } => new_row.truncate(column_num),
+                RowChange::Update {
+                    old_row, new_row, ..
+                } => {
+                    old_row.truncate(column_num);
+                    new_row.truncate(column_num);
+                }
+                RowChange::Delete { old_row, .. } => old_row.truncate(column_num),
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Resolves a column reference against a source.
+///
+/// # Arguments
+/// * `expr` - Expression that may be a column reference
+/// * `source` - Source to resolve against
+/// * `resolution` - How identifiers are resolved; leniently, quoted identifiers are not columns
+///
+/// # Returns
+/// The index of the referenced column, None if `expr` does not refer to a column of `source`
+fn resolve_column(
+    expr: &ast::Expr,
+    source: &SelectSource,
+    resolution: NameResolution,
+) -> Option<usize> {
+    match expr {
+        ast::Expr::Identifier(ident)
+            if resolution == NameResolution::Strict || ident.quote_style.is_none() =>
+        {
+            source.table.get_column_index(&normalize_ident(ident))
+        }
+        ast::Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [qualifier, column] if source.name == Some(normalize_ident(qualifier)) => {
+                source.table.get_column_index(&normalize_ident(column))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Checks whether binding rewrites an expression, see [`SQLExecutor::bind_expr`],
+/// without binding it.
+///
+/// # Arguments
+/// * `expr` - Expression to check
+/// * `source` - Source the expression would be bound to
+/// * `executor_state` - Current executor state for evaluation context
+fn is_rewritten_by_binding(
+    expr: &mut ast::Expr,
+    source: &SelectSource,
+    executor_state: &SQLExecutorState,
+) -> bool {
+    match expr {
+        ast::Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [qualifier, _] => {
+                source.name == Some(normalize_ident(qualifier))
+                    || executor_state
+                        .trigger_rows
+                        .contains_key(&qualifier.value.to_lowercase())
+            }
+            _ => false,
+        },
+        ast::Expr::Subquery(_) | ast::Expr::Exists { .. } | ast::Expr::InSubquery { .. } => true,
+        _ => expr_children_mut(expr)
+            .into_iter()
+            .any(|child| is_rewritten_by_binding(child, source, executor_state)),
+    }
+}
+
+impl SQLExecutor {
+    /// Evaluates a subquery expression into an expression free of subqueries.
+    ///
+    /// # Arguments
+    /// * `expr` - A scalar, EXISTS or IN subquery expression without outer references
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// A literal for scalar and EXISTS subqueries, an IN list of literals for IN subqueries
+    fn evaluate_subquery_expr(
+        &self,
+        expr: &ast::Expr,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<ast::Expr> {
+        let single_column_values = |table: Table| -> DBResult<Vec<Value>> {
+            if table.get_column_num() != 1 {
+                Err(DBSingleError::OtherError(
+                    "subquery must return only one column".into(),
+                ))?
+            }
+            Ok(table.rows.into_values().flatten().flatten().collect())
+        };
+        let evaluate = |query: &ast::Query| {
+            executor_state.explain(
+                false,
+                || self.evaluate_query(query, executor_state),
+                |table| Operator::new("Subquery", table.get_row_num()),
+            )
+        };
+        Ok(match expr {
+            ast::Expr::Subquery(query) => {
+                let values = single_column_values(evaluate(query)?)?;
+                if values.len() > 1 {
+                    Err(DBSingleError::OtherError(
+                        "more than one row returned by a subquery used as an expression".into(),
+                    ))?
+                }
+                value_to_expr(values.into_iter().next().unwrap_or_default())
+            }
+            ast::Expr::Exists { subquery, negated } => {
+                let table = evaluate(subquery)?;
+                ast::Expr::value(ast::Value::Boolean((table.get_row_num() > 0) != *negated))
+            }
+            ast::Expr::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => {
+                let values = single_column_values(executor_state.explain(
+                    false,
+                    || self.evaluate_set_expr(subquery, executor_state),
+                    |table| Operator::new("Subquery", table.get_row_num()),
+                )?)?;
+                ast::Expr::InList {
+                    expr: expr.clone(),
+                    list: values.into_iter().map(value_to_expr).collect(),
+                    negated: *negated,
+                }
+            }
+            _ => panic!("Should not reach here"),
+        })
+    }
+
+    /// Finds the references to an outer source inside a query, and optionally
+    /// replaces them with the values of an outer row.
+    ///
+    /// Columns of the query's own sources (and of the sources of enclosing
+    /// subqueries up to the outer one) shadow the outer columns.
+    ///
+    /// # Arguments
+    /// * `query` - Query to search
+    /// * `outer` - The outer source
+    /// * `row` - Row of the outer source to substitute, None to only search
+    /// * `inner_sources` - Sources of the enclosing subqueries, innermost last
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// Whether any outer reference was found
+    fn bind_outer_refs<'a>(
+        &'a self,
+        query: &mut ast::Query,
+        outer: &SelectSource,
+        row: Option<&[Value]>,
+        inner_sources: &mut Vec<SelectSource<'a>>,
+        executor_state: &'a SQLExecutorState,
+    ) -> DBResult<bool> {
+        if let Some(with) = &query.with {
+            let executor_state = self.evaluate_with(with, executor_state)?;
+            let mut inner_sources = inner_sources.iter().map(SelectSource::reborrow).collect();
+            return self.bind_outer_refs_in_query(
+                query,
+                outer,
+                row,
+                &mut inner_sources,
+                &executor_state,
+            );
+        }
+        self.bind_outer_refs_in_query(query, outer, row, inner_sources, executor_state)
+    }
+
+    /// Same as [`bind_outer_refs`](Self::bind_outer_refs), ignoring the WITH clause of the query.
+    fn bind_outer_refs_in_query<'a>(
+        &'a self,
+        query: &mut ast::Query,
+        outer: &SelectSource,
+        row: Option<&[Value]>,
+        inner_sources: &mut Vec<SelectSource<'a>>,
+        executor_state: &'a SQLExecutorState,
+    ) -> DBResult<bool> {
+        let mut found = false;
+        if let ast::SetExpr::Select(select) = query.body.as_mut() {
+            let source = self.parse_table_from_select(select, executor_state)?;
+            inner_sources.push(source);
+            if let Some(ast::OrderByKind::Expressions(order_by_exprs)) =
+                query.order_by.as_mut().map(|order_by| &mut order_by.kind)
+            {
+                for order_by_expr in order_by_exprs {
+                    found |= self.bind_outer_refs_in_expr(
+                        &mut order_by_expr.expr,
+                        outer,
+                        row,
+                        inner_sources,
+                        executor_state,
+                    )?;
+                }
+            }
+            inner_sources.pop();
+        }
+        found |= self.bind_outer_refs_in_set_expr(
+            &mut query.body,
+            outer,
+            row,
+            inner_sources,
+            executor_state,
+        )?;
+        Ok(found)
+    }
+
+    /// Same as [`bind_outer_refs`](Self::bind_outer_refs), for a query body.
+    fn bind_outer_refs_in_set_expr<'a>(
+        &'a self,
+        body: &mut ast::SetExpr,
+        outer: &SelectSource,
+        row: Option<&[Value]>,
+        inner_sources: &mut Vec<SelectSource<'a>>,
+        executor_state: &'a SQLExecutorState,
+    ) -> DBResult<bool> {
+        let mut found = false;
+        match body {
+            ast::SetExpr::Select(select) => {
+                let source = self.parse_table_from_select(select, executor_state)?;
+                inner_sources.push(source);
+                for expr in select_exprs_mut(select) {
+                    found |= self.bind_outer_refs_in_expr(
+                        expr,
+                        outer,
+                        row,
+                        inner_sources,
+                        executor_state,
+                    )?;
+                }
+                inner_sources.pop();
+            }
+            ast::SetExpr::Query(query) => {
+                found |= self.bind_outer_refs(query, outer, row, inner_sources, executor_state)?;
+            }
+            ast::SetExpr::SetOperation { left, right, .. } => {
+                found |= self.bind_outer_refs_in_set_expr(
+                    left,
+                    outer,
+                    row,
+                    inner_sources,
+                    executor_state,
+                )?;
+                found |= self.bind_outer_refs_in_set_expr(
+                    right,
+                    outer,
+                    row,
+                    inner_sources,
+                    executor_state,
+                )?;
+            }
+            _ => {}
+        }
+        Ok(found)
+    }
+
+    /// Same as [`bind_outer_refs`](Self::bind_outer_refs), for an expression.
+    fn bind_outer_refs_in_expr<'a>(
+        &'a self,
+        expr: &mut ast::Expr,
+        outer: &SelectSource,
+        row: Option<&[Value]>,
+        inner_sources: &mut Vec<SelectSource<'a>>,
+        executor_state: &'a SQLExecutorState,
+    ) -> DBResult<bool> {
+        if matches!(
+            expr,
+            ast::Expr::Identifier(_) | ast::Expr::CompoundIdentifier(_)
+        ) {
+            if inner_sources
+                .iter()
+                .any(|source| resolve_column(expr, source, self.name_resolution()).is_some())
+            {
+                return Ok(false);
+            }
+            let Some(index) = resolve_column(expr, outer, self.name_resolution()) else {
+                return Ok(false);
+            };
+            if let Some(row) = row {
+                *expr = value_to_expr(row[index].clone());
+            }
+            return Ok(true);
+        }
+
+        let mut found = false;
+        match expr {
+            ast::Expr::Subquery(query)
+            | ast::Expr::Exists {
+                subquery: query, ..
+            } => {
+                found |= self.bind_outer_refs(query, outer, row, inner_sources, executor_state)?;
+            }
+            ast::Expr::InSubquery { subquery, .. } => {
+                found |= self.bind_outer_refs_in_set_expr(
+                    subquery,
+                    outer,
+                    row,
+                    inner_sources,
+                    executor_state,
+                )?;
+            }
+            _ => {}
+        }
+        for child in expr_children_mut(expr) {
+            found |=
+                self.bind_outer_refs_in_expr(child, outer, row, inner_sources, executor_state)?;
+        }
+        Ok(found)
+    }
+
+    /// Binds the column references and subqueries of an expression evaluated
+    /// against the rows of a source.
+    ///
+    /// Qualified references to the source (`<name>.<column>`) are replaced by
+    /// plain column references, references to the NEW and OLD rows of a
+    /// firing trigger by their values, and uncorrelated subqueries by their
+    /// results.
+    /// Correlated subqueries are replaced by references to hidden columns and
+    /// collected in `correlated`; if `correlated` is None, they are rejected.
+    ///
+    /// # Arguments
+    /// * `expr` - Expression to bind
+    /// * `source` - The source the expression is evaluated against
+    /// * `correlated` - Collected correlated subqueries, None if not allowed
+    /// * `executor_state` - Current executor state for evaluation context
+    pub(super) fn bind_expr(
+        &self,
+        expr: &mut ast::Expr,
+        source: &SelectSource,
+        correlated: &mut Option<&mut Vec<CorrelatedSubquery>>,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<()> {
+        match expr {
+            ast::Expr::CompoundIdentifier(idents) => {
+                let [qualifier, column] = idents.as_slice() else {
+                    return Ok(());
+                };
+                if source.name == Some(normalize_ident(qualifier)) {
+                    *expr = ast::Expr::Identifier(column.clone());
+                } else if let Some(row) = executor_state
+                    .trigger_rows
+                    .get(&qualifier.value.to_lowercase())
+                {
+                    // NEW or OLD in the body of a trigger
+                    let value = row.get(&normalize_ident(column)).ok_or_else(|| {
+                        DBSingleError::OtherError(format!("column not found: {}", expr))
+                    })?;
+                    *expr = value_to_expr(value.clone());
+                }
+                return Ok(());
+            }
+            ast::Expr::Subquery(_) | ast::Expr::Exists { .. } | ast::Expr::InSubquery { .. } => {}
+            _ => {
+                for child in expr_children_mut(expr) {
+                    self.bind_expr(child, source, correlated, executor_state)?;
+                }
+                return Ok(());
+            }
+        }
+
+        if let ast::Expr::InSubquery { expr, .. } = expr {
+            self.bind_expr(expr, source, correlated, executor_state)?;
+        }
+        // the sources read to search for outer references are not explained
+        let unexplained = executor_state.unexplained();
+        let search_state = unexplained.as_ref().unwrap_or(executor_state);
+        let is_correlated = match expr {
+            ast::Expr::Subquery(query)
+            | ast::Expr::Exists {
+                subquery: query, ..
+            } => {
+                self.bind_outer_refs(&mut query.clone(), source, None, &mut vec![], search_state)?
+            }
+            ast::Expr::InSubquery { subquery, .. } => self.bind_outer_refs_in_set_expr(
+                &mut subquery.clone(),
+                source,
+                None,
+                &mut vec![],
+                search_state,
+            )?,
+            _ => unreachable!(),
+        };
+
+        if !is_correlated {
+            *expr = self.evaluate_subquery_expr(expr, executor_state)?;
+            return Ok(());
+        }
+        let Some(correlated) = correlated else {
+            Err(DBSingleError::UnsupportedOPError(
+                "correlated subqueries are not supported here".into(),
+            ))?
+        };
+        let column_name = format!("#subquery{}", correlated.len());
+        let subquery_expr = std::mem::replace(
+            expr,
+            ast::Expr::Identifier(ast::Ident::new(column_name.clone())),
+        );
+        correlated.push(CorrelatedSubquery {
+            column_name,
+            expr: subquery_expr,
+        });
+        Ok(())
+    }
+
+    /// Binds the expressions of a SELECT and its ORDER BY, see [`bind_expr`](Self::bind_expr).
+    ///
+    /// # Arguments
+    /// * `select` - SELECT to bind
+    /// * `order_by` - ORDER BY expressions to bind
+    /// * `source` - Source of the SELECT
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// The correlated subqueries of the SELECT
+    pub(super) fn bind_select(
+        &self,
+        select: &mut ast::Select,
+        order_by: &mut [ast::OrderByExpr],
+        source: &SelectSource,
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Vec<CorrelatedSubquery>> {
+        // keep the names of the result columns, as written in the query
+        for select_item in select.projection.iter_mut() {
+            if let ast::SelectItem::UnnamedExpr(expr) = select_item
+                && is_rewritten_by_binding(expr, source, executor_state)
+            {
+                let alias = match &*expr {
+                    // a qualified column of the source is named after the column
+                    ast::Expr::CompoundIdentifier(idents)
+                        if source.name == Some(normalize_ident(&idents[0])) =>
+                    {
+                        idents[1].clone()
+                    }
+                    // quoted, so that the name is kept as written
+                    _ => ast::Ident::with_quote('"', self.get_expr_name(expr, executor_state)),
+                };
+                *select_item = ast::SelectItem::ExprWithAlias {
+                    alias,
+                    expr: expr.clone(),
+                };
+            }
+        }
+
+        let mut correlated = vec![];
+        let exprs = select_exprs_mut(select).into_iter().chain(
+            order_by
+                .iter_mut()
+                .map(|order_by_expr| &mut order_by_expr.expr),
+        );
+        for expr in exprs {
+            self.bind_expr(expr, source, &mut Some(&mut correlated), executor_state)?;
+        }
+        Ok(correlated)
+    }
+
+    /// Evaluates a correlated subquery for a row of its outer source.
+    ///
+    /// # Arguments
+    /// * `subquery` - The correlated subquery
+    /// * `outer` - The outer source
+    /// * `row` - Row of the outer source
+    /// * `executor_state` - Current executor state for evaluation context
+    pub(super) fn evaluate_correlated_subquery(
+        &self,
+        subquery: &CorrelatedSubquery,
+        outer: &SelectSource,
+        row: &[Value],
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Value> {
+        let mut expr = subquery.expr.clone();
+        match &mut expr {
+            ast::Expr::Subquery(query)
+            | ast::Expr::Exists {
+                subquery: query, ..
+            } => self.bind_outer_refs(query, outer, Some(row), &mut vec![], executor_state)?,
+            ast::Expr::InSubquery { subquery, .. } => self.bind_outer_refs_in_set_expr(
+                subquery,
+                outer,
+                Some(row),
+                &mut vec![],
+                executor_state,
+            )?,
+            _ => unreachable!(),
+        };
+        let expr = self.evaluate_subquery_expr(&expr, executor_state)?;
+        outer
+            .table
+            .calc_expr_for_row(row, &expr, self.name_resolution())
+    }
+
+    /// Evaluates the correlated subqueries of an UPDATE or DELETE for each
+    /// existing row of the changed table.
+    ///
+    /// # Arguments
+    /// * `source` - The changed table and the name it is referred to by
+    /// * `correlated` - The correlated subqueries of the statement
+    /// * `executor_state` - Current executor state for evaluation context
+    pub(super) fn evaluate_correlated_columns(
+        &self,
+        source: &SelectSource,
+        correlated: &[CorrelatedSubquery],
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<CorrelatedColumns> {
+        let columns_info = correlated
+            .iter()
+            .map(|subquery| expr_column_info(subquery.column_name.clone()))
+            .collect::<Vec<_>>();
+        if correlated.is_empty() {
+            return Ok(CorrelatedColumns {
+                columns_info,
+                values: vec![],
+            });
+        }
+
+        // the subqueries are evaluated for each row, as part of this operator
+        let unexplained = executor_state.unexplained();
+        let subquery_state = unexplained.as_ref().unwrap_or(executor_state);
+        let calc_funcs = correlated
+            .iter()
+            .map(|subquery| -> CalcFunc {
+                Box::new(move |row| {
+                    self.evaluate_correlated_subquery(subquery, source, row, subquery_state)
+                })
+            })
+            .collect();
+        let table = executor_state.explain(
+            false,
+            || {
+                self.table_manager.construct_table_from_calc_func(
+                    &source.table,
+                    columns_info.clone(),
+                    calc_funcs,
+                    None,
+                )
+            },
+            |table| {
+                Operator::new("Correlated Subqueries", table.get_row_num()).detail(
+                    "Subqueries",
+                    ast::display_comma_separated(
+                        &correlated
+                            .iter()
+                            .map(|subquery| &subquery.expr)
+                            .collect::<Vec<_>>(),
+                    ),
+                )
+            },
+        )?;
+        Ok(CorrelatedColumns {
+            columns_info,
+            values: table.rows.into_values().flatten().collect(),
+        })
+    }
+}
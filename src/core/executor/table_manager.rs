@@ -1,54 +1,289 @@
 //! TableManager trait and its implementations for managing tables in a database.
 //! It provides methods for inserting, deleting, updating rows, constructing new tables,
 //! and converting ORDER BY clauses.
+//!
+//! Expressions are passed already bound to the columns of the table they are
+//! evaluated against and compiled, see [`plan`](crate::core::plan).
 
 pub mod parallel;
 pub mod sequential;
 
+use super::aggregate::AggregateCall;
+use super::window::WindowCall;
 use crate::core::data_structure::{ColumnInfo, Table, Value};
+use crate::core::plan::{BoundAssignment, CompiledExpr};
 use crate::error::DBResult;
 pub use parallel::ParallelTableManager;
 pub use sequential::SequentialTableManager;
-use sqlparser::ast;
+use std::collections::HashMap;
+
+/// A change made to a row of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    /// A row was inserted at the index with the values
+    Insert { row_idx: usize, new_row: Vec<Value> },
+    /// The row at the index was updated from the previous values to the new ones
+    Update {
+        row_idx: usize,
+        old_row: Vec<Value>,
+        new_row: Vec<Value>,
+    },
+    /// The row at the index was deleted
+    Delete { row_idx: usize, old_row: Vec<Value> },
+}
+
+impl RowChange {
+    /// Gets the index of the changed row.
+    pub fn row_idx(&self) -> usize {
+        match self {
+            RowChange::Insert { row_idx, .. }
+            | RowChange::Update { row_idx, .. }
+            | RowChange::Delete { row_idx, .. } => *row_idx,
+        }
+    }
+
+    /// Takes the values of the changed row right after the change, None for
+    /// a deletion.
+    pub fn into_new_row(self) -> Option<Vec<Value>> {
+        match self {
+            RowChange::Insert { new_row, .. } | RowChange::Update { new_row, .. } => Some(new_row),
+            RowChange::Delete { .. } => None,
+        }
+    }
+}
+
+/// Gets the row at the index of a table, which should exist.
+fn existing_row(table: &Table, row_idx: usize) -> Vec<Value> {
+    table.rows[&row_idx].clone().expect("row should exist")
+}
+
+/// Gets the changes made by inserting rows, right after they were inserted.
+///
+/// # Arguments
+/// * `table` - The changed table
+/// * `row_indices` - Indices of the inserted rows
+pub fn inserted(table: &Table, row_indices: Vec<usize>) -> Vec<RowChange> {
+    row_indices
+        .into_iter()
+        .map(|row_idx| RowChange::Insert {
+            row_idx,
+            new_row: existing_row(table, row_idx),
+        })
+        .collect()
+}
+
+/// Gets the changes made by updating rows, right after they were updated.
+///
+/// # Arguments
+/// * `table` - The changed table
+/// * `rows` - Indices and previous values of the updated rows
+pub fn updated(table: &Table, rows: Vec<(usize, Vec<Value>)>) -> Vec<RowChange> {
+    rows.into_iter()
+        .map(|(row_idx, old_row)| RowChange::Update {
+            row_idx,
+            old_row,
+            new_row: existing_row(table, row_idx),
+        })
+        .collect()
+}
+
+/// The rows of a table changed by a statement, as they were at some point.
+#[derive(Debug, Clone)]
+pub struct ChangedRows {
+    /// Indices of the changed rows and their values, None if deleted or not
+    /// inserted yet
+    rows: Vec<(usize, Option<Vec<Value>>)>,
+    /// The row index accumulator of the table
+    row_idx_acc: usize,
+}
+
+impl ChangedRows {
+    /// Gets the changed rows as they are now.
+    ///
+    /// # Arguments
+    /// * `table` - The changed table
+    /// * `changes` - The changes made to the table, in order
+    pub fn current(table: &Table, changes: &[RowChange]) -> Self {
+        let mut row_indices = changes.iter().map(RowChange::row_idx).collect::<Vec<_>>();
+        row_indices.sort_unstable();
+        row_indices.dedup();
+        ChangedRows {
+            rows: row_indices
+                .into_iter()
+                .map(|row_idx| (row_idx, table.rows.get(&row_idx).cloned().flatten()))
+                .collect(),
+            row_idx_acc: table.row_idx_acc,
+        }
+    }
+
+    /// Gets the changed rows as they were before the first change.
+    ///
+    /// # Arguments
+    /// * `table` - The changed table
+    /// * `changes` - The changes made to the table, in order
+    pub fn original(table: &Table, changes: &[RowChange]) -> Self {
+        let mut rows = HashMap::new();
+        let mut row_idx_acc = table.row_idx_acc;
+        for change in changes.iter().rev() {
+            let (row_idx, row) = match change {
+                RowChange::Insert { row_idx, .. } => {
+                    row_idx_acc = *row_idx;
+                    (*row_idx, None)
+                }
+                RowChange::Update {
+                    row_idx, old_row, ..
+                }
+                | RowChange::Delete { row_idx, old_row } => (*row_idx, Some(old_row.clone())),
+            };
+            rows.insert(row_idx, row);
+        }
+        ChangedRows {
+            rows: rows.into_iter().collect(),
+            row_idx_acc,
+        }
+    }
+
+    /// Restores the rows in the table, keeping the row count and the unique
+    /// values of the columns in step.
+    ///
+    /// The constraints are not checked, as the restored rows held them
+    /// together before.
+    ///
+    /// # Arguments
+    /// * `table` - The changed table
+    pub fn restore(self, table: &mut Table) {
+        let unique_columns = (0..table.get_column_num())
+            .filter(|&i| table.get_column_info(i).unique)
+            .collect::<Vec<_>>();
+        // values may have moved between the changed rows, so all of them are
+        // removed before any is restored
+        for (row_idx, _) in &self.rows {
+            if let Some(Some(row)) = table.rows.get(row_idx) {
+                for &col_idx in &unique_columns {
+                    table.columns_values[col_idx].remove(&row[col_idx]);
+                }
+                table.row_num -= 1;
+            }
+        }
+        for (row_idx, row) in self.rows {
+            match &row {
+                Some(row) => {
+                    for &col_idx in &unique_columns {
+                        table.columns_values[col_idx].insert(row[col_idx].clone());
+                    }
+                    table.row_num += 1;
+                }
+                // a row not inserted yet leaves no deleted row behind
+                None if row_idx >= self.row_idx_acc => {
+                    table.rows.remove(&row_idx);
+                    continue;
+                }
+                None => {}
+            }
+            table.rows.insert(row_idx, row);
+        }
+        table.row_idx_acc = self.row_idx_acc;
+    }
+}
+
+/// Undoes changes made to a table, restoring the rows as they were before
+/// the first change; see [`ChangedRows::restore`].
+///
+/// # Arguments
+/// * `table` - The changed table
+/// * `changes` - The changes made to the table, in order
+pub fn undo_changes(table: &mut Table, changes: &[RowChange]) {
+    ChangedRows::original(table, changes).restore(table);
+}
 
 pub type CalcFunc<'a> = Box<dyn Fn(&[Value]) -> DBResult<Value> + Send + Sync + 'a>;
 
-pub trait TableManager {
+/// Runs the operations on tables.
+///
+/// The methods changing the rows of a table are atomic: when one fails, the
+/// table is left as it was, except for its modification count.
+pub trait TableManager: Send + Sync {
+    /// Gets the name of the table manager, as shown by EXPLAIN.
+    fn name(&self) -> &'static str;
+
     /// Inserts rows into the table.
     ///
     /// # Arguments
     /// * `table` - The table to insert rows into
-    /// * `raw_rows` - Rows to be inserted, each row is a vector of expressions
+    /// * `raw_rows` - Rows to be inserted, each row is a vector of constant expressions
     /// * `columns_indicator` - List of column names corresponding to the expressions in `raw_rows`
     ///
     /// # Returns
-    /// A result indicating success or failure of the operation
+    /// Indices of the inserted rows
     fn insert_rows(
         &self,
         table: &mut Table,
-        raw_rows: &[Vec<ast::Expr>],
+        raw_rows: &[Vec<CompiledExpr>],
         columns_indicator: Vec<String>,
-    ) -> DBResult<()>;
+    ) -> DBResult<Vec<usize>>;
+
+    /// Inserts rows of values into the table.
+    ///
+    /// # Arguments
+    /// * `table` - The table to insert rows into
+    /// * `rows` - Rows to be inserted, each row holding one value per column of the table
+    ///
+    /// # Returns
+    /// Indices of the inserted rows
+    fn insert_values(&self, table: &mut Table, rows: Vec<Vec<Value>>) -> DBResult<Vec<usize>>;
 
     /// Deletes rows by their indices.
     ///
+    /// The modification count of the table is bumped before the first row
+    /// is deleted.
+    ///
     /// # Arguments
     /// * `table` - The table from which to delete rows
     /// * `cond` - Optional condition to filter which rows to delete
-    fn delete_rows(&self, table: &mut Table, cond: Option<&ast::Expr>) -> DBResult<()>;
+    ///
+    /// # Returns
+    /// Indices and values of the deleted rows, in row order
+    fn delete_rows(
+        &self,
+        table: &mut Table,
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Vec<(usize, Vec<Value>)>>;
 
     /// Updates rows by their indices.
     ///
+    /// The new values are evaluated for all the rows first, and the
+    /// modification count of the table is bumped before the first row is
+    /// updated.
+    ///
     /// # Arguments
     /// * `table` - The table in which to update rows
     /// * `assignments` - List of assignments indicating which columns to update and their new values
     /// * `cond` - Optional condition to filter which rows to update
+    ///
+    /// # Returns
+    /// Indices and previous values of the updated rows, in row order
     fn update_rows(
         &self,
         table: &mut Table,
-        assignments: &[ast::Assignment],
-        cond: Option<&ast::Expr>,
-    ) -> DBResult<()>;
+        assignments: &[BoundAssignment],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Vec<(usize, Vec<Value>)>>;
+
+    /// Replaces the values of a row.
+    ///
+    /// # Arguments
+    /// * `table` - The table containing the row
+    /// * `row_idx` - Index of the row to replace
+    /// * `row` - New values of the row, one per column of the table
+    ///
+    /// # Returns
+    /// Previous values of the row
+    fn replace_row(
+        &self,
+        table: &mut Table,
+        row_idx: usize,
+        row: Vec<Value>,
+    ) -> DBResult<Vec<Value>>;
 
     /// Constructs a new table based on the provided calculation functions.
     ///
@@ -65,13 +300,54 @@ pub trait TableManager {
         table: &Table,
         columns_info: Vec<ColumnInfo>,
         calc_funcs: Vec<CalcFunc>,
-        cond: Option<&ast::Expr>,
+        cond: Option<&CompiledExpr>,
     ) -> DBResult<Table>;
 
+    /// Groups the rows of a table and computes aggregates for each group.
+    ///
+    /// # Arguments
+    /// * `table` - The table to aggregate
+    /// * `group_by` - Expressions whose values form the group key
+    /// * `calls` - Aggregate calls to compute for each group
+    /// * `cond` - Optional condition to filter which rows to aggregate
+    ///
+    /// # Returns
+    /// A table as described in [`construct_aggregate_table`](super::aggregate::construct_aggregate_table)
+    fn aggregate(
+        &self,
+        table: &Table,
+        group_by: &[CompiledExpr],
+        calls: &[AggregateCall],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Table>;
+
+    /// Computes window function calls over the rows of a table.
+    ///
+    /// # Arguments
+    /// * `table` - The table whose rows are computed
+    /// * `calls` - Window function calls to compute
+    /// * `cond` - Optional condition to filter which rows to keep, applied before computing the windows
+    ///
+    /// # Returns
+    /// A table as described in [`construct_window_table`](super::window::construct_window_table)
+    fn window(
+        &self,
+        table: &Table,
+        calls: &[WindowCall],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Table>;
+
+    /// Removes rows that duplicate an earlier row on the given columns.
+    ///
+    /// # Arguments
+    /// * `table` - The table to deduplicate
+    /// * `key_columns` - Indices of the columns compared between rows
+    fn dedup_rows(&self, table: &mut Table, key_columns: &[usize]) -> DBResult<()>;
+
     /// Converts the ORDER BY clause into a format suitable for the table manager.
     ///
     /// # Arguments
     /// * `table` - The table on which to apply the ORDER BY clause
     /// * `keys` - A list of expressions and their sort order (ascending/descending)
-    fn convert_order_by(&self, table: &mut Table, keys: &[(&ast::Expr, bool)]) -> DBResult<()>;
+    fn convert_order_by(&self, table: &mut Table, keys: &[(CompiledExpr, bool)]) -> DBResult<()>;
 }
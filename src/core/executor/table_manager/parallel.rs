@@ -1,11 +1,17 @@
-use super::TableManager;
+use super::{TableManager, undo_changes, updated};
 use crate::core::data_structure::{ColumnInfo, Table, Value};
+use crate::core::executor::aggregate::{AggregateCall, AggregateGroup, construct_aggregate_table};
+use crate::core::executor::window::{WindowCall, construct_window_table};
+use crate::core::plan::{BoundAssignment, CompiledExpr, is_row_satisfy_cond};
 use crate::error::{DBResult, DBSingleError};
 use rayon::prelude::*;
-use sqlparser::ast;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
 
+/// Minimum number of rows for [`ParallelTableManager::dedup_rows`] to
+/// deduplicate in parallel; smaller tables are deduplicated sequentially.
+const PARALLEL_DEDUP_THRESHOLD: usize = 4096;
+
 /// A parallel implementation of the `TableManager` trait.
 /// This manager uses Rayon for parallel processing of table operations.
 pub struct ParallelTableManager;
@@ -52,10 +58,8 @@ impl ParallelTableManager {
                     column_values.insert(value_to_add.clone());
                     is_duplicate = false;
                 }
-                if !is_duplicate {
-                    if let Some(value_to_delete) = value_to_delete {
-                        column_values.remove(value_to_delete);
-                    }
+                if !is_duplicate && let Some(value_to_delete) = value_to_delete {
+                    column_values.remove(value_to_delete);
                 }
             }
             if is_duplicate {
@@ -76,28 +80,38 @@ fn get_mutexed_columns_values(
 }
 
 impl TableManager for ParallelTableManager {
+    fn name(&self) -> &'static str {
+        "parallel"
+    }
+
     fn insert_rows(
         &self,
         table: &mut Table,
-        raw_rows: &[Vec<ast::Expr>],
+        raw_rows: &[Vec<CompiledExpr>],
         columns_indicator: Vec<String>,
-    ) -> DBResult<()> {
-        let base_row_idx = table.row_idx_acc;
-        table.row_idx_acc += raw_rows.len();
-        table.row_num += raw_rows.len();
-
-        let table_confine_header = unsafe { &*(table as *const Table) };
-        let column_values = get_mutexed_columns_values(&mut table.columns_values);
-        let insert_rows = raw_rows
+    ) -> DBResult<Vec<usize>> {
+        let table_confine_header = &*table;
+        let rows = raw_rows
             .par_iter()
-            .enumerate()
-            .map(|(local_idx, raw_row)| -> DBResult<_> {
-                let row_idx = base_row_idx + local_idx;
-                let row = crate::core::executor::insert::parse_raw_row_and_rearrange(
+            .map(|raw_row| {
+                crate::core::executor::insert::parse_raw_row_and_rearrange(
                     table_confine_header,
                     raw_row,
                     &columns_indicator,
-                )?;
+                )
+            })
+            .collect::<DBResult<Vec<_>>>()?;
+        self.insert_values(table, rows)
+    }
+
+    fn insert_values(&self, table: &mut Table, rows: Vec<Vec<Value>>) -> DBResult<Vec<usize>> {
+        let base_row_idx = table.row_idx_acc;
+        let column_values = get_mutexed_columns_values(&mut table.columns_values);
+        let insert_rows = rows
+            .into_par_iter()
+            .enumerate()
+            .map(|(local_idx, row)| -> DBResult<_> {
+                let row_idx = base_row_idx + local_idx;
                 if row.len() != table.columns_info.len() {
                     Err(DBSingleError::OtherError(format!(
                         "row length {} not match columns num {}",
@@ -106,102 +120,192 @@ impl TableManager for ParallelTableManager {
                     )))?
                 }
                 for (col_idx, value) in row.iter().enumerate() {
-                    self.update_column_values(
+                    if let Err(err) = self.update_column_values(
                         &table.columns_info[col_idx],
                         &column_values[col_idx],
                         None,
                         Some(value),
-                    )?;
+                    ) {
+                        // remove the values of the columns already checked
+                        for (restore_idx, value) in row[..col_idx].iter().enumerate() {
+                            self.update_column_values(
+                                &table.columns_info[restore_idx],
+                                &column_values[restore_idx],
+                                Some(value),
+                                None,
+                            )?;
+                        }
+                        return Err(err);
+                    }
                 }
-                Ok((row_idx, Some(row)))
+                Ok((row_idx, row))
             })
-            .collect::<DBResult<Vec<_>>>()?;
-        table.rows.par_extend(insert_rows.into_par_iter());
-        Ok(())
-    }
+            .collect::<Vec<_>>();
 
-    fn delete_rows(&self, table: &mut Table, cond: Option<&ast::Expr>) -> DBResult<()> {
-        let table_confine_header = unsafe { &*(table as *const Table) };
-        let column_values = get_mutexed_columns_values(&mut table.columns_values);
-        let deleted_num = table
-            .rows
-            .par_iter_mut()
-            .map(|(_, opt_row)| -> DBResult<usize> {
-                if opt_row.is_none() {
-                    return Ok(0);
+        let row_count = insert_rows.len();
+        let mut checked_rows = Vec::with_capacity(row_count);
+        let mut first_err = None;
+        for row in insert_rows {
+            match row {
+                Ok(row) => checked_rows.push(row),
+                Err(err) => {
+                    first_err.get_or_insert(err);
                 }
-                let row = opt_row.as_mut().unwrap();
-                if !table_confine_header.is_row_satisfy_cond(row, cond)? {
-                    return Ok(0);
+            }
+        }
+        if let Some(err) = first_err {
+            // remove the values of the rows which were checked
+            checked_rows.par_iter().for_each(|(_, row)| {
+                for (value, column_values) in row.iter().zip(&column_values) {
+                    column_values.lock().unwrap().remove(value);
                 }
-                for (col_idx, value) in row.iter().enumerate() {
-                    self.update_column_values(
-                        &table.columns_info[col_idx],
-                        &column_values[col_idx],
-                        Some(value),
-                        None,
-                    )?;
+            });
+            return Err(err);
+        }
+        table.rows.par_extend(
+            checked_rows
+                .into_par_iter()
+                .map(|(row_idx, row)| (row_idx, Some(row))),
+        );
+        table.row_idx_acc += row_count;
+        table.row_num += row_count;
+        Ok((base_row_idx..table.row_idx_acc).collect())
+    }
+
+    fn delete_rows(
+        &self,
+        table: &mut Table,
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Vec<(usize, Vec<Value>)>> {
+        let row_indices = table
+            .rows
+            .par_iter()
+            .map(|(&row_idx, opt_row)| -> DBResult<Option<usize>> {
+                match opt_row {
+                    Some(row) if is_row_satisfy_cond(row, cond)? => Ok(Some(row_idx)),
+                    _ => Ok(None),
                 }
-                *opt_row = None;
-                Ok(1)
             })
-            .try_reduce(|| 0, |acc, res| Ok(acc + res))?;
-        table.row_num -= deleted_num;
-        Ok(())
+            .collect::<DBResult<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<HashSet<_>>();
+        if row_indices.is_empty() {
+            return Ok(vec![]);
+        }
+        table.modification_count += 1;
+
+        let column_values = get_mutexed_columns_values(&mut table.columns_values);
+        let deleted_rows = table
+            .rows
+            .par_iter_mut()
+            .map(
+                |(&row_idx, opt_row)| -> DBResult<Option<(usize, Vec<Value>)>> {
+                    if !row_indices.contains(&row_idx) {
+                        return Ok(None);
+                    }
+                    let row = opt_row.take().expect("row should exist");
+                    for (col_idx, value) in row.iter().enumerate() {
+                        self.update_column_values(
+                            &table.columns_info[col_idx],
+                            &column_values[col_idx],
+                            Some(value),
+                            None,
+                        )?;
+                    }
+                    Ok(Some((row_idx, row)))
+                },
+            )
+            .collect::<DBResult<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        table.row_num -= deleted_rows.len();
+        Ok(deleted_rows)
     }
 
     fn update_rows(
         &self,
         table: &mut Table,
-        assignments: &[ast::Assignment],
-        cond: Option<&ast::Expr>,
-    ) -> DBResult<()> {
+        assignments: &[BoundAssignment],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Vec<(usize, Vec<Value>)>> {
+        // evaluate the new values first, so that an error there changes nothing
+        let updates = table
+            .rows
+            .par_iter()
+            .map(
+                |(&row_idx, opt_row)| -> DBResult<Option<(usize, Vec<Value>)>> {
+                    let Some(row) = opt_row else {
+                        return Ok(None);
+                    };
+                    if !is_row_satisfy_cond(row, cond)? {
+                        return Ok(None);
+                    }
+                    let values = assignments
+                        .iter()
+                        .map(|assignment| assignment.value.eval(row))
+                        .collect::<DBResult<Vec<_>>>()?;
+                    Ok(Some((row_idx, values)))
+                },
+            )
+            .collect::<DBResult<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<HashMap<_, _>>();
+        if updates.is_empty() {
+            return Ok(vec![]);
+        }
+        table.modification_count += 1;
+
         let table_confine_header = unsafe { &*(table as *const Table) };
         let column_values = get_mutexed_columns_values(&mut table.columns_values);
-
-        table
+        let results = table
             .rows
             .par_iter_mut()
-            .try_for_each(|(_, opt_row)| -> DBResult<()> {
-                if opt_row.is_none() {
-                    return Ok(());
-                }
-                let row = opt_row.as_mut().unwrap();
-                if !table_confine_header.is_row_satisfy_cond(row, cond)? {
-                    return Ok(());
-                }
-
+            .filter_map(|(&row_idx, opt_row)| {
+                let values = updates.get(&row_idx)?;
+                let row = opt_row.as_mut().expect("row should exist");
                 let orig_row = row.clone();
+                let result = assignments.iter().zip(values).try_for_each(
+                    |(BoundAssignment { column, .. }, value)| -> DBResult<()> {
+                        self.update_column_values(
+                            &table_confine_header.columns_info[*column],
+                            &column_values[*column],
+                            Some(&row[*column]),
+                            Some(value),
+                        )?;
+                        row[*column] = value.clone();
+                        Ok(())
+                    },
+                );
+                Some(((row_idx, orig_row), result))
+            })
+            .collect::<Vec<_>>();
 
-                for ast::Assignment {
-                    target,
-                    value: expr,
-                } in assignments
-                {
-                    let ast::AssignmentTarget::ColumnName(column_name) = target else {
-                        Err(DBSingleError::UnsupportedOPError(
-                            "only support column name".into(),
-                        ))?
-                    };
-                    let column_name = column_name.to_string();
-
-                    let col_idx = table_confine_header
-                        .get_column_index(&column_name)
-                        .ok_or_else(|| {
-                            DBSingleError::OtherError(format!("column not found: {}", column_name))
-                        })?;
+        let mut updated_rows = Vec::with_capacity(results.len());
+        let mut first_err = None;
+        for (updated_row, result) in results {
+            updated_rows.push(updated_row);
+            if let Err(err) = result {
+                first_err.get_or_insert(err);
+            }
+        }
+        if let Some(err) = first_err {
+            undo_changes(table, &updated(table, updated_rows));
+            return Err(err);
+        }
+        Ok(updated_rows)
+    }
 
-                    let value = table_confine_header.calc_expr_for_row(&orig_row, expr)?;
-                    self.update_column_values(
-                        &table_confine_header.columns_info[col_idx],
-                        &column_values[col_idx],
-                        Some(&row[col_idx]),
-                        Some(&value),
-                    )?;
-                    row[col_idx] = value;
-                }
-                Ok(())
-            })
+    fn replace_row(
+        &self,
+        table: &mut Table,
+        row_idx: usize,
+        row: Vec<Value>,
+    ) -> DBResult<Vec<Value>> {
+        // a single row has nothing to parallelize
+        super::SequentialTableManager.replace_row(table, row_idx, row)
     }
 
     fn construct_table_from_calc_func(
@@ -209,7 +313,7 @@ impl TableManager for ParallelTableManager {
         table: &Table,
         columns_info: Vec<ColumnInfo>,
         calc_funcs: Vec<super::CalcFunc>,
-        cond: Option<&ast::Expr>,
+        cond: Option<&CompiledExpr>,
     ) -> DBResult<Table> {
         let mut new_table = Table::new(columns_info);
         let insert_rows = table
@@ -220,7 +324,7 @@ impl TableManager for ParallelTableManager {
                     return Ok(None);
                 }
                 let row = opt_row.as_ref().unwrap();
-                if !table.is_row_satisfy_cond(row, cond)? {
+                if !is_row_satisfy_cond(row, cond)? {
                     return Ok(None);
                 }
                 let mut new_row = vec![];
@@ -230,18 +334,150 @@ impl TableManager for ParallelTableManager {
                 Ok(Some(new_row))
             })
             .collect::<DBResult<Vec<_>>>()?;
+        // rows keep the indices of their source rows
+        new_table.row_idx_acc = insert_rows.len();
         new_table.rows.par_extend(
             insert_rows
                 .into_par_iter()
                 .enumerate()
                 .filter(|(_, x)| x.is_some()),
         );
-        new_table.row_idx_acc = new_table.rows.len();
         new_table.row_num = new_table.rows.len();
         Ok(new_table)
     }
 
-    fn convert_order_by(&self, table: &mut Table, keys: &[(&ast::Expr, bool)]) -> DBResult<()> {
+    fn aggregate(
+        &self,
+        table: &Table,
+        group_by: &[CompiledExpr],
+        calls: &[AggregateCall],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Table> {
+        let args = calls
+            .iter()
+            .map(|call| call.compile_args(table))
+            .collect::<DBResult<Vec<_>>>()?;
+        // each chunk aggregates into its own partial groups, which are merged afterwards
+        let groups = table
+            .rows
+            .par_iter()
+            .try_fold(
+                HashMap::<Vec<Value>, AggregateGroup>::new,
+                |mut groups, (row_idx, opt_row)| -> DBResult<_> {
+                    let Some(row) = opt_row else {
+                        return Ok(groups);
+                    };
+                    if !is_row_satisfy_cond(row, cond)? {
+                        return Ok(groups);
+                    }
+                    let key = group_by
+                        .iter()
+                        .map(|expr| expr.eval(row))
+                        .collect::<DBResult<Vec<_>>>()?;
+                    let group = groups
+                        .entry(key)
+                        .or_insert_with(|| AggregateGroup::new(*row_idx, row.clone(), calls));
+                    for ((call, args), state) in calls.iter().zip(&args).zip(&mut group.states) {
+                        call.update(args, row, state)?;
+                    }
+                    Ok(groups)
+                },
+            )
+            .try_reduce(HashMap::new, |mut groups, other| {
+                for (key, other_group) in other {
+                    match groups.entry(key) {
+                        std::collections::hash_map::Entry::Occupied(mut entry) => {
+                            entry.get_mut().merge(other_group, calls)?
+                        }
+                        std::collections::hash_map::Entry::Vacant(entry) => {
+                            entry.insert(other_group);
+                        }
+                    }
+                }
+                Ok(groups)
+            })?;
+        construct_aggregate_table(
+            table,
+            calls,
+            groups.into_values().collect(),
+            !group_by.is_empty(),
+        )
+    }
+
+    fn window(
+        &self,
+        table: &Table,
+        calls: &[WindowCall],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Table> {
+        let rows = table
+            .rows
+            .par_iter()
+            .map(|(_, opt_row)| -> DBResult<_> {
+                Ok(match opt_row {
+                    Some(row) if is_row_satisfy_cond(row, cond)? => Some(row),
+                    _ => None,
+                })
+            })
+            .collect::<DBResult<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        let mut results = vec![];
+        for call in calls {
+            // partitions are independent: compute them in parallel
+            let partition_values = call
+                .partition_rows(table, &rows)?
+                .par_iter()
+                .map(|partition| call.evaluate_partition(table, &rows, partition))
+                .collect::<DBResult<Vec<_>>>()?;
+            let mut values = vec![Value::from_null(); rows.len()];
+            for (i, value) in partition_values.into_iter().flatten() {
+                values[i] = value;
+            }
+            results.push(values);
+        }
+        Ok(construct_window_table(table, &rows, calls, results))
+    }
+
+    fn dedup_rows(&self, table: &mut Table, key_columns: &[usize]) -> DBResult<()> {
+        if table.rows.len() < PARALLEL_DEDUP_THRESHOLD {
+            return super::SequentialTableManager.dedup_rows(table, key_columns);
+        }
+
+        // find the first row index of every key, then keep only those rows
+        let get_key = |row: &Vec<Value>| key_columns.iter().map(|&i| row[i].clone()).collect();
+        let first_rows = table
+            .rows
+            .par_iter()
+            .fold(
+                HashMap::<Vec<Value>, usize>::new,
+                |mut first_rows, (row_idx, opt_row)| {
+                    if let Some(row) = opt_row {
+                        first_rows.entry(get_key(row)).or_insert(*row_idx);
+                    }
+                    first_rows
+                },
+            )
+            .reduce(HashMap::new, |mut first_rows, other| {
+                for (key, row_idx) in other {
+                    let first_row_idx = first_rows.entry(key).or_insert(row_idx);
+                    *first_row_idx = (*first_row_idx).min(row_idx);
+                }
+                first_rows
+            })
+            .into_values()
+            .collect::<HashSet<_>>();
+
+        table.rows = std::mem::take(&mut table.rows)
+            .into_par_iter()
+            .filter(|(row_idx, _)| first_rows.contains(row_idx))
+            .collect();
+        table.row_num = table.rows.len();
+        Ok(())
+    }
+
+    fn convert_order_by(&self, table: &mut Table, keys: &[(CompiledExpr, bool)]) -> DBResult<()> {
         let mut rows = std::mem::take(&mut table.rows)
             .into_values()
             .flatten()
@@ -251,10 +487,10 @@ impl TableManager for ParallelTableManager {
         let mut cached_entries = vec![];
 
         // beforehand check: to avoid panic when sorting
-        for &(expr, _) in keys {
+        for (expr, _) in keys {
             let row_entries = rows
                 .par_iter()
-                .map(|(row, _)| table.calc_expr_for_row(row, expr))
+                .map(|(row, _)| expr.eval(row))
                 .collect::<DBResult<Vec<_>>>()?;
             cached_entries.push(row_entries);
         }
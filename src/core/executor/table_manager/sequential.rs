@@ -1,7 +1,10 @@
-use super::TableManager;
+use super::{TableManager, inserted, undo_changes, updated};
 use crate::core::data_structure::{ColumnInfo, Table, Value};
+use crate::core::executor::aggregate::{AggregateCall, AggregateGroup, construct_aggregate_table};
+use crate::core::executor::window::{WindowCall, construct_window_table};
+use crate::core::plan::{BoundAssignment, CompiledExpr, is_row_satisfy_cond};
 use crate::error::{DBResult, DBSingleError};
-use sqlparser::ast;
+use std::collections::{HashMap, HashSet};
 
 /// A table manager that inserts rows sequentially.
 pub struct SequentialTableManager;
@@ -24,7 +27,13 @@ impl SequentialTableManager {
             )))?
         }
         for (col_idx, value) in row.iter().enumerate() {
-            self.update_column_values(table, col_idx, None, Some(value))?;
+            if let Err(err) = self.update_column_values(table, col_idx, None, Some(value)) {
+                // remove the values of the columns already checked
+                for (restore_idx, value) in row[..col_idx].iter().enumerate() {
+                    self.update_column_values(table, restore_idx, Some(value), None)?;
+                }
+                return Err(err);
+            }
         }
         self.insert_row_unchecked(table, row)
     }
@@ -71,10 +80,8 @@ impl SequentialTableManager {
                     column_values.insert(value_to_add.clone());
                     is_duplicate = false;
                 }
-                if !is_duplicate {
-                    if let Some(value_to_delete) = value_to_delete {
-                        column_values.remove(value_to_delete);
-                    }
+                if !is_duplicate && let Some(value_to_delete) = value_to_delete {
+                    column_values.remove(value_to_delete);
                 }
             }
             if is_duplicate {
@@ -89,86 +96,159 @@ impl SequentialTableManager {
 }
 
 impl TableManager for SequentialTableManager {
+    fn name(&self) -> &'static str {
+        "sequential"
+    }
+
     fn insert_rows(
         &self,
         table: &mut Table,
-        raw_rows: &[Vec<ast::Expr>],
+        raw_rows: &[Vec<CompiledExpr>],
         columns_indicator: Vec<String>,
-    ) -> DBResult<()> {
-        for raw_row in raw_rows {
-            let row = crate::core::executor::insert::parse_raw_row_and_rearrange(
-                table,
-                raw_row,
-                &columns_indicator,
-            )?;
-            self.insert_row(table, row)?;
-        }
-        Ok(())
+    ) -> DBResult<Vec<usize>> {
+        let rows = raw_rows
+            .iter()
+            .map(|raw_row| {
+                crate::core::executor::insert::parse_raw_row_and_rearrange(
+                    table,
+                    raw_row,
+                    &columns_indicator,
+                )
+            })
+            .collect::<DBResult<Vec<_>>>()?;
+        self.insert_values(table, rows)
     }
 
-    fn delete_rows(&self, table: &mut Table, cond: Option<&ast::Expr>) -> DBResult<()> {
-        let table_confine_header = unsafe { &mut *(table as *mut Table) };
-        for opt_row in table.rows.values_mut() {
-            if opt_row.is_none() {
-                continue;
+    fn insert_values(&self, table: &mut Table, rows: Vec<Vec<Value>>) -> DBResult<Vec<usize>> {
+        let mut row_indices = vec![];
+        for row in rows {
+            match self.insert_row(table, row) {
+                Ok(row_idx) => row_indices.push(row_idx),
+                Err(err) => {
+                    undo_changes(table, &inserted(table, row_indices));
+                    return Err(err);
+                }
             }
-            if !table_confine_header.is_row_satisfy_cond(opt_row.as_ref().unwrap(), cond)? {
-                continue;
+        }
+        Ok(row_indices)
+    }
+
+    fn delete_rows(
+        &self,
+        table: &mut Table,
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Vec<(usize, Vec<Value>)>> {
+        let mut row_indices = vec![];
+        for (row_idx, row) in table.existed_indexed_rows() {
+            if is_row_satisfy_cond(row, cond)? {
+                row_indices.push(row_idx);
             }
-            let row = opt_row.as_mut().unwrap();
+        }
+        if !row_indices.is_empty() {
+            table.modification_count += 1;
+        }
+
+        let mut deleted_rows = vec![];
+        for row_idx in row_indices {
+            let row = table
+                .rows
+                .insert(row_idx, None)
+                .flatten()
+                .expect("row should exist");
             for (col_idx, value) in row.iter().enumerate() {
-                self.update_column_values(table_confine_header, col_idx, Some(value), None)?;
+                self.update_column_values(table, col_idx, Some(value), None)?;
             }
-            *opt_row = None;
+            deleted_rows.push((row_idx, row));
             table.row_num -= 1;
         }
-        Ok(())
+        Ok(deleted_rows)
     }
 
     fn update_rows(
         &self,
         table: &mut Table,
-        assignments: &[ast::Assignment],
-        cond: Option<&ast::Expr>,
-    ) -> DBResult<()> {
-        let table_confine_header = unsafe { &mut *(table as *mut Table) };
-
-        for row in table.existed_rows_mut() {
-            if !table_confine_header.is_row_satisfy_cond(row, cond)? {
+        assignments: &[BoundAssignment],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Vec<(usize, Vec<Value>)>> {
+        // evaluate the new values first, so that an error there changes nothing
+        let mut updates = vec![];
+        for (row_idx, row) in table.existed_indexed_rows() {
+            if !is_row_satisfy_cond(row, cond)? {
                 continue;
             }
+            let values = assignments
+                .iter()
+                .map(|assignment| assignment.value.eval(row))
+                .collect::<DBResult<Vec<_>>>()?;
+            updates.push((row_idx, values));
+        }
+        if !updates.is_empty() {
+            table.modification_count += 1;
+        }
 
-            let orig_row = row.clone();
-
-            for ast::Assignment {
-                target,
-                value: expr,
-            } in assignments
-            {
-                let ast::AssignmentTarget::ColumnName(column_name) = target else {
-                    Err(DBSingleError::UnsupportedOPError(
-                        "only support column name".into(),
-                    ))?
-                };
-                let column_name = column_name.to_string();
-
-                let col_idx = table_confine_header
-                    .get_column_index(&column_name)
-                    .ok_or_else(|| {
-                        DBSingleError::OtherError(format!("column not found: {}", column_name))
-                    })?;
-
-                let value = table_confine_header.calc_expr_for_row(&orig_row, expr)?;
-                self.update_column_values(
+        let table_confine_header = unsafe { &mut *(table as *mut Table) };
+        let mut updated_rows = vec![];
+        for (row_idx, values) in updates {
+            let row = table
+                .rows
+                .get_mut(&row_idx)
+                .and_then(Option::as_mut)
+                .expect("row should exist");
+            updated_rows.push((row_idx, row.clone()));
+            for (BoundAssignment { column, .. }, value) in assignments.iter().zip(values) {
+                if let Err(err) = self.update_column_values(
                     table_confine_header,
-                    col_idx,
-                    Some(&row[col_idx]),
+                    *column,
+                    Some(&row[*column]),
                     Some(&value),
-                )?;
-                row[col_idx] = value;
+                ) {
+                    undo_changes(table, &updated(table, updated_rows));
+                    return Err(err);
+                }
+                row[*column] = value;
             }
         }
-        Ok(())
+        Ok(updated_rows)
+    }
+
+    fn replace_row(
+        &self,
+        table: &mut Table,
+        row_idx: usize,
+        row: Vec<Value>,
+    ) -> DBResult<Vec<Value>> {
+        let orig_row = table
+            .rows
+            .get(&row_idx)
+            .cloned()
+            .flatten()
+            .ok_or_else(|| DBSingleError::OtherError(format!("row {} not found", row_idx)))?;
+        if row.len() != orig_row.len() {
+            Err(DBSingleError::OtherError(format!(
+                "row length {} not match columns num {}",
+                row.len(),
+                orig_row.len()
+            )))?
+        }
+        for (col_idx, (orig_value, value)) in orig_row.iter().zip(&row).enumerate() {
+            if let Err(err) =
+                self.update_column_values(table, col_idx, Some(orig_value), Some(value))
+            {
+                // restore the values of the columns already updated
+                for restore_idx in 0..col_idx {
+                    self.update_column_values(
+                        table,
+                        restore_idx,
+                        Some(&row[restore_idx]),
+                        Some(&orig_row[restore_idx]),
+                    )?;
+                }
+                return Err(err);
+            }
+        }
+        table.rows.insert(row_idx, Some(row));
+        table.modification_count += 1;
+        Ok(orig_row)
     }
 
     fn construct_table_from_calc_func(
@@ -176,11 +256,11 @@ impl TableManager for SequentialTableManager {
         table: &Table,
         columns_info: Vec<ColumnInfo>,
         calc_funcs: Vec<super::CalcFunc>,
-        cond: Option<&ast::Expr>,
+        cond: Option<&CompiledExpr>,
     ) -> DBResult<Table> {
         let mut new_table = Table::new(columns_info);
         for row in table.existed_rows() {
-            if !table.is_row_satisfy_cond(row, cond)? {
+            if !is_row_satisfy_cond(row, cond)? {
                 continue;
             }
             let mut new_row = vec![];
@@ -192,7 +272,81 @@ impl TableManager for SequentialTableManager {
         Ok(new_table)
     }
 
-    fn convert_order_by(&self, table: &mut Table, keys: &[(&ast::Expr, bool)]) -> DBResult<()> {
+    fn aggregate(
+        &self,
+        table: &Table,
+        group_by: &[CompiledExpr],
+        calls: &[AggregateCall],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Table> {
+        let args = calls
+            .iter()
+            .map(|call| call.compile_args(table))
+            .collect::<DBResult<Vec<_>>>()?;
+        let mut group_rmap: HashMap<Vec<Value>, usize> = HashMap::new();
+        let mut groups = vec![];
+        for (row_idx, row) in table.existed_indexed_rows() {
+            if !is_row_satisfy_cond(row, cond)? {
+                continue;
+            }
+            let key = group_by
+                .iter()
+                .map(|expr| expr.eval(row))
+                .collect::<DBResult<Vec<_>>>()?;
+            let group_idx = *group_rmap.entry(key).or_insert_with(|| {
+                groups.push(AggregateGroup::new(row_idx, row.clone(), calls));
+                groups.len() - 1
+            });
+            let group: &mut AggregateGroup = &mut groups[group_idx];
+            for ((call, args), state) in calls.iter().zip(&args).zip(&mut group.states) {
+                call.update(args, row, state)?;
+            }
+        }
+        construct_aggregate_table(table, calls, groups, !group_by.is_empty())
+    }
+
+    fn window(
+        &self,
+        table: &Table,
+        calls: &[WindowCall],
+        cond: Option<&CompiledExpr>,
+    ) -> DBResult<Table> {
+        let mut rows = vec![];
+        for row in table.existed_rows() {
+            if is_row_satisfy_cond(row, cond)? {
+                rows.push(row);
+            }
+        }
+        let mut results = vec![];
+        for call in calls {
+            let mut values = vec![Value::from_null(); rows.len()];
+            for partition in call.partition_rows(table, &rows)? {
+                for (i, value) in call.evaluate_partition(table, &rows, &partition)? {
+                    values[i] = value;
+                }
+            }
+            results.push(values);
+        }
+        Ok(construct_window_table(table, &rows, calls, results))
+    }
+
+    fn dedup_rows(&self, table: &mut Table, key_columns: &[usize]) -> DBResult<()> {
+        let mut seen_keys = HashSet::new();
+        table.rows.retain(|_, opt_row| {
+            opt_row.as_ref().is_some_and(|row| {
+                seen_keys.insert(
+                    key_columns
+                        .iter()
+                        .map(|&i| row[i].clone())
+                        .collect::<Vec<_>>(),
+                )
+            })
+        });
+        table.row_num = table.rows.len();
+        Ok(())
+    }
+
+    fn convert_order_by(&self, table: &mut Table, keys: &[(CompiledExpr, bool)]) -> DBResult<()> {
         let mut rows = std::mem::take(&mut table.rows)
             .into_values()
             .flatten()
@@ -202,10 +356,10 @@ impl TableManager for SequentialTableManager {
         let mut cached_entries = vec![];
 
         // beforehand check: to avoid panic when sorting
-        for &(expr, _) in keys {
+        for (expr, _) in keys {
             let mut row_entries = vec![];
             for (row, _) in rows.iter() {
-                let v = table.calc_expr_for_row(row, expr)?;
+                let v = expr.eval(row)?;
                 if row_entries
                     .last()
                     .is_some_and(|prev: &Value| prev.partial_cmp(&v).is_none())
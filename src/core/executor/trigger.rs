@@ -0,0 +1,331 @@
+//! CREATE TRIGGER and DROP TRIGGER statement execution, and trigger firing.
+//!
+//! A statement changing a table reports the changed rows in row order, also
+//! with the parallel table manager. The triggers of the table then fire for
+//! each changed row in that order, and for each row in the order of their
+//! names. BEFORE triggers fire before the statement's changes are visible,
+//! and AFTER triggers once the statement changed the table.
+
+use super::query::SelectSource;
+use super::table_manager::{ChangedRows, RowChange, undo_changes};
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{Table, Trigger, TriggerEvent, TriggerTiming, Value};
+use crate::core::parser::{CreateTrigger, SQLParser, normalize_object_name};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Maximum number of triggers fired by the statements of each other.
+const MAX_TRIGGER_DEPTH: usize = 16;
+
+/// A row change as seen by a trigger.
+struct TriggerRow {
+    /// The kind of change
+    event: TriggerEvent,
+    /// The row before the change, None for an inserted row
+    old_row: Option<Vec<Value>>,
+    /// The row after the change, None for a deleted row
+    new_row: Option<Vec<Value>>,
+}
+
+impl SQLExecutor {
+    /// Executes a CREATE TRIGGER statement.
+    ///
+    /// # Arguments
+    /// * `create_trigger` - Parsed CREATE TRIGGER statement
+    pub(super) fn execute_create_trigger(
+        &mut self,
+        create_trigger: &CreateTrigger,
+    ) -> DBResult<()> {
+        let trigger_name = normalize_object_name(&create_trigger.name);
+        if self.database.triggers.contains_key(&trigger_name) && !create_trigger.or_replace {
+            Err(DBSingleError::OtherError(format!(
+                "trigger {} already exists",
+                trigger_name
+            )))?
+        }
+        let table_name = normalize_object_name(&create_trigger.table_name);
+        if self.database.get_table(&table_name).is_none() {
+            Err(DBSingleError::OtherError(format!(
+                "table not found: {}",
+                table_name
+            )))?
+        }
+
+        let timing = match create_trigger.period {
+            ast::TriggerPeriod::Before => TriggerTiming::Before,
+            ast::TriggerPeriod::After => TriggerTiming::After,
+            ast::TriggerPeriod::InsteadOf => Err(DBSingleError::UnsupportedOPError(
+                "INSTEAD OF triggers are not supported".into(),
+            ))?,
+        };
+        let mut events = vec![];
+        for event in &create_trigger.events {
+            let event = match event {
+                ast::TriggerEvent::Insert => TriggerEvent::Insert,
+                ast::TriggerEvent::Update(columns) if columns.is_empty() => TriggerEvent::Update,
+                ast::TriggerEvent::Delete => TriggerEvent::Delete,
+                _ => Err(DBSingleError::UnsupportedOPError(format!(
+                    "unsupported trigger event {}",
+                    event
+                )))?,
+            };
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+
+        let trigger = Trigger {
+            table_name,
+            timing,
+            events,
+            condition: create_trigger
+                .condition
+                .as_ref()
+                .map(|condition| condition.to_string()),
+            body: create_trigger
+                .body
+                .iter()
+                .map(|statement| statement.to_string())
+                .collect::<Vec<_>>()
+                .join(";\n"),
+        };
+        self.database.triggers.insert(trigger_name, trigger);
+        Ok(())
+    }
+
+    /// Executes a DROP TRIGGER statement.
+    ///
+    /// # Arguments
+    /// * `drop_statement` - Parsed DROP TRIGGER statement
+    pub(super) fn execute_drop_trigger(&mut self, drop_statement: &ast::Statement) -> DBResult<()> {
+        let ast::Statement::DropTrigger {
+            if_exists,
+            trigger_name,
+            table_name,
+            ..
+        } = drop_statement
+        else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+        let trigger_name = normalize_object_name(trigger_name);
+        let exists = self
+            .database
+            .triggers
+            .get(&trigger_name)
+            .is_some_and(|trigger| {
+                table_name.as_ref().is_none_or(|table_name| {
+                    trigger.table_name == normalize_object_name(table_name)
+                })
+            });
+        if !exists {
+            if *if_exists {
+                return Ok(());
+            }
+            Err(DBSingleError::OtherError(format!(
+                "trigger {} not found",
+                trigger_name
+            )))?
+        }
+        self.database.triggers.remove(&trigger_name);
+        Ok(())
+    }
+
+    /// Changes the rows of a table, firing the triggers of the table for
+    /// each changed row, and reporting the committed changes to the change
+    /// hooks before the AFTER triggers fire.
+    ///
+    /// The change must leave the table as it was when it fails, as nothing
+    /// is reported then.
+    ///
+    /// With BEFORE triggers, the changed rows are set back while they fire,
+    /// and set again afterwards. They must not change the table themselves.
+    /// An error in a BEFORE trigger leaves the table unchanged, while an
+    /// error in an AFTER trigger keeps the changes.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the table to change
+    /// * `events` - The kinds of changes the statement may make
+    /// * `executor_state` - Current executor state for evaluation context
+    /// * `change` - Function changing the table atomically, and returning the changes in order
+    ///
+    /// # Returns
+    /// The changes made to the table, in order
+    pub(super) fn change_table(
+        &mut self,
+        table_name: &str,
+        events: &[TriggerEvent],
+        executor_state: &SQLExecutorState,
+        change: impl FnOnce(&mut Self) -> DBResult<Vec<RowChange>>,
+    ) -> DBResult<Vec<RowChange>> {
+        let triggers = self
+            .database
+            .get_table_triggers(table_name)
+            .into_iter()
+            .filter(|(_, trigger)| trigger.events.iter().any(|event| events.contains(event)))
+            .map(|(name, trigger)| (name.clone(), trigger.clone()))
+            .collect::<Vec<_>>();
+        if triggers.is_empty() {
+            let changes = change(self)?;
+            self.notify_changes(table_name, &changes);
+            return Ok(changes);
+        }
+        let changes = change(self)?;
+        let table = self
+            .database
+            .get_table(table_name)
+            .expect("table should exist");
+        let column_names = table
+            .columns_info
+            .iter()
+            .map(|column| column.name.clone())
+            .collect::<Vec<_>>();
+        let rows = changes
+            .iter()
+            .map(|change| match change {
+                RowChange::Insert { new_row, .. } => TriggerRow {
+                    event: TriggerEvent::Insert,
+                    old_row: None,
+                    new_row: Some(new_row.clone()),
+                },
+                RowChange::Update {
+                    old_row, new_row, ..
+                } => TriggerRow {
+                    event: TriggerEvent::Update,
+                    old_row: Some(old_row.clone()),
+                    new_row: Some(new_row.clone()),
+                },
+                RowChange::Delete { old_row, .. } => TriggerRow {
+                    event: TriggerEvent::Delete,
+                    old_row: Some(old_row.clone()),
+                    new_row: None,
+                },
+            })
+            .collect::<Vec<_>>();
+
+        if triggers
+            .iter()
+            .any(|(_, trigger)| trigger.timing == TriggerTiming::Before)
+        {
+            let table = self
+                .database
+                .get_table_mut(table_name)
+                .expect("table should exist");
+            let changed_rows = ChangedRows::current(table, &changes);
+            undo_changes(table, &changes);
+            let (row_idx_acc, modification_count) = (table.row_idx_acc, table.modification_count);
+            self.fire_triggers(
+                &triggers,
+                TriggerTiming::Before,
+                &column_names,
+                &rows,
+                executor_state,
+            )?;
+            let table = self
+                .database
+                .get_table_mut(table_name)
+                .filter(|table| {
+                    table.row_idx_acc == row_idx_acc
+                        && table.modification_count == modification_count
+                })
+                .ok_or_else(|| {
+                    DBSingleError::OtherError(format!(
+                        "table {} was changed by one of its BEFORE triggers",
+                        table_name
+                    ))
+                })?;
+            changed_rows.restore(table);
+        }
+        self.notify_changes(table_name, &changes);
+        self.fire_triggers(
+            &triggers,
+            TriggerTiming::After,
+            &column_names,
+            &rows,
+            executor_state,
+        )?;
+        Ok(changes)
+    }
+
+    /// Fires the triggers with the given timing for each changed row.
+    ///
+    /// # Arguments
+    /// * `triggers` - Triggers of the table, sorted by name
+    /// * `timing` - Timing of the triggers to fire
+    /// * `column_names` - Names of the columns of the table
+    /// * `rows` - The changed rows, in order
+    /// * `executor_state` - Current executor state for evaluation context
+    fn fire_triggers(
+        &mut self,
+        triggers: &[(String, Trigger)],
+        timing: TriggerTiming,
+        column_names: &[String],
+        rows: &[TriggerRow],
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<()> {
+        for row in rows {
+            let named_row = |row: &Option<Vec<Value>>| -> HashMap<String, Value> {
+                column_names
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| {
+                        let value = row
+                            .as_ref()
+                            .map_or_else(Value::from_null, |row| row[i].clone());
+                        (name.clone(), value)
+                    })
+                    .collect()
+            };
+            let trigger_rows = HashMap::from([
+                ("new".to_string(), named_row(&row.new_row)),
+                ("old".to_string(), named_row(&row.old_row)),
+            ]);
+
+            for (name, trigger) in triggers {
+                if trigger.timing != timing || !trigger.events.contains(&row.event) {
+                    continue;
+                }
+                if executor_state.trigger_depth == MAX_TRIGGER_DEPTH {
+                    Err(DBSingleError::OtherError(format!(
+                        "trigger {} exceeded the maximum trigger depth {}",
+                        name, MAX_TRIGGER_DEPTH
+                    )))?
+                }
+
+                if let Some(condition) = &trigger.condition {
+                    let condition_state = SQLExecutorState {
+                        sql_statements: condition,
+                        trigger_rows: trigger_rows.clone(),
+                        ..Default::default()
+                    };
+                    let mut condition = SQLParser::new().parse_expr(condition)?;
+                    let source = SelectSource {
+                        table: Cow::Borrowed(Table::get_dummy()),
+                        name: None,
+                    };
+                    self.bind_expr(&mut condition, &source, &mut None, &condition_state)?;
+                    if !Table::get_dummy().is_row_satisfy_cond(
+                        &[],
+                        Some(&condition),
+                        self.name_resolution(),
+                    )? {
+                        continue;
+                    }
+                }
+
+                let mut body_state = SQLExecutorState {
+                    sql_statements: &trigger.body,
+                    trigger_rows: trigger_rows.clone(),
+                    trigger_depth: executor_state.trigger_depth + 1,
+                    ..Default::default()
+                };
+                for statement in SQLParser::new().parse(&trigger.body)? {
+                    self.execute_statement(&statement, &mut body_state)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
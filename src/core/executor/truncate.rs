@@ -0,0 +1,56 @@
+//! TRUNCATE TABLE statement execution.
+//!
+//! Handles parsing and execution of TRUNCATE TABLE statements.
+
+use super::SQLExecutor;
+use crate::core::parser::normalize_object_name;
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+
+impl SQLExecutor {
+    /// Executes a TRUNCATE TABLE statement.
+    ///
+    /// The tables are emptied without scanning their rows; the storage of
+    /// deleted rows is reclaimed as well. All the tables are checked to
+    /// exist before any is truncated.
+    ///
+    /// # Arguments
+    /// * `truncate_statement` - Parsed TRUNCATE statement
+    pub(super) fn execute_truncate(&mut self, truncate_statement: &ast::Statement) -> DBResult<()> {
+        let ast::Statement::Truncate {
+            table_names,
+            partitions,
+            ..
+        } = truncate_statement
+        else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+
+        if partitions.is_some() {
+            Err(DBSingleError::UnsupportedOPError(
+                "truncating partitions is not supported".into(),
+            ))?
+        }
+        let table_names = table_names
+            .iter()
+            .map(|target| normalize_object_name(&target.name))
+            .collect::<Vec<_>>();
+        for table_name in &table_names {
+            if self.database.get_table(table_name).is_none() {
+                Err(DBSingleError::OtherError(format!(
+                    "table {} not found",
+                    table_name
+                )))?
+            }
+        }
+        for table_name in &table_names {
+            self.database
+                .get_table_mut(table_name)
+                .expect("table should exist")
+                .truncate();
+            self.notify_truncate(table_name);
+        }
+        Ok(())
+    }
+}
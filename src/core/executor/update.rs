@@ -2,20 +2,38 @@
 //!
 //! Handles parsing and execution of UPDATE statements.
 
-use super::SQLExecutor;
+use super::explain::Operator;
+use super::query::SelectSource;
+use super::subquery::CorrelatedColumns;
+use super::table_manager::{RowChange, updated};
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::TriggerEvent;
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::core::plan::{BoundAssignment, compile_cond};
 use crate::error::{DBResult, DBSingleError};
 use sqlparser::ast;
+use std::borrow::Cow;
 
 impl SQLExecutor {
-    /// Executes an UPDATE statement.
+    /// Executes an UPDATE statement, and outputs the updated rows with
+    /// RETURNING.
     ///
     /// # Arguments
     /// * `update_statement` - Parsed UPDATE statement
-    pub(super) fn execute_update(&mut self, update_statement: &ast::Statement) -> DBResult<()> {
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// Number of updated rows
+    pub(super) fn execute_update(
+        &mut self,
+        update_statement: &ast::Statement,
+        executor_state: &mut SQLExecutorState,
+    ) -> DBResult<usize> {
         let ast::Statement::Update {
             table,
             assignments,
             selection,
+            returning,
             ..
         } = update_statement
         else {
@@ -25,6 +43,7 @@ impl SQLExecutor {
 
         let ast::TableFactor::Table {
             name: ref table_name,
+            ref alias,
             ..
         } = table.relation
         else {
@@ -32,16 +51,84 @@ impl SQLExecutor {
                 "only support table".into(),
             ))?
         };
-        let table_name = table_name.to_string();
+        let table_name = normalize_object_name(table_name);
+        let source_name = alias
+            .as_ref()
+            .map_or(table_name.clone(), |a| normalize_ident(&a.name));
 
-        let table = self
-            .database
-            .get_table_mut(&table_name)
-            .ok_or_else(|| DBSingleError::OtherError(format!("table not found: {}", table_name)))?;
+        let mut assignments = assignments.clone();
+        let mut selection = selection.clone();
+        let (bound_assignments, cond, correlated_columns) = {
+            let source = SelectSource {
+                table: Cow::Borrowed(self.database.get_table(&table_name).ok_or_else(|| {
+                    DBSingleError::OtherError(format!("table not found: {}", table_name))
+                })?),
+                name: Some(source_name.clone()),
+            };
+            let mut correlated = vec![];
+            let exprs = assignments
+                .iter_mut()
+                .map(|assignment| &mut assignment.value)
+                .chain(selection.as_mut());
+            for expr in exprs {
+                self.bind_expr(expr, &source, &mut Some(&mut correlated), executor_state)?;
+            }
+            let header = CorrelatedColumns::header(&source.table, &correlated);
+            let bound_assignments = assignments
+                .iter()
+                .map(|assignment| {
+                    BoundAssignment::bind(assignment, &header, self.name_resolution())
+                })
+                .collect::<DBResult<Vec<_>>>()?;
+            (
+                bound_assignments,
+                compile_cond(selection.as_ref(), &header, self.name_resolution())?,
+                self.evaluate_correlated_columns(&source, &correlated, executor_state)?,
+            )
+        };
 
-        self.table_manager
-            .update_rows(table, assignments, selection.as_ref())?;
+        let changes = executor_state.explain(
+            true,
+            || {
+                self.change_table(
+                    &table_name,
+                    &[TriggerEvent::Update],
+                    executor_state,
+                    |this| {
+                        let table = this.database.get_table_mut(&table_name).ok_or_else(|| {
+                            DBSingleError::OtherError(format!("table not found: {}", table_name))
+                        })?;
+                        correlated_columns.change(table, |table| {
+                            let rows = this.table_manager.update_rows(
+                                table,
+                                &bound_assignments,
+                                cond.as_ref(),
+                            )?;
+                            Ok(updated(table, rows))
+                        })
+                    },
+                )
+            },
+            |changes| {
+                Operator::new(format!("Update on {}", table_name), changes.len())
+                    .detail("Set", ast::display_comma_separated(&assignments))
+                    .detail_opt("Filter", selection.as_ref())
+            },
+        )?;
 
-        Ok(())
+        let updated_num = changes.len();
+        if let Some(returning) = returning {
+            let table = self
+                .database
+                .get_table(&table_name)
+                .expect("table should exist");
+            // the rows as updated, even if an AFTER trigger changed them since
+            let rows = changes
+                .into_iter()
+                .filter_map(RowChange::into_new_row)
+                .collect();
+            self.output_returning(returning, table, source_name, rows, executor_state)?;
+        }
+        Ok(updated_num)
     }
 }
@@ -0,0 +1,389 @@
+//! INSERT ... ON CONFLICT / ON DUPLICATE KEY UPDATE execution.
+//!
+//! Rows conflicting with an existing row on a unique column are skipped or
+//! turned into an update of the existing row.
+
+use super::insert::{parse_raw_row_and_rearrange, rearrange_row};
+use super::query::SelectSource;
+use super::subquery::CorrelatedColumns;
+use super::table_manager::{RowChange, undo_changes};
+use super::utils::expr_children_mut;
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{Table, Value};
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::core::plan::{self, is_row_satisfy_cond};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Gets the name of the hidden column holding a value of the proposed row.
+fn excluded_column_name(column_index: usize) -> String {
+    format!("#excluded{}", column_index)
+}
+
+/// Rewrites references to the proposed row, `EXCLUDED.<column>` and MySQL
+/// `VALUES(<column>)`, into its hidden columns.
+///
+/// # Arguments
+/// * `expr` - Expression to rewrite
+/// * `table` - Table being inserted into
+fn bind_excluded(expr: &mut ast::Expr, table: &Table) -> DBResult<()> {
+    let column = match expr {
+        ast::Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+            [qualifier, column] if qualifier.value.eq_ignore_ascii_case("excluded") => {
+                Some(normalize_ident(column))
+            }
+            _ => None,
+        },
+        ast::Expr::Function(function)
+            if function.name.to_string().eq_ignore_ascii_case("values") =>
+        {
+            match &function.args {
+                ast::FunctionArguments::List(list) => match list.args.as_slice() {
+                    [
+                        ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(
+                            ast::Expr::Identifier(column),
+                        )),
+                    ] => Some(normalize_ident(column)),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    if let Some(column) = column {
+        let index = table
+            .get_column_index(&column)
+            .ok_or_else(|| DBSingleError::OtherError(format!("column not found: {}", column)))?;
+        *expr = ast::Expr::Identifier(ast::Ident::new(excluded_column_name(index)));
+        return Ok(());
+    }
+    for child in expr_children_mut(expr) {
+        bind_excluded(child, table)?;
+    }
+    Ok(())
+}
+
+/// Gets the unique columns on which an INSERT conflicts with existing rows.
+///
+/// # Arguments
+/// * `table` - Table being inserted into
+/// * `target` - Conflict target; all unique columns if absent
+fn conflict_columns(table: &Table, target: Option<&ast::ConflictTarget>) -> DBResult<Vec<usize>> {
+    match target {
+        None => Ok((0..table.get_column_num())
+            .filter(|&i| table.get_column_info(i).unique)
+            .collect()),
+        Some(ast::ConflictTarget::Columns(columns)) => columns
+            .iter()
+            .map(|column| {
+                let index = table
+                    .get_column_index(&normalize_ident(column))
+                    .ok_or_else(|| {
+                        DBSingleError::OtherError(format!("column not found: {}", column))
+                    })?;
+                if !table.get_column_info(index).unique {
+                    Err(DBSingleError::OtherError(
+                        "there is no unique constraint matching the ON CONFLICT specification"
+                            .into(),
+                    ))?
+                }
+                Ok(index)
+            })
+            .collect(),
+        Some(ast::ConflictTarget::OnConstraint(_)) => Err(DBSingleError::UnsupportedOPError(
+            "ON CONFLICT ON CONSTRAINT is not supported".into(),
+        ))?,
+    }
+}
+
+/// Adds the values of a row in the conflict columns to their index, or
+/// removes them.
+///
+/// # Arguments
+/// * `conflict_columns` - The conflict columns of the table
+/// * `conflict_index` - The row holding each value, for each conflict column
+/// * `row_idx` - Index of the row
+/// * `row` - Values of the row
+/// * `add` - Whether to add the values, or remove them
+fn index_conflict_values(
+    conflict_columns: &[usize],
+    conflict_index: &mut [HashMap<Value, usize>],
+    row_idx: usize,
+    row: &[Value],
+    add: bool,
+) {
+    for (&col, index) in conflict_columns.iter().zip(conflict_index) {
+        let value = &row[col];
+        if value.is_null() {
+            continue;
+        }
+        if add {
+            index.insert(value.clone(), row_idx);
+        } else if index.get(value) == Some(&row_idx) {
+            index.remove(value);
+        }
+    }
+}
+
+impl SQLExecutor {
+    /// Executes an INSERT statement with an ON CONFLICT or ON DUPLICATE KEY
+    /// UPDATE clause.
+    ///
+    /// Rows are applied one by one, so a row may conflict with a row inserted
+    /// earlier by the same statement. If a row fails, the rows applied before
+    /// it are set back, leaving the table unchanged.
+    ///
+    /// # Arguments
+    /// * `insert` - Parsed INSERT statement
+    /// * `on_insert` - The conflict clause of the statement
+    /// * `table_name` - Name of the table to insert into
+    /// * `query` - Source of the rows to insert
+    /// * `columns_indicator` - The columns given by the statement, in order
+    /// * `executor_state` - Current executor state for evaluation context
+    ///
+    /// # Returns
+    /// The changes made to the table, in order
+    pub(super) fn execute_upsert(
+        &mut self,
+        insert: &ast::Insert,
+        on_insert: &ast::OnInsert,
+        table_name: &str,
+        query: &ast::Query,
+        columns_indicator: &[String],
+        executor_state: &SQLExecutorState,
+    ) -> DBResult<Vec<RowChange>> {
+        let table = self
+            .database
+            .get_table(table_name)
+            .ok_or_else(|| DBSingleError::OtherError(format!("table not found: {}", table_name)))?;
+        let rows = match query.body.as_ref() {
+            ast::SetExpr::Values(values) => {
+                let source = SelectSource {
+                    table: Cow::Borrowed(Table::get_dummy()),
+                    name: None,
+                };
+                let mut rows = vec![];
+                for raw_row in &values.rows {
+                    let mut raw_row = raw_row.clone();
+                    for expr in raw_row.iter_mut() {
+                        self.bind_expr(expr, &source, &mut None, executor_state)?;
+                    }
+                    let raw_row = raw_row
+                        .iter()
+                        .map(|expr| plan::compile(expr, Table::get_dummy(), self.name_resolution()))
+                        .collect::<DBResult<Vec<_>>>()?;
+                    rows.push(parse_raw_row_and_rearrange(
+                        table,
+                        &raw_row,
+                        columns_indicator,
+                    )?);
+                }
+                rows
+            }
+            _ => self
+                .evaluate_query(query, executor_state)?
+                .rows
+                .into_values()
+                .flatten()
+                .map(|row| rearrange_row(table, row, columns_indicator))
+                .collect::<DBResult<Vec<_>>>()?,
+        };
+
+        let (conflict_columns, action) = match on_insert {
+            ast::OnInsert::DuplicateKeyUpdate(assignments) => {
+                (conflict_columns(table, None)?, Some((assignments, None)))
+            }
+            ast::OnInsert::OnConflict(on_conflict) => {
+                let action = match &on_conflict.action {
+                    ast::OnConflictAction::DoNothing => None,
+                    ast::OnConflictAction::DoUpdate(do_update) => {
+                        Some((&do_update.assignments, do_update.selection.as_ref()))
+                    }
+                };
+                (
+                    conflict_columns(table, on_conflict.conflict_target.as_ref())?,
+                    action,
+                )
+            }
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported conflict clause {}",
+                on_insert
+            )))?,
+        };
+
+        // the existing row, followed by the proposed row and the results of
+        // the correlated subqueries in hidden columns
+        let source_name = insert
+            .table_alias
+            .as_ref()
+            .map_or(table_name.to_string(), normalize_ident);
+        let mut correlated = vec![];
+        let action = match action {
+            Some((assignments, selection)) => {
+                let source = SelectSource {
+                    table: Cow::Borrowed(table),
+                    name: Some(source_name.clone()),
+                };
+                let mut targets = vec![];
+                let mut values = vec![];
+                for assignment in assignments {
+                    let ast::AssignmentTarget::ColumnName(column_name) = &assignment.target else {
+                        Err(DBSingleError::UnsupportedOPError(
+                            "only support column name".into(),
+                        ))?
+                    };
+                    let column_name = normalize_object_name(column_name);
+                    targets.push(table.get_column_index(&column_name).ok_or_else(|| {
+                        DBSingleError::OtherError(format!("column not found: {}", column_name))
+                    })?);
+                    let mut value = assignment.value.clone();
+                    bind_excluded(&mut value, table)?;
+                    self.bind_expr(
+                        &mut value,
+                        &source,
+                        &mut Some(&mut correlated),
+                        executor_state,
+                    )?;
+                    values.push(value);
+                }
+                let mut selection = selection.cloned();
+                if let Some(selection) = &mut selection {
+                    bind_excluded(selection, table)?;
+                    self.bind_expr(
+                        selection,
+                        &source,
+                        &mut Some(&mut correlated),
+                        executor_state,
+                    )?;
+                }
+
+                let mut columns_info = table.columns_info.clone();
+                columns_info.extend(table.columns_info.iter().enumerate().map(|(i, column)| {
+                    let mut column = column.clone();
+                    column.name = excluded_column_name(i);
+                    column
+                }));
+                let header =
+                    CorrelatedColumns::header(&Table::new(columns_info), &correlated).into_owned();
+                let values = values
+                    .iter()
+                    .map(|value| plan::compile(value, &header, self.name_resolution()))
+                    .collect::<DBResult<Vec<_>>>()?;
+                let selection =
+                    plan::compile_cond(selection.as_ref(), &header, self.name_resolution())?;
+                Some((targets, values, selection))
+            }
+            None => None,
+        };
+
+        // the row holding each value of the conflict columns
+        let table = self
+            .database
+            .get_table(table_name)
+            .expect("table should exist");
+        let mut conflict_index = vec![HashMap::new(); conflict_columns.len()];
+        for (row_idx, row) in table.existed_indexed_rows() {
+            index_conflict_values(&conflict_columns, &mut conflict_index, row_idx, row, true);
+        }
+
+        // the subqueries are evaluated for each conflicting row
+        let unexplained = executor_state.unexplained();
+        let subquery_state = unexplained.as_ref().unwrap_or(executor_state);
+        let mut changes = vec![];
+        let result = (|| -> DBResult<()> {
+            for row in rows {
+                let existing = conflict_columns.iter().zip(&conflict_index).find_map(
+                    |(&col, index)| match row[col].is_null() {
+                        true => None,
+                        false => index.get(&row[col]).copied(),
+                    },
+                );
+                let Some(row_idx) = existing else {
+                    let table = self
+                        .database
+                        .get_table_mut(table_name)
+                        .expect("table should exist");
+                    let row_idx = self.table_manager.insert_values(table, vec![row])?[0];
+                    let new_row = table.rows[&row_idx].clone().expect("row should exist");
+                    index_conflict_values(
+                        &conflict_columns,
+                        &mut conflict_index,
+                        row_idx,
+                        &new_row,
+                        true,
+                    );
+                    changes.push(RowChange::Insert { row_idx, new_row });
+                    continue;
+                };
+                let Some((targets, values, selection)) = &action else {
+                    continue;
+                };
+
+                let table = self
+                    .database
+                    .get_table(table_name)
+                    .expect("table should exist");
+                let existing_row = table.rows[&row_idx].as_ref().expect("row should exist");
+                let mut joined = existing_row.clone();
+                joined.extend(row);
+                let source = SelectSource {
+                    table: Cow::Borrowed(table),
+                    name: Some(source_name.clone()),
+                };
+                for subquery in &correlated {
+                    joined.push(self.evaluate_correlated_subquery(
+                        subquery,
+                        &source,
+                        existing_row,
+                        subquery_state,
+                    )?);
+                }
+                if !is_row_satisfy_cond(&joined, selection.as_ref())? {
+                    continue;
+                }
+                let mut new_row = joined[..table.get_column_num()].to_vec();
+                for (&col_idx, value) in targets.iter().zip(values) {
+                    new_row[col_idx] = value.eval(&joined)?;
+                }
+                let table = self
+                    .database
+                    .get_table_mut(table_name)
+                    .expect("table should exist");
+                let old_row = self.table_manager.replace_row(table, row_idx, new_row)?;
+                index_conflict_values(
+                    &conflict_columns,
+                    &mut conflict_index,
+                    row_idx,
+                    &old_row,
+                    false,
+                );
+                let new_row = table.rows[&row_idx].clone().expect("row should exist");
+                index_conflict_values(
+                    &conflict_columns,
+                    &mut conflict_index,
+                    row_idx,
+                    &new_row,
+                    true,
+                );
+                changes.push(RowChange::Update {
+                    row_idx,
+                    old_row,
+                    new_row,
+                });
+            }
+            Ok(())
+        })();
+        if let Err(err) = result {
+            let table = self
+                .database
+                .get_table_mut(table_name)
+                .expect("table should exist");
+            undo_changes(table, &changes);
+            return Err(err);
+        }
+        Ok(changes)
+    }
+}
@@ -3,6 +3,11 @@
 //! Contains helper methods used across different executor operations.
 
 use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{Value, ValueNotNull};
+use crate::core::parser::normalize_ident;
+use sqlparser::ast::{self, Spanned};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
 
 impl SQLExecutor {
     /// Extracts SQL text content from a source span.
@@ -35,4 +40,113 @@ impl SQLExecutor {
         }
         Some(sql_line[start_column..end_column].to_string())
     }
+
+    /// Gets the display name of an expression, preferring its original SQL text.
+    ///
+    /// A column is named after the column it refers to, see
+    /// [`normalize_ident`]. The source span of some expressions (e.g.
+    /// function calls) does not cover the whole expression, so the text is
+    /// only used when it parses back to the same expression.
+    ///
+    /// # Arguments
+    /// * `expr` - Expression to name
+    /// * `executor_state` - Current executor state containing SQL text
+    pub(super) fn get_expr_name(
+        &self,
+        expr: &ast::Expr,
+        executor_state: &SQLExecutorState,
+    ) -> String {
+        if let ast::Expr::Identifier(ident) = expr {
+            return normalize_ident(ident);
+        }
+        self.get_content_from_span(expr.span(), executor_state)
+            .filter(|content| {
+                Parser::new(&GenericDialect {})
+                    .try_with_sql(content)
+                    .and_then(|mut parser| parser.parse_expr())
+                    .is_ok_and(|parsed| parsed == *expr)
+            })
+            .unwrap_or_else(|| expr.to_string())
+    }
+}
+
+/// Gets mutable references to the direct sub-expressions of an expression.
+///
+/// Subqueries are not sub-expressions; for `<expr> IN (<subquery>)` only
+/// `<expr>` is returned. The window definition of a window function call
+/// is part of the call.
+pub(super) fn expr_children_mut(expr: &mut ast::Expr) -> Vec<&mut ast::Expr> {
+    use ast::Expr;
+    match expr {
+        Expr::Nested(expr)
+        | Expr::UnaryOp { expr, .. }
+        | Expr::IsFalse(expr)
+        | Expr::IsTrue(expr)
+        | Expr::IsNotTrue(expr)
+        | Expr::IsNotFalse(expr)
+        | Expr::IsNull(expr)
+        | Expr::IsNotNull(expr)
+        | Expr::InSubquery { expr, .. } => vec![expr.as_mut()],
+        Expr::BinaryOp { left, right, .. } => vec![left.as_mut(), right.as_mut()],
+        Expr::Between {
+            expr, low, high, ..
+        } => vec![expr.as_mut(), low.as_mut(), high.as_mut()],
+        Expr::InList { expr, list, .. } => std::iter::once(expr.as_mut())
+            .chain(list.iter_mut())
+            .collect(),
+        Expr::Function(function) => {
+            let mut children = vec![];
+            if let ast::FunctionArguments::List(list) = &mut function.args {
+                children.extend(list.args.iter_mut().filter_map(|arg| match arg {
+                    ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(expr))
+                    | ast::FunctionArg::Named {
+                        arg: ast::FunctionArgExpr::Expr(expr),
+                        ..
+                    } => Some(expr),
+                    _ => None,
+                }));
+            }
+            if let Some(ast::WindowType::WindowSpec(spec)) = &mut function.over {
+                children.extend(spec.partition_by.iter_mut());
+                children.extend(
+                    spec.order_by
+                        .iter_mut()
+                        .map(|order_by_expr| &mut order_by_expr.expr),
+                );
+            }
+            children
+        }
+        _ => vec![],
+    }
+}
+
+/// Gets mutable references to the expressions of a SELECT evaluated against
+/// its source rows: projection, WHERE, GROUP BY, HAVING and DISTINCT ON.
+pub(super) fn select_exprs_mut(select: &mut ast::Select) -> Vec<&mut ast::Expr> {
+    let mut exprs = vec![];
+    for select_item in select.projection.iter_mut() {
+        if let ast::SelectItem::UnnamedExpr(expr) | ast::SelectItem::ExprWithAlias { expr, .. } =
+            select_item
+        {
+            exprs.push(expr);
+        }
+    }
+    exprs.extend(select.selection.as_mut());
+    if let ast::GroupByExpr::Expressions(group_by, _) = &mut select.group_by {
+        exprs.extend(group_by.iter_mut());
+    }
+    exprs.extend(select.having.as_mut());
+    if let Some(ast::Distinct::On(distinct_on)) = &mut select.distinct {
+        exprs.extend(distinct_on.iter_mut());
+    }
+    exprs
+}
+
+/// Converts a value into a literal expression evaluating to it.
+pub(super) fn value_to_expr(value: Value) -> ast::Expr {
+    ast::Expr::value(match value.0 {
+        Some(ValueNotNull::Int(i)) => ast::Value::Number(i.to_string(), false),
+        Some(ValueNotNull::Varchar(s)) => ast::Value::SingleQuotedString(s),
+        None => ast::Value::Null,
+    })
 }
@@ -0,0 +1,61 @@
+//! VACUUM statement execution and automatic compaction.
+//!
+//! Deleted rows are kept in [`Table::rows`](crate::core::data_structure::Table::rows)
+//! as `None` until the table is compacted.
+
+use super::SQLExecutor;
+use crate::core::parser::normalize_object_name;
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+
+impl SQLExecutor {
+    /// Executes a VACUUM statement, compacting the given tables, or all
+    /// tables if none is given.
+    ///
+    /// # Arguments
+    /// * `table_names` - Names of the tables to compact
+    pub(super) fn execute_vacuum(&mut self, table_names: &[ast::ObjectName]) -> DBResult<()> {
+        if table_names.is_empty() {
+            for table in self.database.tables.values_mut() {
+                table.compact();
+            }
+            return Ok(());
+        }
+
+        let table_names = table_names
+            .iter()
+            .map(normalize_object_name)
+            .collect::<Vec<_>>();
+        for table_name in &table_names {
+            if self.database.get_table(table_name).is_none() {
+                Err(DBSingleError::OtherError(format!(
+                    "table {} not found",
+                    table_name
+                )))?
+            }
+        }
+        for table_name in &table_names {
+            self.database
+                .get_table_mut(table_name)
+                .expect("table should exist")
+                .compact();
+        }
+        Ok(())
+    }
+
+    /// Compacts a table if its fraction of deleted rows exceeds the
+    /// configured threshold.
+    ///
+    /// # Arguments
+    /// * `table_name` - Name of the table
+    pub(super) fn auto_vacuum(&mut self, table_name: &str) {
+        let Some(threshold) = self.config.auto_vacuum_threshold else {
+            return;
+        };
+        if let Some(table) = self.database.get_table_mut(table_name)
+            && table.get_tombstone_num() as f64 > threshold * table.rows.len() as f64
+        {
+            table.compact();
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! CREATE VIEW and REFRESH MATERIALIZED VIEW statement execution, and view
+//! evaluation.
+//!
+//! A view stores the SQL text of its query, which is evaluated whenever the
+//! view is read. A materialized view also stores the rows of its query,
+//! which are only recomputed on refresh.
+
+use super::cte::{collect_relations, table_with_rows};
+use super::utils::{expr_children_mut, select_exprs_mut};
+use super::window::collect_window_calls;
+use super::{SQLExecutor, SQLExecutorState};
+use crate::core::data_structure::{MaterializedView, Table, View};
+use crate::core::parser::{SQLParser, normalize_ident, normalize_object_name};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+impl SQLExecutor {
+    /// Executes a CREATE [MATERIALIZED] VIEW statement.
+    ///
+    /// The query of a view is evaluated once to check it. With
+    /// `IF NOT EXISTS`, nothing is done if a table or view already has the
+    /// name. With `OR REPLACE`, an existing view of the same kind is
+    /// replaced.
+    ///
+    /// # Arguments
+    /// * `create_view` - Parsed CREATE VIEW statement
+    pub(super) fn execute_create_view(&mut self, create_view: &ast::Statement) -> DBResult<()> {
+        let ast::Statement::CreateView {
+            or_replace,
+            materialized,
+            name,
+            columns,
+            query,
+            if_not_exists,
+            ..
+        } = create_view
+        else {
+            // This should never happen, as we have entered into this function
+            panic!("Should not reach here");
+        };
+
+        let view_name = normalize_object_name(name);
+        if self.database.contains_relation(&view_name) {
+            if *if_not_exists {
+                return Ok(());
+            }
+            let same_kind = if *materialized {
+                self.database.materialized_views.contains_key(&view_name)
+            } else {
+                self.database.views.contains_key(&view_name)
+            };
+            if !*or_replace || !same_kind {
+                Err(DBSingleError::OtherError(format!(
+                    "table name {} already exists",
+                    view_name
+                )))?
+            }
+        }
+
+        let mut query = query.as_ref().clone();
+        let mut relations = HashSet::new();
+        collect_relations(&mut query, &mut relations);
+        // names which are not tables or views refer to the WITH clauses
+        let mut dependencies = relations
+            .into_iter()
+            .filter(|relation| self.database.contains_relation(relation))
+            .collect::<Vec<_>>();
+        dependencies.sort();
+        let dependents = self.database.get_dependent_views(&view_name);
+        if dependencies
+            .iter()
+            .any(|dependency| *dependency == view_name || dependents.contains(dependency))
+        {
+            Err(DBSingleError::OtherError(format!(
+                "view {} cannot depend on itself",
+                view_name
+            )))?
+        }
+
+        let view = View {
+            query: query.to_string(),
+            column_names: columns
+                .iter()
+                .map(|column| normalize_ident(&column.name))
+                .collect(),
+            dependencies,
+        };
+        let table = self.evaluate_view(&view_name, &view, HashMap::new())?;
+        if *materialized {
+            let dependency_states = self.get_dependency_states(&view);
+            self.database.materialized_views.insert(
+                view_name,
+                MaterializedView {
+                    view,
+                    table,
+                    dependency_states,
+                },
+            );
+        } else {
+            self.database.views.insert(view_name, view);
+        }
+        Ok(())
+    }
+
+    /// Executes a REFRESH MATERIALIZED VIEW statement.
+    ///
+    /// When the view reads from a single table which only had rows appended
+    /// since the last refresh, and each row of the view is computed from a
+    /// single row of the table, only the appended rows are evaluated.
+    /// Otherwise the whole query is evaluated again.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the materialized view
+    pub(super) fn execute_refresh_materialized_view(
+        &mut self,
+        name: &ast::ObjectName,
+    ) -> DBResult<()> {
+        let view_name = normalize_object_name(name);
+        let materialized = self
+            .database
+            .get_materialized_view(&view_name)
+            .ok_or_else(|| {
+                DBSingleError::OtherError(format!("materialized view {} not found", view_name))
+            })?;
+        let dependency_states = self.get_dependency_states(&materialized.view);
+
+        let appended_rows = match (
+            materialized.view.dependencies.as_slice(),
+            materialized.dependency_states.as_slice(),
+            dependency_states.as_slice(),
+        ) {
+            (
+                [source_name],
+                [Some((old_row_idx_acc, old_modification_count))],
+                [Some((_, modification_count))],
+            ) if old_modification_count == modification_count
+                && self.is_row_wise(&materialized.view)? =>
+            {
+                let source = self
+                    .database
+                    .get_table(source_name)
+                    .expect("table should exist");
+                let rows = source
+                    .rows
+                    .range(old_row_idx_acc..)
+                    .filter_map(|(_, row)| row.clone())
+                    .collect();
+                let ctes =
+                    HashMap::from([(source_name.clone(), Arc::new(table_with_rows(source, rows)))]);
+                Some(self.evaluate_view(&view_name, &materialized.view, ctes)?)
+            }
+            _ => None,
+        };
+
+        let table = match appended_rows {
+            Some(appended_rows) => {
+                let mut table = materialized.table.clone();
+                for row in appended_rows.rows.into_values().flatten() {
+                    table.rows.insert(table.row_idx_acc, Some(row));
+                    table.row_idx_acc += 1;
+                    table.row_num += 1;
+                }
+                table
+            }
+            None => self.evaluate_view(&view_name, &materialized.view, HashMap::new())?,
+        };
+        let materialized = self
+            .database
+            .materialized_views
+            .get_mut(&view_name)
+            .expect("materialized view should exist");
+        materialized.table = table;
+        materialized.dependency_states = dependency_states;
+        Ok(())
+    }
+
+    /// Gets the row index accumulator and modification count of each table
+    /// a view depends on.
+    ///
+    /// # Arguments
+    /// * `view` - Definition of the view
+    fn get_dependency_states(&self, view: &View) -> Vec<Option<(usize, usize)>> {
+        view.dependencies
+            .iter()
+            .map(|dependency| {
+                self.database
+                    .get_table(dependency)
+                    .map(|table| (table.row_idx_acc, table.modification_count))
+            })
+            .collect()
+    }
+
+    /// Checks whether each row of a view is computed from a single row of
+    /// the table it reads from, so that rows appended to the table can be
+    /// evaluated on their own.
+    ///
+    /// # Arguments
+    /// * `view` - Definition of the view
+    fn is_row_wise(&self, view: &View) -> DBResult<bool> {
+        let mut query = SQLParser::new().parse_query(&view.query)?;
+        if query.with.is_some()
+            || query.order_by.is_some()
+            || query.limit_clause.is_some()
+            || query.fetch.is_some()
+        {
+            return Ok(false);
+        }
+        let mut relations = HashSet::new();
+        collect_relations(&mut query, &mut relations);
+        let ast::SetExpr::Select(select) = query.body.as_mut() else {
+            return Ok(false);
+        };
+        let no_group_by = matches!(
+            &select.group_by,
+            ast::GroupByExpr::Expressions(group_by, modifiers)
+                if group_by.is_empty() && modifiers.is_empty()
+        );
+        // the only relation read is the table of the FROM clause, once
+        if relations.len() != 1
+            || select.distinct.is_some()
+            || select.having.is_some()
+            || !no_group_by
+            || select.from.len() != 1
+            || !select.from[0].joins.is_empty()
+            || !matches!(select.from[0].relation, ast::TableFactor::Table { .. })
+        {
+            return Ok(false);
+        }
+        for expr in select_exprs_mut(select) {
+            if contains_subquery(expr) {
+                return Ok(false);
+            }
+            let mut calls = vec![];
+            self.aggregates
+                .collect_calls(expr, self.name_resolution(), &mut calls)?;
+            let mut window_calls = vec![];
+            collect_window_calls(
+                &self.aggregates,
+                expr,
+                self.name_resolution(),
+                &mut window_calls,
+            )?;
+            if !calls.is_empty() || !window_calls.is_empty() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Evaluates the query of a view.
+    ///
+    /// The query does not see the WITH clauses of the query reading the view.
+    ///
+    /// # Arguments
+    /// * `view_name` - Name of the view, for error messages
+    /// * `view` - Definition of the view
+    /// * `ctes` - Relations read instead of the tables of the same name
+    ///
+    /// # Returns
+    /// New table containing the rows of the view
+    pub(super) fn evaluate_view(
+        &self,
+        view_name: &str,
+        view: &View,
+        ctes: HashMap<String, Arc<Table>>,
+    ) -> DBResult<Table> {
+        let query = SQLParser::new().parse_query(&view.query)?;
+        let executor_state = SQLExecutorState {
+            sql_statements: &view.query,
+            ctes,
+            ..Default::default()
+        };
+        let mut table = self.evaluate_query(&query, &executor_state)?;
+        if view.column_names.len() > table.get_column_num() {
+            Err(DBSingleError::OtherError(format!(
+                "view {} has {} columns available but {} columns specified",
+                view_name,
+                table.get_column_num(),
+                view.column_names.len()
+            )))?
+        }
+        for (i, column_name) in view.column_names.iter().enumerate() {
+            table.rename_column(i, column_name.clone());
+        }
+        Ok(table)
+    }
+}
+
+/// Checks whether an expression contains a subquery.
+fn contains_subquery(expr: &mut ast::Expr) -> bool {
+    matches!(
+        expr,
+        ast::Expr::Subquery(_) | ast::Expr::Exists { .. } | ast::Expr::InSubquery { .. }
+    ) || expr_children_mut(expr).into_iter().any(contains_subquery)
+}
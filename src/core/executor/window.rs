@@ -0,0 +1,582 @@
+//! Window functions.
+//!
+//! A window function call (`<function>(...) OVER (PARTITION BY ... ORDER BY ...)`)
+//! computes a value for every row from the rows of its partition, sorted by
+//! the window's ORDER BY. Supported functions are:
+//! - the ranking functions `ROW_NUMBER()`, `RANK()` and `DENSE_RANK()`;
+//! - the offset functions `LAG(expr [, offset [, default]])` and `LEAD(...)`;
+//! - any aggregate of the [`AggregateRegistry`], computed over the window
+//!   frame of each row.
+//!
+//! The frame defaults to the rows from the start of the partition to the
+//! last peer of the current row (rows with equal ORDER BY keys), or to the
+//! whole partition without ORDER BY. `ROWS` frames accept any bounds;
+//! `RANGE` frames accept `UNBOUNDED PRECEDING`, `CURRENT ROW` and
+//! `UNBOUNDED FOLLOWING`.
+//!
+//! Partitions are independent of each other, which allows the
+//! [`ParallelTableManager`](super::table_manager::ParallelTableManager)
+//! to compute them in parallel.
+
+use super::aggregate::{AggregateRegistry, DynAccumulator};
+use crate::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table, Value};
+use crate::core::plan::{self, NameResolution};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The function computed by a window function call.
+enum WindowFunction<'a> {
+    /// `ROW_NUMBER()`
+    RowNumber,
+    /// `RANK()`
+    Rank,
+    /// `DENSE_RANK()`
+    DenseRank,
+    /// `LAG(...)` or `LEAD(...)`
+    Offset {
+        /// Expression evaluated on the row at the offset
+        expr: &'a ast::Expr,
+        /// Offset from the current row, 1 if not given
+        offset: Option<&'a ast::Expr>,
+        /// Value if there is no row at the offset, NULL if not given
+        default: Option<&'a ast::Expr>,
+        /// Whether the offset counts rows forward (LEAD) rather than backward (LAG)
+        is_lead: bool,
+    },
+    /// An aggregate over the frame of each row
+    Aggregate {
+        /// The aggregate implementation
+        func: Arc<dyn DynAccumulator>,
+        /// Argument expressions; empty for `*`
+        args: Vec<&'a ast::Expr>,
+    },
+}
+
+/// A bound of a window frame, relative to the current row.
+#[derive(Clone, Copy)]
+enum FrameBound {
+    UnboundedPreceding,
+    Preceding(usize),
+    CurrentRow,
+    Following(usize),
+    UnboundedFollowing,
+}
+
+/// The window frame of an aggregate window function.
+struct Frame {
+    /// Whether the bounds count rows (ROWS) rather than peer groups (RANGE)
+    is_rows: bool,
+    start: FrameBound,
+    end: FrameBound,
+}
+
+impl Frame {
+    /// Creates a frame from its definition in a window.
+    fn new(window_frame: Option<&ast::WindowFrame>) -> DBResult<Self> {
+        let Some(window_frame) = window_frame else {
+            return Ok(Frame {
+                is_rows: false,
+                start: FrameBound::UnboundedPreceding,
+                end: FrameBound::CurrentRow,
+            });
+        };
+        let is_rows = match window_frame.units {
+            ast::WindowFrameUnits::Rows => true,
+            ast::WindowFrameUnits::Range => false,
+            ast::WindowFrameUnits::Groups => Err(DBSingleError::UnsupportedOPError(
+                "GROUPS window frames are not supported".into(),
+            ))?,
+        };
+        let convert_bound = |bound: &ast::WindowFrameBound| -> DBResult<FrameBound> {
+            let offset = |expr: &ast::Expr| -> DBResult<usize> {
+                let offset = Table::get_dummy()
+                    .calc_expr_for_row(&[], expr, NameResolution::Strict)?
+                    .try_to_int()?
+                    .and_then(|offset| usize::try_from(offset).ok())
+                    .ok_or_else(|| {
+                        DBSingleError::OtherError(format!("invalid frame offset {}", expr))
+                    })?;
+                if !is_rows {
+                    Err(DBSingleError::UnsupportedOPError(
+                        "RANGE window frames with an offset are not supported".into(),
+                    ))?
+                }
+                Ok(offset)
+            };
+            Ok(match bound {
+                ast::WindowFrameBound::CurrentRow => FrameBound::CurrentRow,
+                ast::WindowFrameBound::Preceding(None) => FrameBound::UnboundedPreceding,
+                ast::WindowFrameBound::Preceding(Some(expr)) => {
+                    FrameBound::Preceding(offset(expr)?)
+                }
+                ast::WindowFrameBound::Following(None) => FrameBound::UnboundedFollowing,
+                ast::WindowFrameBound::Following(Some(expr)) => {
+                    FrameBound::Following(offset(expr)?)
+                }
+            })
+        };
+        Ok(Frame {
+            is_rows,
+            start: convert_bound(&window_frame.start_bound)?,
+            end: window_frame
+                .end_bound
+                .as_ref()
+                .map_or(Ok(FrameBound::CurrentRow), convert_bound)?,
+        })
+    }
+
+    /// Gets the range of positions covered by the frame of a row.
+    ///
+    /// # Arguments
+    /// * `position` - Position of the row in its partition
+    /// * `peers` - Range of positions of the peers of the row
+    /// * `len` - Length of the partition
+    fn range(&self, position: usize, peers: (usize, usize), len: usize) -> (usize, usize) {
+        let start = match self.start {
+            FrameBound::UnboundedPreceding => 0,
+            FrameBound::Preceding(n) => position.saturating_sub(n),
+            FrameBound::CurrentRow if self.is_rows => position,
+            FrameBound::CurrentRow => peers.0,
+            FrameBound::Following(n) => position.saturating_add(n).min(len),
+            FrameBound::UnboundedFollowing => len,
+        };
+        let end = match self.end {
+            FrameBound::UnboundedPreceding => 0,
+            FrameBound::Preceding(n) => (position + 1).saturating_sub(n),
+            FrameBound::CurrentRow if self.is_rows => position + 1,
+            FrameBound::CurrentRow => peers.1,
+            FrameBound::Following(n) => position.saturating_add(n + 1).min(len),
+            FrameBound::UnboundedFollowing => len,
+        };
+        (start, end.max(start))
+    }
+}
+
+/// Compares two window ORDER BY keys.
+///
+/// NULLs sort last in ascending order and first in descending order,
+/// unless `NULLS FIRST` or `NULLS LAST` is given.
+fn compare_keys(a: &[Value], b: &[Value], order_by: &[ast::OrderByExpr]) -> Ordering {
+    for ((a, b), order_by_expr) in a.iter().zip(b).zip(order_by) {
+        let is_asc = order_by_expr.options.asc.unwrap_or(true);
+        let nulls_first = order_by_expr.options.nulls_first.unwrap_or(!is_asc);
+        let ord = match (a.is_null(), b.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let ord = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+                if is_asc { ord } else { ord.reverse() }
+            }
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+/// Collects the window function calls in an expression.
+///
+/// # Arguments
+/// * `aggregates` - The aggregates usable as window functions
+/// * `expr` - Expression to search
+/// * `resolution` - How identifiers in the calls are resolved
+/// * `calls` - Collected calls; duplicates are skipped
+pub fn collect_window_calls<'a>(
+    aggregates: &AggregateRegistry,
+    expr: &'a ast::Expr,
+    resolution: NameResolution,
+    calls: &mut Vec<WindowCall<'a>>,
+) -> DBResult<()> {
+    use ast::Expr;
+    match expr {
+        Expr::Function(function) if function.over.is_some() => {
+            if calls.iter().any(|call| call.expr == expr) {
+                return Ok(());
+            }
+            calls.push(WindowCall::new(aggregates, expr, function, resolution)?);
+        }
+        Expr::Nested(expr)
+        | Expr::UnaryOp { expr, .. }
+        | Expr::IsFalse(expr)
+        | Expr::IsTrue(expr)
+        | Expr::IsNotTrue(expr)
+        | Expr::IsNotFalse(expr)
+        | Expr::IsNull(expr)
+        | Expr::IsNotNull(expr) => collect_window_calls(aggregates, expr, resolution, calls)?,
+        Expr::BinaryOp { left, right, .. } => {
+            collect_window_calls(aggregates, left, resolution, calls)?;
+            collect_window_calls(aggregates, right, resolution, calls)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A window function call found in a query, e.g. `RANK() OVER (ORDER BY score DESC)`.
+pub struct WindowCall<'a> {
+    /// The whole call expression; its text names the result column
+    pub expr: &'a ast::Expr,
+    /// Expressions whose values form the partition key
+    pub partition_by: &'a [ast::Expr],
+    /// Expressions sorting the rows of a partition
+    pub order_by: &'a [ast::OrderByExpr],
+    function: WindowFunction<'a>,
+    frame: Frame,
+    /// How identifiers in the call are resolved
+    resolution: NameResolution,
+}
+
+impl<'a> WindowCall<'a> {
+    /// Creates a window function call from a parsed function call.
+    fn new(
+        aggregates: &AggregateRegistry,
+        expr: &'a ast::Expr,
+        function: &'a ast::Function,
+        resolution: NameResolution,
+    ) -> DBResult<Self> {
+        let unsupported = || {
+            DBSingleError::UnsupportedOPError(format!("unsupported window function call {}", expr))
+        };
+        let Some(ast::WindowType::WindowSpec(spec)) = &function.over else {
+            Err(DBSingleError::UnsupportedOPError(format!(
+                "named windows are not supported: {}",
+                expr
+            )))?
+        };
+        if spec.window_name.is_some() || function.filter.is_some() {
+            Err(unsupported())?
+        }
+
+        let mut args = vec![];
+        let mut is_wildcard = false;
+        match &function.args {
+            ast::FunctionArguments::None => {}
+            ast::FunctionArguments::List(list) => {
+                if list.duplicate_treatment == Some(ast::DuplicateTreatment::Distinct)
+                    || !list.clauses.is_empty()
+                {
+                    Err(unsupported())?
+                }
+                for arg in &list.args {
+                    match arg {
+                        ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Expr(arg)) => {
+                            args.push(arg)
+                        }
+                        ast::FunctionArg::Unnamed(ast::FunctionArgExpr::Wildcard)
+                            if list.args.len() == 1 =>
+                        {
+                            is_wildcard = true
+                        }
+                        _ => Err(unsupported())?,
+                    }
+                }
+            }
+            ast::FunctionArguments::Subquery(_) => Err(unsupported())?,
+        }
+
+        let name = function.name.to_string().to_uppercase();
+        let window_function = match name.as_str() {
+            "ROW_NUMBER" | "RANK" | "DENSE_RANK" => {
+                if !args.is_empty() || is_wildcard {
+                    Err(DBSingleError::OtherError(format!(
+                        "{} expects no argument",
+                        name
+                    )))?
+                }
+                match name.as_str() {
+                    "ROW_NUMBER" => WindowFunction::RowNumber,
+                    "RANK" => WindowFunction::Rank,
+                    _ => WindowFunction::DenseRank,
+                }
+            }
+            "LAG" | "LEAD" => match args.as_slice() {
+                [expr, rest @ ..] if rest.len() <= 2 => WindowFunction::Offset {
+                    expr,
+                    offset: rest.first().copied(),
+                    default: rest.get(1).copied(),
+                    is_lead: name == "LEAD",
+                },
+                _ => Err(DBSingleError::OtherError(format!(
+                    "{} expects one to three arguments",
+                    name
+                )))?,
+            },
+            _ => match aggregates.get(&name) {
+                Some(func) => WindowFunction::Aggregate {
+                    func: func.clone(),
+                    args,
+                },
+                None => Err(DBSingleError::UnsupportedOPError(format!(
+                    "unknown window function {}",
+                    function.name
+                )))?,
+            },
+        };
+
+        Ok(WindowCall {
+            expr,
+            partition_by: &spec.partition_by,
+            order_by: &spec.order_by,
+            function: window_function,
+            frame: Frame::new(spec.window_frame.as_ref())?,
+            resolution,
+        })
+    }
+
+    /// Gets the name of the result column of this call.
+    pub fn column_name(&self) -> String {
+        self.expr.to_string()
+    }
+
+    /// Splits rows into the partitions of this call.
+    ///
+    /// # Arguments
+    /// * `table` - Table the rows belong to, used to evaluate the partition keys
+    /// * `rows` - Rows to split
+    ///
+    /// # Returns
+    /// The positions in `rows` of the rows of each partition, in order of first appearance
+    pub fn partition_rows(&self, table: &Table, rows: &[&Vec<Value>]) -> DBResult<Vec<Vec<usize>>> {
+        let partition_by = self
+            .partition_by
+            .iter()
+            .map(|expr| plan::compile(expr, table, self.resolution))
+            .collect::<DBResult<Vec<_>>>()?;
+        let mut partition_rmap: HashMap<Vec<Value>, usize> = HashMap::new();
+        let mut partitions: Vec<Vec<usize>> = vec![];
+        for (i, row) in rows.iter().enumerate() {
+            let key = partition_by
+                .iter()
+                .map(|expr| expr.eval(row))
+                .collect::<DBResult<Vec<_>>>()?;
+            let partition_idx = *partition_rmap.entry(key).or_insert_with(|| {
+                partitions.push(vec![]);
+                partitions.len() - 1
+            });
+            partitions[partition_idx].push(i);
+        }
+        Ok(partitions)
+    }
+
+    /// Computes the results of this call for one partition.
+    ///
+    /// # Arguments
+    /// * `table` - Table the rows belong to, used to evaluate expressions
+    /// * `rows` - All the rows being computed
+    /// * `partition` - Positions in `rows` of the rows of the partition
+    ///
+    /// # Returns
+    /// The position in `rows` and the result of each row of the partition
+    pub fn evaluate_partition(
+        &self,
+        table: &Table,
+        rows: &[&Vec<Value>],
+        partition: &[usize],
+    ) -> DBResult<Vec<(usize, Value)>> {
+        // sort the partition by the ORDER BY keys, keeping ties in input order
+        let order_by = self
+            .order_by
+            .iter()
+            .map(|order_by_expr| plan::compile(&order_by_expr.expr, table, self.resolution))
+            .collect::<DBResult<Vec<_>>>()?;
+        let keys = partition
+            .iter()
+            .map(|&i| {
+                order_by
+                    .iter()
+                    .map(|expr| expr.eval(rows[i]))
+                    .collect::<DBResult<Vec<_>>>()
+            })
+            .collect::<DBResult<Vec<_>>>()?;
+        for column in 0..self.order_by.len() {
+            let mut non_null = keys.iter().map(|key| &key[column]).filter(|v| !v.is_null());
+            if let Some(first) = non_null.next()
+                && let Some(v) = non_null.find(|v| first.partial_cmp(v).is_none())
+            {
+                Err(DBSingleError::OtherError(format!(
+                    "invalid value type for order by: {:?}",
+                    v
+                )))?
+            }
+        }
+        let mut order = (0..partition.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| compare_keys(&keys[a], &keys[b], self.order_by));
+        let sorted_rows = order
+            .iter()
+            .map(|&i| rows[partition[i]])
+            .collect::<Vec<_>>();
+
+        // peer groups: ranges of positions with equal keys
+        let mut peers = vec![(0, 0); order.len()];
+        let mut group_start = 0;
+        for position in 1..=order.len() {
+            if position == order.len()
+                || compare_keys(
+                    &keys[order[position - 1]],
+                    &keys[order[position]],
+                    self.order_by,
+                ) != Ordering::Equal
+            {
+                peers[group_start..position].fill((group_start, position));
+                group_start = position;
+            }
+        }
+
+        let values = match &self.function {
+            WindowFunction::RowNumber => (1..=order.len())
+                .map(|n| Value::from_int(n as i32))
+                .collect(),
+            WindowFunction::Rank => peers
+                .iter()
+                .map(|&(start, _)| Value::from_int(start as i32 + 1))
+                .collect(),
+            WindowFunction::DenseRank => {
+                let mut rank = 0;
+                peers
+                    .iter()
+                    .enumerate()
+                    .map(|(position, &(start, _))| {
+                        if position == start {
+                            rank += 1;
+                        }
+                        Value::from_int(rank)
+                    })
+                    .collect()
+            }
+            WindowFunction::Offset {
+                expr,
+                offset,
+                default,
+                is_lead,
+            } => {
+                let expr = plan::compile(expr, table, self.resolution)?;
+                let offset = offset
+                    .map(|offset| plan::compile(offset, table, self.resolution))
+                    .transpose()?;
+                let default = default
+                    .map(|default| plan::compile(default, table, self.resolution))
+                    .transpose()?;
+                let mut values = vec![];
+                for (position, row) in sorted_rows.iter().enumerate() {
+                    let offset = match &offset {
+                        Some(offset) => offset.eval(row)?.try_to_int()?,
+                        None => Some(1),
+                    };
+                    let target = offset.and_then(|offset| {
+                        let offset = if *is_lead { offset } else { -offset };
+                        usize::try_from(position as i64 + offset as i64)
+                            .ok()
+                            .filter(|&target| target < sorted_rows.len())
+                    });
+                    values.push(match (target, &default) {
+                        (Some(target), _) => expr.eval(sorted_rows[target])?,
+                        (None, Some(default)) => default.eval(row)?,
+                        (None, None) => Value::from_null(),
+                    });
+                }
+                values
+            }
+            WindowFunction::Aggregate { func, args } => {
+                let compiled_args = args
+                    .iter()
+                    .map(|arg| plan::compile(arg, table, self.resolution))
+                    .collect::<DBResult<Vec<_>>>()?;
+                let args = sorted_rows
+                    .iter()
+                    .map(|row| {
+                        compiled_args
+                            .iter()
+                            .map(|arg| arg.eval(row))
+                            .collect::<DBResult<Vec<_>>>()
+                    })
+                    .collect::<DBResult<Vec<_>>>()?;
+                let ranges = (0..order.len())
+                    .map(|position| self.frame.range(position, peers[position], order.len()))
+                    .collect::<Vec<_>>();
+
+                let mut values = vec![];
+                if ranges.iter().all(|&(start, _)| start == 0) {
+                    // frames growing from the start of the partition: accumulate
+                    // incrementally, as long as the state can be evaluated
+                    let mut state = func.init();
+                    let mut accumulated = 0;
+                    for &(_, end) in &ranges {
+                        while accumulated < end {
+                            func.update(&mut state, &args[accumulated])?;
+                            accumulated += 1;
+                        }
+                        let Some(value) = func.evaluate(&state) else {
+                            break;
+                        };
+                        values.push(value?);
+                    }
+                }
+                for &(start, end) in &ranges[values.len()..] {
+                    let mut state = func.init();
+                    for args in &args[start..end] {
+                        func.update(&mut state, args)?;
+                    }
+                    values.push(func.finalize(state)?);
+                }
+                values
+            }
+        };
+
+        Ok(order
+            .into_iter()
+            .map(|i| partition[i])
+            .zip(values)
+            .collect())
+    }
+}
+
+/// Constructs the result table of the window stage.
+///
+/// The result has the columns of the source table followed by one column per
+/// call, named after it; its rows are `rows`, in the same order.
+///
+/// # Arguments
+/// * `table` - Source table, whose columns are kept
+/// * `rows` - Rows of the result
+/// * `calls` - The window function calls
+/// * `results` - For each call, the result of each row
+pub fn construct_window_table(
+    table: &Table,
+    rows: &[&Vec<Value>],
+    calls: &[WindowCall],
+    results: Vec<Vec<Value>>,
+) -> Table {
+    let mut columns_info = table.columns_info.clone();
+    for call in calls {
+        columns_info.push(ColumnInfo {
+            name: call.column_name(),
+            nullable: true,
+            unique: false,
+            type_specific: ColumnTypeSpecific::Any,
+        });
+    }
+    let mut new_table = Table::new(columns_info);
+    let mut results = results
+        .into_iter()
+        .map(|values| values.into_iter())
+        .collect::<Vec<_>>();
+    new_table.rows = rows
+        .iter()
+        .map(|&row| {
+            let mut new_row = row.clone();
+            new_row.extend(results.iter_mut().map(|values| values.next().unwrap()));
+            Some(new_row)
+        })
+        .enumerate()
+        .collect();
+    new_table.row_num = rows.len();
+    new_table.row_idx_acc = rows.len();
+    new_table
+}
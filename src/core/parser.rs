@@ -1,17 +1,59 @@
 //! SQL statement parsing using sqlparser.
 //!
+//! Statements the SQL parser does not support are parsed here into
+//! [`SQLStatement`] extensions.
+//!
 //! # Example
 //! ```
 //! use helidb::core::parser::SQLParser;
 //!
 //! let parser = SQLParser::new();
-//! let statements = parser.parse("SELECT * FROM users").unwrap();
+//! let statements = parser.parse("SELECT * FROM users; VACUUM users").unwrap();
 //! ```
+//!
+//! Names are compared after [`normalize_ident`]: unquoted identifiers are
+//! case-insensitive, and quoted ones match their exact case.
 
 use crate::error::DBResult;
-use sqlparser::ast::Statement;
+use sqlparser::ast::{Expr, Ident, ObjectName, Query, Statement, TriggerEvent, TriggerPeriod};
 use sqlparser::dialect::GenericDialect;
-use sqlparser::parser::Parser;
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::Token;
+
+/// A parsed SQL statement.
+#[derive(Debug, Clone)]
+pub enum SQLStatement {
+    /// A statement supported by the SQL parser
+    Standard(Box<Statement>),
+    /// `VACUUM [<table,>...]`, all tables if none is given
+    Vacuum { table_names: Vec<ObjectName> },
+    /// `REFRESH MATERIALIZED VIEW <view>`
+    RefreshMaterializedView { name: ObjectName },
+    /// `CREATE TRIGGER` with a SQL body
+    CreateTrigger(Box<CreateTrigger>),
+}
+
+/// A `CREATE [OR REPLACE] TRIGGER <name> BEFORE|AFTER <events> ON <table>
+/// FOR EACH ROW [WHEN (<condition>)] <body>` statement, where the body is a
+/// statement or `BEGIN <statement;>... END`.
+#[derive(Debug, Clone)]
+pub struct CreateTrigger {
+    /// Whether an existing trigger with the same name is replaced
+    pub or_replace: bool,
+    /// Name of the trigger
+    pub name: ObjectName,
+    /// Whether the trigger fires before or after the change
+    pub period: TriggerPeriod,
+    /// Events firing the trigger, separated by OR
+    pub events: Vec<TriggerEvent>,
+    /// Table the trigger is attached to
+    pub table_name: ObjectName,
+    /// Condition on the changed row for the trigger to fire
+    pub condition: Option<Expr>,
+    /// Statements run for each changed row
+    pub body: Vec<Statement>,
+}
 
 /// SQL parser that converts SQL strings into abstract syntax trees.
 #[derive(Default, Debug)]
@@ -29,14 +71,186 @@ impl SQLParser {
     /// * `sql` - SQL string to parse (can contain multiple statements)
     ///
     /// # Returns
-    /// Vector of parsed `Statement` ASTs or error
+    /// Vector of parsed `SQLStatement` ASTs or error
     ///
     /// # Errors
     /// Returns `DBError` if parsing fails due to:
     /// - Syntax errors
     /// - Unsupported SQL features
-    pub fn parse(&self, sql: &str) -> DBResult<Vec<Statement>> {
+    pub fn parse(&self, sql: &str) -> DBResult<Vec<SQLStatement>> {
+        let dialect = GenericDialect {};
+        let mut parser = Parser::new(&dialect).try_with_sql(sql)?;
+        let mut statements = vec![];
+        let mut expecting_statement_delimiter = false;
+        loop {
+            // ignore empty statements (between successive statement delimiters)
+            while parser.consume_token(&Token::SemiColon) {
+                expecting_statement_delimiter = false;
+            }
+            if parser.peek_token().token == Token::EOF {
+                break;
+            }
+            if expecting_statement_delimiter {
+                parser.expected("end of statement", parser.peek_token())?;
+            }
+            statements.push(Self::parse_statement(&mut parser)?);
+            expecting_statement_delimiter = true;
+        }
+        Ok(statements)
+    }
+
+    /// Parses a SQL string holding a single query, e.g. a stored view
+    /// definition.
+    ///
+    /// # Arguments
+    /// * `sql` - SQL string to parse
+    pub fn parse_query(&self, sql: &str) -> DBResult<Query> {
+        let dialect = GenericDialect {};
+        let mut parser = Parser::new(&dialect).try_with_sql(sql)?;
+        let query = parser.parse_query()?;
+        parser.expect_token(&Token::EOF)?;
+        Ok(*query)
+    }
+
+    /// Parses a SQL string holding a single expression, e.g. a stored
+    /// trigger condition.
+    ///
+    /// # Arguments
+    /// * `sql` - SQL string to parse
+    pub fn parse_expr(&self, sql: &str) -> DBResult<Expr> {
         let dialect = GenericDialect {};
-        Ok(Parser::parse_sql(&dialect, sql)?)
+        let mut parser = Parser::new(&dialect).try_with_sql(sql)?;
+        let expr = parser.parse_expr()?;
+        parser.expect_token(&Token::EOF)?;
+        Ok(expr)
+    }
+
+    /// Parses a single statement, stopping before the statement separator.
+    ///
+    /// # Arguments
+    /// * `parser` - Parser positioned at the start of the statement
+    fn parse_statement(parser: &mut Parser) -> Result<SQLStatement, ParserError> {
+        if parser.parse_keyword(Keyword::VACUUM) {
+            let table_names = match parser.peek_token().token {
+                Token::SemiColon | Token::EOF => vec![],
+                _ => parser.parse_comma_separated(|parser| parser.parse_object_name(false))?,
+            };
+            return Ok(SQLStatement::Vacuum { table_names });
+        }
+        let is_create_trigger = match parser.peek_tokens::<4>() {
+            [Token::Word(create), Token::Word(trigger), ..]
+                if create.keyword == Keyword::CREATE && trigger.keyword == Keyword::TRIGGER =>
+            {
+                true
+            }
+            [
+                Token::Word(create),
+                Token::Word(or),
+                Token::Word(replace),
+                Token::Word(trigger),
+            ] => {
+                create.keyword == Keyword::CREATE
+                    && or.keyword == Keyword::OR
+                    && replace.keyword == Keyword::REPLACE
+                    && trigger.keyword == Keyword::TRIGGER
+            }
+            _ => false,
+        };
+        if is_create_trigger {
+            return Ok(SQLStatement::CreateTrigger(Box::new(
+                Self::parse_create_trigger(parser)?,
+            )));
+        }
+        if let Token::Word(word) = &parser.peek_token().token
+            && word.value.eq_ignore_ascii_case("REFRESH")
+        {
+            parser.next_token();
+            parser.expect_keywords(&[Keyword::MATERIALIZED, Keyword::VIEW])?;
+            let name = parser.parse_object_name(false)?;
+            return Ok(SQLStatement::RefreshMaterializedView { name });
+        }
+        Ok(SQLStatement::Standard(Box::new(parser.parse_statement()?)))
     }
+
+    /// Parses a CREATE TRIGGER statement with a SQL body.
+    ///
+    /// # Arguments
+    /// * `parser` - Parser positioned at the CREATE keyword
+    fn parse_create_trigger(parser: &mut Parser) -> Result<CreateTrigger, ParserError> {
+        parser.expect_keyword_is(Keyword::CREATE)?;
+        let or_replace = parser.parse_keywords(&[Keyword::OR, Keyword::REPLACE]);
+        parser.expect_keyword_is(Keyword::TRIGGER)?;
+        let name = parser.parse_object_name(false)?;
+        let period = parser.parse_trigger_period()?;
+        let events = parser.parse_keyword_separated(Keyword::OR, Parser::parse_trigger_event)?;
+        parser.expect_keyword_is(Keyword::ON)?;
+        let table_name = parser.parse_object_name(false)?;
+        parser.expect_keyword_is(Keyword::FOR)?;
+        // EACH is optional
+        let _ = parser.parse_keyword(Keyword::EACH);
+        parser.expect_keyword_is(Keyword::ROW)?;
+        let condition = if parser.parse_keyword(Keyword::WHEN) {
+            parser.expect_token(&Token::LParen)?;
+            let condition = parser.parse_expr()?;
+            parser.expect_token(&Token::RParen)?;
+            Some(condition)
+        } else {
+            None
+        };
+
+        let mut body = vec![];
+        if parser.parse_keyword(Keyword::BEGIN) {
+            loop {
+                while parser.consume_token(&Token::SemiColon) {}
+                if parser.parse_keyword(Keyword::END) {
+                    break;
+                }
+                body.push(parser.parse_statement()?);
+                if !parser.consume_token(&Token::SemiColon) {
+                    parser.expect_keyword_is(Keyword::END)?;
+                    break;
+                }
+            }
+        } else {
+            body.push(parser.parse_statement()?);
+        }
+        Ok(CreateTrigger {
+            or_replace,
+            name,
+            period,
+            events,
+            table_name,
+            condition,
+            body,
+        })
+    }
+}
+
+/// Gets the name an identifier refers to: an unquoted identifier is folded
+/// to lower case, and a quoted one is kept as written.
+///
+/// # Example
+/// ```
+/// use helidb::core::parser::normalize_ident;
+/// use sqlparser::ast::Ident;
+///
+/// assert_eq!(normalize_ident(&Ident::new("Users")), "users");
+/// assert_eq!(normalize_ident(&Ident::with_quote('"', "Users")), "Users");
+/// ```
+pub fn normalize_ident(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+/// Gets the name an object name refers to, its parts normalized by
+/// [`normalize_ident`] and joined by dots.
+pub fn normalize_object_name(name: &ObjectName) -> String {
+    name.0
+        .iter()
+        .filter_map(|part| part.as_ident())
+        .map(normalize_ident)
+        .collect::<Vec<_>>()
+        .join(".")
 }
@@ -0,0 +1,322 @@
+//! Logical plan: expressions bound to the tables they are evaluated against.
+//!
+//! The executor binds the expressions of a statement once, before handing
+//! them to the [`TableManager`](super::executor::table_manager::TableManager):
+//! column names are resolved to column indexes, literals are parsed into
+//! values, and operators are checked. Evaluating a bound expression against
+//! a row then no longer looks at the parsed statement.
+//!
+//! Bound expressions are compiled into a [`CompiledExpr`] before they are
+//! evaluated, which happens once for each row.
+//!
+//! # Scope
+//!
+//! The plan covers expressions only. The executor still walks the parsed
+//! statement to pick the operations to run, in the order of the clauses,
+//! and EXPLAIN records these operations as they run. Expressions are not
+//! typed: operand types are checked when an operator is evaluated. A
+//! function call is bound to the column an earlier stage computed it into,
+//! found by the text of the call. An operator tree with typed expressions
+//! is left for a later change.
+//!
+//! # Example
+//! ```
+//! use helidb::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table, Value};
+//! use helidb::core::plan::{BoundExpr, NameResolution};
+//! use sqlparser::ast::{BinaryOperator, Expr, Ident};
+//!
+//! let table = Table::new(vec![ColumnInfo {
+//!     name: "age".into(),
+//!     nullable: true,
+//!     unique: false,
+//!     type_specific: ColumnTypeSpecific::Int { display_width: None },
+//! }]);
+//! let expr = Expr::BinaryOp {
+//!     left: Box::new(Expr::Identifier(Ident::new("age"))),
+//!     op: BinaryOperator::Plus,
+//!     right: Box::new(Expr::value(sqlparser::ast::Value::Number("1".into(), false))),
+//! };
+//! let compiled = BoundExpr::bind(&expr, &table, NameResolution::Strict)
+//!     .unwrap()
+//!     .compile();
+//! assert_eq!(compiled.eval(&[Value::from_int(41)]).unwrap(), Value::from_int(42));
+//! ```
+
+mod compiled;
+
+pub use compiled::CompiledExpr;
+
+use crate::core::data_structure::{Table, Value};
+use crate::core::parser::{normalize_ident, normalize_object_name};
+use crate::error::{DBResult, DBSingleError};
+use sqlparser::ast;
+
+/// A binary operator supported by bound expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    Gt,
+    Lt,
+    GtEq,
+    LtEq,
+    Eq,
+    NotEq,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    /// Converts a parsed binary operator.
+    ///
+    /// # Errors
+    /// Returns an error if the operator is not supported.
+    fn from_ast(op: &ast::BinaryOperator) -> DBResult<Self> {
+        use ast::BinaryOperator as Op;
+        Ok(match op {
+            Op::Plus => BinaryOperator::Plus,
+            Op::Minus => BinaryOperator::Minus,
+            Op::Multiply => BinaryOperator::Multiply,
+            Op::Divide => BinaryOperator::Divide,
+            Op::Modulo => BinaryOperator::Modulo,
+            Op::Gt => BinaryOperator::Gt,
+            Op::Lt => BinaryOperator::Lt,
+            Op::GtEq => BinaryOperator::GtEq,
+            Op::LtEq => BinaryOperator::LtEq,
+            Op::Eq => BinaryOperator::Eq,
+            Op::NotEq => BinaryOperator::NotEq,
+            Op::And => BinaryOperator::And,
+            Op::Or => BinaryOperator::Or,
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported binary operator {:?}",
+                op
+            )))?,
+        })
+    }
+}
+
+/// How identifiers are resolved when binding expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameResolution {
+    /// An identifier names a column of the table, or binding fails
+    #[default]
+    Strict,
+    /// An unquoted identifier that is not a column, and a double-quoted
+    /// one, stand for a VARCHAR literal, as in earlier versions
+    Lenient,
+}
+
+/// An expression bound to the columns of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundExpr {
+    /// The value of the column at the index
+    Column(usize),
+    /// A constant value
+    Literal(Value),
+    /// `<expr> IS TRUE`
+    IsTrue(Box<BoundExpr>),
+    /// `<expr> IS FALSE`
+    IsFalse(Box<BoundExpr>),
+    /// `<expr> IS NOT TRUE`
+    IsNotTrue(Box<BoundExpr>),
+    /// `<expr> IS NOT FALSE`
+    IsNotFalse(Box<BoundExpr>),
+    /// `<expr> IS NULL`
+    IsNull(Box<BoundExpr>),
+    /// `<expr> IS NOT NULL`
+    IsNotNull(Box<BoundExpr>),
+    /// `<expr> [NOT] IN (<list>)`
+    InList {
+        expr: Box<BoundExpr>,
+        list: Vec<BoundExpr>,
+        negated: bool,
+    },
+    /// `<left> <op> <right>`
+    BinaryOp {
+        left: Box<BoundExpr>,
+        op: BinaryOperator,
+        right: Box<BoundExpr>,
+    },
+}
+
+impl BoundExpr {
+    /// Binds a parsed expression to the columns of a table.
+    ///
+    /// An identifier names a column of the table, compared after
+    /// [`normalize_ident`]; see [`NameResolution`] for identifiers that do
+    /// not.
+    /// Function calls are not computed here: they name columns after the
+    /// call (e.g. `COUNT(*)`), materialized by an earlier stage.
+    ///
+    /// # Arguments
+    /// * `expr` - Parsed expression
+    /// * `table` - Table whose rows the expression is evaluated against; only its columns are used
+    /// * `resolution` - How identifiers that do not name a column are resolved
+    ///
+    /// # Errors
+    /// Returns an error if the expression is not supported, or if an
+    /// identifier does not name a column under strict resolution.
+    pub fn bind(expr: &ast::Expr, table: &Table, resolution: NameResolution) -> DBResult<Self> {
+        use ast::Expr;
+        let bind = |expr| Self::bind(expr, table, resolution).map(Box::new);
+        Ok(match expr {
+            Expr::Nested(expr) => Self::bind(expr, table, resolution)?,
+            Expr::Identifier(name) => match resolution {
+                NameResolution::Strict => match table.get_column_index(&normalize_ident(name)) {
+                    Some(index) => BoundExpr::Column(index),
+                    None => Err(DBSingleError::OtherError(format!(
+                        "column not found: {}",
+                        name
+                    )))?,
+                },
+                NameResolution::Lenient => match table
+                    .get_column_index(&normalize_ident(name))
+                    .filter(|_| name.quote_style.is_none())
+                {
+                    Some(index) => BoundExpr::Column(index),
+                    None => BoundExpr::Literal(Value::from_varchar(name.value.clone())),
+                },
+            },
+
+            Expr::Value(val) => BoundExpr::Literal(match &val.value {
+                ast::Value::Number(num, ..) => {
+                    Value::from_int(num.parse::<i32>().map_err(|_| {
+                        DBSingleError::OtherError(format!("invalid number {}", num))
+                    })?)
+                }
+                ast::Value::Boolean(b) => Value::from_bool(*b),
+                ast::Value::Null => Value::from_null(),
+                ast::Value::SingleQuotedString(s) => Value::from_varchar(s.clone()),
+                ast::Value::DoubleQuotedString(s) => Value::from_varchar(s.clone()),
+                _ => Err(DBSingleError::UnsupportedOPError(format!(
+                    "unsupported value type {:?}",
+                    val
+                )))?,
+            }),
+
+            Expr::Function(_) => match table.get_column_index(&expr.to_string()) {
+                Some(index) => BoundExpr::Column(index),
+                None => Err(DBSingleError::UnsupportedOPError(format!(
+                    "unsupported function {}",
+                    expr
+                )))?,
+            },
+
+            Expr::IsFalse(expr) => BoundExpr::IsFalse(bind(expr)?),
+            Expr::IsTrue(expr) => BoundExpr::IsTrue(bind(expr)?),
+            Expr::IsNotTrue(expr) => BoundExpr::IsNotTrue(bind(expr)?),
+            Expr::IsNotFalse(expr) => BoundExpr::IsNotFalse(bind(expr)?),
+            Expr::IsNull(expr) => BoundExpr::IsNull(bind(expr)?),
+            Expr::IsNotNull(expr) => BoundExpr::IsNotNull(bind(expr)?),
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => BoundExpr::InList {
+                expr: bind(expr)?,
+                list: list
+                    .iter()
+                    .map(|item| Self::bind(item, table, resolution))
+                    .collect::<DBResult<_>>()?,
+                negated: *negated,
+            },
+            Expr::BinaryOp { left, op, right } => BoundExpr::BinaryOp {
+                left: bind(left)?,
+                op: BinaryOperator::from_ast(op)?,
+                right: bind(right)?,
+            },
+
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported expression {:?}",
+                expr
+            )))?,
+        })
+    }
+}
+
+/// An assignment of an UPDATE, bound to the columns of the updated table.
+#[derive(Debug)]
+pub struct BoundAssignment {
+    /// Index of the assigned column
+    pub column: usize,
+    /// The new value, evaluated against the row before the update
+    pub value: CompiledExpr,
+}
+
+impl BoundAssignment {
+    /// Binds a parsed assignment to the columns of a table.
+    ///
+    /// # Arguments
+    /// * `assignment` - Parsed assignment
+    /// * `table` - The updated table
+    /// * `resolution` - How identifiers that do not name a column are resolved
+    ///
+    /// # Errors
+    /// Returns an error if the target is not a column of the table.
+    pub fn bind(
+        assignment: &ast::Assignment,
+        table: &Table,
+        resolution: NameResolution,
+    ) -> DBResult<Self> {
+        let ast::AssignmentTarget::ColumnName(column_name) = &assignment.target else {
+            Err(DBSingleError::UnsupportedOPError(
+                "only support column name".into(),
+            ))?
+        };
+        let column_name = normalize_object_name(column_name);
+        let column = table.get_column_index(&column_name).ok_or_else(|| {
+            DBSingleError::OtherError(format!("column not found: {}", column_name))
+        })?;
+        Ok(BoundAssignment {
+            column,
+            value: compile(&assignment.value, table, resolution)?,
+        })
+    }
+}
+
+/// Checks if a row satisfies a compiled condition.
+///
+/// # Arguments
+/// * `row` - Row values to check against the condition
+/// * `cond` - Optional condition; a row always satisfies no condition
+///
+/// # Returns
+/// True if the condition evaluates to true, false if it is false or NULL.
+pub fn is_row_satisfy_cond(row: &[Value], cond: Option<&CompiledExpr>) -> DBResult<bool> {
+    Ok(match cond {
+        Some(cond) => cond.eval(row)?.try_to_bool()?.unwrap_or(false),
+        None => true,
+    })
+}
+
+/// Binds a parsed expression to the columns of a table and compiles it.
+///
+/// # Arguments
+/// * `expr` - Parsed expression
+/// * `table` - Table whose rows the expression is evaluated against
+/// * `resolution` - How identifiers that do not name a column are resolved
+pub fn compile(
+    expr: &ast::Expr,
+    table: &Table,
+    resolution: NameResolution,
+) -> DBResult<CompiledExpr> {
+    Ok(BoundExpr::bind(expr, table, resolution)?.compile())
+}
+
+/// Binds an optional condition to the columns of a table and compiles it.
+///
+/// # Arguments
+/// * `cond` - Optional parsed condition
+/// * `table` - Table whose rows the condition is evaluated against
+/// * `resolution` - How identifiers that do not name a column are resolved
+pub fn compile_cond(
+    cond: Option<&ast::Expr>,
+    table: &Table,
+    resolution: NameResolution,
+) -> DBResult<Option<CompiledExpr>> {
+    cond.map(|cond| compile(cond, table, resolution))
+        .transpose()
+}
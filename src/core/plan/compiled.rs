@@ -0,0 +1,314 @@
+//! Bound expressions compiled into trees of closures.
+//!
+//! Compiling resolves everything that does not depend on the row once:
+//! subexpressions without columns are folded into constants, operators are
+//! dispatched to a closure of their own, and operands that are columns or
+//! constants are read in place instead of being evaluated and copied.
+//! `AND` and `OR` skip their right operand when the left one determines
+//! the result.
+//!
+//! Operators follow three-valued logic: a NULL operand gives NULL, unless
+//! the other operand of `AND` or `OR` determines the result.
+//!
+//! Integer arithmetic fails on division by zero and on overflow. A constant
+//! subexpression that fails is left to fail when a row is evaluated, so
+//! that it does not fail a query without rows.
+
+use super::{BinaryOperator, BoundExpr};
+use crate::core::data_structure::{Value, ValueNotNull};
+use crate::error::{DBResult, DBSingleError};
+use std::borrow::Cow;
+
+/// Function computing a value from a row.
+type EvalFn = Box<dyn Fn(&[Value]) -> DBResult<Value> + Send + Sync>;
+
+/// An expression compiled for evaluation against rows.
+pub struct CompiledExpr(Compiled);
+
+enum Compiled {
+    /// The value of the column at the index
+    Column(usize),
+    /// A constant value
+    Constant(Value),
+    /// A value computed from the row
+    Computed(EvalFn),
+}
+
+impl std::fmt::Debug for CompiledExpr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Compiled::Column(index) => f.debug_tuple("Column").field(index).finish(),
+            Compiled::Constant(value) => f.debug_tuple("Constant").field(value).finish(),
+            Compiled::Computed(_) => f.write_str("Computed"),
+        }
+    }
+}
+
+impl CompiledExpr {
+    /// Creates an expression reading the column at the index.
+    pub fn column(index: usize) -> Self {
+        CompiledExpr(Compiled::Column(index))
+    }
+
+    /// Gets the index of the column the expression reads as is, None if it
+    /// computes its value.
+    pub fn column_index(&self) -> Option<usize> {
+        match self.0 {
+            Compiled::Column(index) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Checks if the expression is a constant.
+    fn is_constant(&self) -> bool {
+        matches!(self.0, Compiled::Constant(_))
+    }
+
+    /// Creates an expression computed by a function, folded into a constant
+    /// if its operands are constants and it evaluates without error.
+    ///
+    /// Errors are left to be raised when evaluating against a row, so that
+    /// an expression evaluated against no rows does not fail.
+    ///
+    /// # Arguments
+    /// * `is_constant` - Whether all operands of the expression are constants
+    /// * `eval` - Function computing the expression from a row
+    fn computed(
+        is_constant: bool,
+        eval: impl Fn(&[Value]) -> DBResult<Value> + Send + Sync + 'static,
+    ) -> Self {
+        if is_constant && let Ok(value) = eval(&[]) {
+            return CompiledExpr(Compiled::Constant(value));
+        }
+        CompiledExpr(Compiled::Computed(Box::new(eval)))
+    }
+
+    /// Evaluates the expression against a row, borrowing the value if it is
+    /// a column or a constant.
+    #[inline]
+    fn get<'a>(&'a self, row: &'a [Value]) -> DBResult<Cow<'a, Value>> {
+        Ok(match &self.0 {
+            Compiled::Column(index) => Cow::Borrowed(&row[*index]),
+            Compiled::Constant(value) => Cow::Borrowed(value),
+            Compiled::Computed(eval) => Cow::Owned(eval(row)?),
+        })
+    }
+
+    /// Evaluates the expression against a row of the table it is bound to.
+    ///
+    /// # Arguments
+    /// * `row` - Row values to evaluate against
+    ///
+    /// # Returns
+    /// The evaluated [`Value`].
+    pub fn eval(&self, row: &[Value]) -> DBResult<Value> {
+        Ok(self.get(row)?.into_owned())
+    }
+}
+
+impl BoundExpr {
+    /// Compiles the expression for evaluation against rows.
+    pub fn compile(&self) -> CompiledExpr {
+        match self {
+            BoundExpr::Column(index) => CompiledExpr::column(*index),
+            BoundExpr::Literal(value) => CompiledExpr(Compiled::Constant(value.clone())),
+            BoundExpr::IsFalse(expr) => unary(expr, |value| {
+                Ok(Value::from_bool(
+                    value.try_to_bool()?.map(|b| !b).unwrap_or(false),
+                ))
+            }),
+            BoundExpr::IsTrue(expr) => unary(expr, |value| {
+                Ok(Value::from_bool(value.try_to_bool()?.unwrap_or(false)))
+            }),
+            BoundExpr::IsNotTrue(expr) => unary(expr, |value| {
+                Ok(Value::from_bool(
+                    value.try_to_bool()?.map(|b| !b).unwrap_or(true),
+                ))
+            }),
+            BoundExpr::IsNotFalse(expr) => unary(expr, |value| {
+                Ok(Value::from_bool(value.try_to_bool()?.unwrap_or(true)))
+            }),
+            BoundExpr::IsNull(expr) => unary(expr, |value| Ok(Value::from_bool(value.is_null()))),
+            BoundExpr::IsNotNull(expr) => {
+                unary(expr, |value| Ok(Value::from_bool(!value.is_null())))
+            }
+            BoundExpr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let mut operands = vec![expr.compile()];
+                operands.extend(list.iter().map(BoundExpr::compile));
+                let negated = *negated;
+                let is_constant = operands.iter().all(CompiledExpr::is_constant);
+                CompiledExpr::computed(is_constant, move |row| {
+                    let value = operands[0].get(row)?;
+                    if value.is_null() {
+                        return Ok(Value::from_null());
+                    }
+                    let mut has_null = false;
+                    let mut found = false;
+                    for item in &operands[1..] {
+                        let item = item.get(row)?;
+                        if item.is_null() {
+                            has_null = true;
+                        } else if item == value {
+                            found = true;
+                            break;
+                        }
+                    }
+                    Ok(if !found && has_null {
+                        Value::from_null()
+                    } else {
+                        Value::from_bool(found != negated)
+                    })
+                })
+            }
+            BoundExpr::BinaryOp { left, op, right } => {
+                compile_binary(left.compile(), *op, right.compile())
+            }
+        }
+    }
+}
+
+/// Compiles an expression with a single operand.
+///
+/// # Arguments
+/// * `expr` - The operand
+/// * `f` - Function computing the expression from the value of the operand
+fn unary(
+    expr: &BoundExpr,
+    f: impl Fn(&Value) -> DBResult<Value> + Send + Sync + 'static,
+) -> CompiledExpr {
+    let operand = expr.compile();
+    CompiledExpr::computed(operand.is_constant(), move |row| f(&*operand.get(row)?))
+}
+
+/// Compiles a binary operation.
+fn compile_binary(left: CompiledExpr, op: BinaryOperator, right: CompiledExpr) -> CompiledExpr {
+    use BinaryOperator::*;
+    match op {
+        Plus => int_binary(left, op, right, |l, r| checked_int(l.checked_add(r))),
+        Minus => int_binary(left, op, right, |l, r| checked_int(l.checked_sub(r))),
+        Multiply => int_binary(left, op, right, |l, r| checked_int(l.checked_mul(r))),
+        Divide => int_binary(left, op, right, |l, r| {
+            checked_int(checked_divisor(l, r)?.checked_div(r))
+        }),
+        // the remainder always fits, even where the quotient overflows
+        Modulo => int_binary(left, op, right, |l, r| {
+            Ok(Value::from_int(checked_divisor(l, r)?.wrapping_rem(r)))
+        }),
+        Gt => int_binary(left, op, right, |l, r| Ok(Value::from_bool(l > r))),
+        Lt => int_binary(left, op, right, |l, r| Ok(Value::from_bool(l < r))),
+        GtEq => int_binary(left, op, right, |l, r| Ok(Value::from_bool(l >= r))),
+        LtEq => int_binary(left, op, right, |l, r| Ok(Value::from_bool(l <= r))),
+        Eq => int_binary(left, op, right, |l, r| Ok(Value::from_bool(l == r))),
+        NotEq => int_binary(left, op, right, |l, r| Ok(Value::from_bool(l != r))),
+        And | Or => {
+            let is_constant = left.is_constant() && right.is_constant();
+            // the left operand determines the result if it is false for AND, or true for OR
+            let determining = op == Or;
+            let eval = move |row: &[Value]| {
+                let l = left.get(row)?;
+                if let Some(ValueNotNull::Int(l)) = l.0
+                    && (l != 0) == determining
+                {
+                    return Ok(Value::from_bool(determining));
+                }
+                apply(op, &l, &*right.get(row)?)
+            };
+            CompiledExpr::computed(is_constant, eval)
+        }
+    }
+}
+
+/// Compiles a binary operation with a fast path for two integers.
+///
+/// # Arguments
+/// * `left` - Left operand
+/// * `op` - The operator, applied by [`apply`] to other operands
+/// * `right` - Right operand
+/// * `f` - Function applying the operator to two integers
+fn int_binary(
+    left: CompiledExpr,
+    op: BinaryOperator,
+    right: CompiledExpr,
+    f: impl Fn(i32, i32) -> DBResult<Value> + Send + Sync + 'static,
+) -> CompiledExpr {
+    let is_constant = left.is_constant() && right.is_constant();
+    let eval = move |row: &[Value]| {
+        let l = left.get(row)?;
+        let r = right.get(row)?;
+        match (&l.0, &r.0) {
+            (Some(ValueNotNull::Int(l)), Some(ValueNotNull::Int(r))) => f(*l, *r),
+            _ => apply(op, &l, &r),
+        }
+    };
+    CompiledExpr::computed(is_constant, eval)
+}
+
+/// Checks the divisor of an integer division.
+///
+/// # Returns
+/// The dividend, or an error if the divisor is zero
+fn checked_divisor(left: i32, right: i32) -> DBResult<i32> {
+    if right == 0 {
+        Err(DBSingleError::OtherError("division by zero".into()))?
+    }
+    Ok(left)
+}
+
+/// Wraps the result of a checked integer operation.
+///
+/// # Returns
+/// The integer value, or an error if the operation overflowed
+fn checked_int(result: Option<i32>) -> DBResult<Value> {
+    match result {
+        Some(result) => Ok(Value::from_int(result)),
+        None => Err(DBSingleError::OtherError("integer overflow".into()))?,
+    }
+}
+
+/// Applies a binary operator to any two values.
+fn apply(op: BinaryOperator, left: &Value, right: &Value) -> DBResult<Value> {
+    use BinaryOperator::*;
+    Ok(match (&left.0, &right.0) {
+        (Some(ValueNotNull::Int(l)), Some(ValueNotNull::Int(r))) => {
+            let (l, r) = (*l, *r);
+            match op {
+                Plus => checked_int(l.checked_add(r))?,
+                Minus => checked_int(l.checked_sub(r))?,
+                Multiply => checked_int(l.checked_mul(r))?,
+                Divide => checked_int(checked_divisor(l, r)?.checked_div(r))?,
+                Modulo => Value::from_int(checked_divisor(l, r)?.wrapping_rem(r)),
+                Gt => Value::from_bool(l > r),
+                Lt => Value::from_bool(l < r),
+                GtEq => Value::from_bool(l >= r),
+                LtEq => Value::from_bool(l <= r),
+                Eq => Value::from_bool(l == r),
+                NotEq => Value::from_bool(l != r),
+                And => Value::from_bool(l != 0 && r != 0),
+                Or => Value::from_bool(l != 0 || r != 0),
+            }
+        }
+        (Some(ValueNotNull::Varchar(l)), Some(ValueNotNull::Varchar(r))) => match op {
+            Eq => Value::from_bool(l == r),
+            _ => Err(DBSingleError::UnsupportedOPError(format!(
+                "unsupported binary operator {:?}",
+                op
+            )))?,
+        },
+        // NULL operands give NULL, except where AND/OR are determined by the other operand
+        (None, Some(ValueNotNull::Int(other))) | (Some(ValueNotNull::Int(other)), None) => match op
+        {
+            And if *other == 0 => Value::from_bool(false),
+            Or if *other != 0 => Value::from_bool(true),
+            _ => Value::from_null(),
+        },
+        (None, _) | (_, None) => Value::from_null(),
+        (l, r) => Err(DBSingleError::UnsupportedOPError(format!(
+            "unsupported binary operator {:?} {:?} {:?}",
+            op, l, r
+        )))?,
+    })
+}
@@ -1,5 +1,15 @@
 //! Database persist storage using bincode serialization.
 //!
+//! A storage file starts with [`MAGIC`] and the format version, followed by
+//! the encoded [`Database`]. Files without the header were written before it
+//! was introduced, with the layout of [`FORMAT_VERSION`] 0, and are loaded
+//! by converting them to the current layout.
+//!
+//! Version 0 files were also written before unquoted identifiers were folded
+//! to lower case, so the names of their tables and columns are folded when
+//! loaded, unless two names would become the same; those are kept as
+//! written, and can be referred to by quoting them.
+//!
 //! # Example
 //! ```
 //! # use helidb::core::storage::{load_database_from, write_database_to};
@@ -13,9 +23,101 @@
 //! let loaded = load_database_from(&*mem_file).unwrap();
 //! ```
 
-use crate::core::data_structure::Database;
+use crate::core::data_structure::{ColumnInfo, Database, Table, Value};
 use crate::error::{DBResult, DBSingleError};
-use bincode;
+use bincode::{self, Decode};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Bytes a storage file starts with, followed by the format version.
+pub const MAGIC: &[u8; 8] = b"HELIDB\0\0";
+
+/// Version of the format written by [`write_database_to`].
+///
+/// Version 0 is the format without header, whose database holds only tables,
+/// whose tables do not count their modifications, and whose names keep the
+/// case they were written with.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A database in the storage format version 0.
+#[derive(Decode)]
+struct DatabaseV0 {
+    tables: HashMap<String, TableV0>,
+}
+
+/// A table in the storage format version 0.
+#[derive(Decode)]
+struct TableV0 {
+    rows: BTreeMap<usize, Option<Vec<Value>>>,
+    row_idx_acc: usize,
+    row_num: usize,
+    columns_values: Vec<HashSet<Value>>,
+    columns_info: Vec<ColumnInfo>,
+    column_rmap: HashMap<String, usize>,
+}
+
+/// Folds names to lower case, unless two of them would become the same.
+///
+/// # Returns
+/// The folded name of each name
+fn fold_names<'a>(names: impl Iterator<Item = &'a String> + Clone) -> HashMap<String, String> {
+    let mut counts = HashMap::new();
+    for name in names.clone() {
+        *counts.entry(name.to_lowercase()).or_insert(0) += 1;
+    }
+    names
+        .map(|name| {
+            let folded = name.to_lowercase();
+            match counts[&folded] {
+                1 => (name.clone(), folded),
+                _ => (name.clone(), name.clone()),
+            }
+        })
+        .collect()
+}
+
+impl From<DatabaseV0> for Database {
+    fn from(database: DatabaseV0) -> Self {
+        let table_names = fold_names(database.tables.keys());
+        let tables = database
+            .tables
+            .into_iter()
+            .map(|(name, table)| {
+                let column_names = fold_names(table.columns_info.iter().map(|column| &column.name));
+                let mut columns_info = table.columns_info;
+                for column in &mut columns_info {
+                    column.name = column_names[&column.name].clone();
+                }
+                let column_rmap = table
+                    .column_rmap
+                    .into_iter()
+                    .map(|(name, i)| (column_names.get(&name).cloned().unwrap_or(name), i))
+                    .collect();
+                let table = Table {
+                    rows: table.rows,
+                    row_idx_acc: table.row_idx_acc,
+                    row_num: table.row_num,
+                    columns_values: table.columns_values,
+                    columns_info,
+                    column_rmap,
+                    modification_count: 0,
+                };
+                (table_names[&name].clone(), table)
+            })
+            .collect();
+        Database {
+            tables,
+            ..Database::new()
+        }
+    }
+}
+
+/// Decodes a value from the start of a buffer.
+fn decode<T: Decode<()>>(buffer: &[u8]) -> DBResult<T> {
+    let config = bincode::config::standard();
+    let (value, _) = bincode::decode_from_slice(buffer, config)
+        .map_err(|e| DBSingleError::OtherError(format!("Failed to decode data: {}", e)))?;
+    Ok(value)
+}
 
 /// Loads a database from a binary reader.
 ///
@@ -30,10 +132,19 @@ where
 {
     let mut buffer = Vec::new();
     reader.read_to_end(&mut buffer)?;
-    let config = bincode::config::standard();
-    let (database, _) = bincode::decode_from_slice(&buffer, config)
-        .map_err(|e| DBSingleError::OtherError(format!("Failed to decode data: {}", e)))?;
-    Ok(database)
+    let Some(buffer) = buffer.strip_prefix(MAGIC) else {
+        return Ok(decode::<DatabaseV0>(&buffer)?.into());
+    };
+    let (version, data) = buffer.split_first_chunk().ok_or_else(|| {
+        DBSingleError::OtherError("Failed to decode data: missing format version".into())
+    })?;
+    match u32::from_le_bytes(*version) {
+        FORMAT_VERSION => decode(data),
+        version => Err(DBSingleError::OtherError(format!(
+            "unsupported storage format version {}",
+            version
+        )))?,
+    }
 }
 
 /// Loads a database from a file at the specified path.
@@ -72,6 +183,8 @@ where
     let config = bincode::config::standard();
     let buffer = bincode::encode_to_vec(database, config)
         .map_err(|e| DBSingleError::OtherError(format!("Failed to encode data: {}", e)))?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
     writer.write_all(&buffer)?;
     Ok(())
 }
@@ -42,6 +42,14 @@ pub struct SQLExecConfig {
     pub(crate) write_back: bool,
     /// Whether to execute queries in parallel
     pub(crate) parallel: bool,
+    /// Maximum number of iterations of a recursive common table expression
+    pub(crate) max_recursion_depth: usize,
+    /// Whether to output the number of rows affected by data-modifying statements
+    pub(crate) show_affected_rows: bool,
+    /// Fraction of deleted rows in a table above which a DELETE compacts the table
+    pub(crate) auto_vacuum_threshold: Option<f64>,
+    /// Whether identifiers that do not name a column stand for strings
+    pub(crate) lenient_identifiers: bool,
 }
 
 impl Default for SQLExecConfig {
@@ -51,6 +59,10 @@ impl Default for SQLExecConfig {
             reinit: false,
             write_back: true,
             parallel: false,
+            max_recursion_depth: 1000,
+            show_affected_rows: false,
+            auto_vacuum_threshold: None,
+            lenient_identifiers: false,
         }
     }
 }
@@ -111,6 +123,64 @@ impl SQLExecConfig {
         self
     }
 
+    /// Sets the maximum number of iterations of a recursive common table
+    /// expression (`WITH RECURSIVE`), beyond which the query fails.
+    /// Defaults to 1000.
+    ///
+    /// # Arguments
+    /// * `max_recursion_depth` - Maximum number of iterations
+    ///
+    /// # Returns
+    /// Self for method chaining
+    pub fn max_recursion_depth(mut self, max_recursion_depth: usize) -> Self {
+        self.max_recursion_depth = max_recursion_depth;
+        self
+    }
+
+    /// Sets whether to output the number of rows affected by INSERT, UPDATE
+    /// and DELETE statements, as `<n> rows affected.`. The numbers are
+    /// available through [`SQLExecutor::affected_rows`] either way.
+    ///
+    /// # Arguments
+    /// * `show_affected_rows` - true to output the numbers, false otherwise
+    ///
+    /// # Returns
+    /// Self for method chaining
+    pub fn show_affected_rows(mut self, show_affected_rows: bool) -> Self {
+        self.show_affected_rows = show_affected_rows;
+        self
+    }
+
+    /// Sets the fraction of deleted rows in a table above which a DELETE
+    /// compacts the table, as `VACUUM <table>` does. Compacting renumbers the
+    /// remaining rows. None disables the automatic compaction, which is the
+    /// default.
+    ///
+    /// # Arguments
+    /// * `auto_vacuum_threshold` - Fraction of deleted rows, between 0 and 1
+    ///
+    /// # Returns
+    /// Self for method chaining
+    pub fn auto_vacuum_threshold(mut self, auto_vacuum_threshold: Option<f64>) -> Self {
+        self.auto_vacuum_threshold = auto_vacuum_threshold;
+        self
+    }
+
+    /// Sets whether identifiers are resolved leniently, for compatibility
+    /// with earlier versions: an unquoted identifier that does not name a
+    /// column, and a double-quoted identifier, stand for a VARCHAR value.
+    /// By default, an identifier that does not name a column is an error.
+    ///
+    /// # Arguments
+    /// * `lenient_identifiers` - true to resolve identifiers leniently, false otherwise
+    ///
+    /// # Returns
+    /// Self for method chaining
+    pub fn lenient_identifiers(mut self, lenient_identifiers: bool) -> Self {
+        self.lenient_identifiers = lenient_identifiers;
+        self
+    }
+
     /// Connects to the database using the specified configuration.
     ///
     /// # Returns
@@ -13,18 +13,40 @@
 //!
 //! | Operation     | Syntax |
 //! |---------------|--------|
-//! | **Create table** | `CREATE TABLE <table> (<columns,>...);` |
-//! | **Drop table**   | `DROP TABLE <table,>...;` |
-//! | **Insert**       | `INSERT <table> VALUES (<values,>...);`<br>or<br>`INSERT <table> (<columns,>...) VALUES (<values,>...);` |
-//! | **Query**        | `SELECT <columns,>... FROM <table> WHERE <condition>;` |
+//! | **Create table** | `CREATE TABLE [IF NOT EXISTS] <table> (<columns,>...);`<br>or<br>`CREATE TABLE [IF NOT EXISTS] <table> AS <query>;` |
+//! | **Drop table**   | `DROP TABLE [IF EXISTS] <table,>... [CASCADE];` |
+//! | **View**         | `CREATE [OR REPLACE] VIEW [IF NOT EXISTS] <view> [(<columns,>...)] AS <query>;`<br>or<br>`DROP VIEW [IF EXISTS] <view,>... [CASCADE];` |
+//! | **Materialized view** | `CREATE MATERIALIZED VIEW [IF NOT EXISTS] <view> [(<columns,>...)] AS <query>;`<br>or<br>`REFRESH MATERIALIZED VIEW <view>;`<br>or<br>`DROP MATERIALIZED VIEW [IF EXISTS] <view,>... [CASCADE];` |
+//! | **Alter table**  | `ALTER TABLE [IF EXISTS] <table> <operations,>...;` with operations<br>`ADD [COLUMN] [IF NOT EXISTS] <column> <type> [DEFAULT <value>]`, `DROP [COLUMN] [IF EXISTS] <column>`, `RENAME COLUMN <column> TO <name>`,<br>`ALTER [COLUMN] <column> SET/DROP NOT NULL`, `ALTER [COLUMN] <column> SET DATA TYPE <type>`, `RENAME TO <name>` |
+//! | **Insert**       | `INSERT <table> VALUES (<values,>...);`<br>or<br>`INSERT <table> (<columns,>...) VALUES (<values,>...);`<br>or<br>`INSERT <table> [(<columns,>...)] <query>;`<br>followed by<br>`ON CONFLICT [(<columns,>...)] DO NOTHING` or `ON CONFLICT [(<columns,>...)] DO UPDATE SET <assignments,> [WHERE <condition>]` or `ON DUPLICATE KEY UPDATE <assignments,>` |
+//! | **Query**        | `SELECT [DISTINCT [ON (<exprs,>...)]] <expr [AS alias],>... FROM <table> [alias] WHERE <condition> ORDER BY <keys,>... LIMIT <n> OFFSET <m>;` |
+//! | **Subquery**     | `SELECT ... FROM (<query>) [AS] <alias> [(<columns,>...)] WHERE <expr> [NOT] IN (<query>) AND [NOT] EXISTS (<query>) AND <expr> = (<query>);` |
+//! | **CTE**          | `WITH [RECURSIVE] <name> [(<columns,>...)] AS (<query>),... <query>;` |
+//! | **Set operation** | `<query> UNION/INTERSECT/EXCEPT [ALL] <query> ORDER BY <keys,>... LIMIT <n>;` |
+//! | **Aggregate**    | `SELECT <keys,>..., <aggregates,>... FROM <table> GROUP BY <keys,>... HAVING <condition>;` |
+//! | **Window**       | `SELECT <function>(<args,>...) OVER ([PARTITION BY <exprs,>...] [ORDER BY <keys,>...] [ROWS/RANGE BETWEEN <start> AND <end>]) FROM <table>;` |
 //! | **Update**       | `UPDATE <table> SET <column=value,>... WHERE <condition>;` |
 //! | **Delete**       | `DELETE FROM <table> WHERE <condition>;` |
+//! | **Truncate**     | `TRUNCATE [TABLE] <table,>...;` |
+//! | **Vacuum**       | `VACUUM [<table,>...];` |
+//! | **Trigger**      | `CREATE [OR REPLACE] TRIGGER <trigger> BEFORE/AFTER <INSERT/UPDATE/DELETE OR...> ON <table> FOR EACH ROW [WHEN (<condition>)] <statement or BEGIN <statements;>... END>;` with the changed row as `NEW.<column>` and `OLD.<column>`<br>or<br>`DROP TRIGGER [IF EXISTS] <trigger> [ON <table>];` |
+//! | **Catalog**      | `SHOW [FULL] TABLES;`<br>or<br>`SHOW CREATE TABLE/VIEW <name>;`<br>or<br>`DESCRIBE <table>;` / `SHOW COLUMNS FROM <table>;`<br>or<br>`SELECT ... FROM information_schema.tables/columns;` |
+//! | **Explain**      | `EXPLAIN [ANALYZE] <query, INSERT, UPDATE or DELETE>;` |
+//! | **Returning**    | `<insert, update or delete> RETURNING <columns,>...;` |
+//!
+//! BEFORE triggers fire once the statement computed all its changed rows,
+//! with the table as it was before the statement. They cannot change `NEW`
+//! or skip a row, and must not change their own table; an error in one
+//! cancels the statement.
 //!
 //! ## Data Model
 //!
 //! - **Database**: Contains multiple tables
 //! - **Table**: Contains rows and columns with defined schema
 //! - **Column**: Supports INT, VARCHAR, and NULL values
+//! - **NULL**: An operator with a NULL operand gives NULL, except `AND` and
+//!   `OR` when the other operand determines the result (`NULL AND FALSE` is
+//!   false). A row satisfies a condition only if the condition is true.
 //!
 //! ## Configuration ([`SQLExecConfig`])
 //!
@@ -36,6 +58,10 @@
 //! | [`reinit`](SQLExecConfig::reinit) | Reinitialize storage (clear existing data) | `false` |
 //! | [`write_back`](SQLExecConfig::write_back) | Persist changes to storage path | `true` |
 //! | [`parallel`](SQLExecConfig::parallel) | Enable parallel query execution (uses RAYON_NUM_THREADS) | `false` |
+//! | [`max_recursion_depth`](SQLExecConfig::max_recursion_depth) | Maximum iterations of a recursive CTE | `1000` |
+//! | [`show_affected_rows`](SQLExecConfig::show_affected_rows) | Output the number of rows affected by INSERT, UPDATE and DELETE | `false` |
+//! | [`auto_vacuum_threshold`](SQLExecConfig::auto_vacuum_threshold) | Fraction of deleted rows above which a DELETE compacts the table (None to disable) | `None` |
+//! | [`lenient_identifiers`](SQLExecConfig::lenient_identifiers) | Treat identifiers that are not columns, and double-quoted ones, as strings (compatibility) | `false` |
 //!
 //! ### Configuration Example
 //!
@@ -64,6 +90,9 @@
 //!
 //! - [`execute_sql`](SQLExecutor::execute_sql): Executes a single SQL statement
 //! - [`execute_sql_combine_outputs`](SQLExecutor::execute_sql_combine_outputs): Combines normal and error outputs
+//! - [`register_aggregate`](SQLExecutor::register_aggregate): Registers a user-defined aggregate function, also usable as a window function
+//! - [`affected_rows`](SQLExecutor::affected_rows): Gets the number of rows affected by each statement of the last execution
+//! - [`on_change`](SQLExecutor::on_change): Registers a function called for each committed row change
 //!
 //! Data persistence (if enabled in [`SQLExecConfig`]) occurs after SQL execution.
 //!
@@ -23,6 +23,10 @@ struct Cli {
     /// Execute queries in parallel
     #[arg(long)]
     parallel: bool,
+
+    /// Print the number of rows affected by INSERT, UPDATE and DELETE
+    #[arg(long)]
+    show_affected_rows: bool,
 }
 
 impl Cli {
@@ -32,6 +36,7 @@ impl Cli {
             .reinit(self.reinit)
             .write_back(!self.no_write_back)
             .parallel(self.parallel)
+            .show_affected_rows(self.show_affected_rows)
             .connect()
             .expect("Failed to connect to database")
     }
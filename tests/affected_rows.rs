@@ -0,0 +1,51 @@
+pub mod utils;
+
+use helidb::SQLExecConfig;
+use utils::run_sql_with_config;
+
+const SQL: &str = "CREATE TABLE t (id INT PRIMARY KEY, v INT);
+    INSERT INTO t VALUES (1, 1), (2, 2), (3, 3);
+    UPDATE t SET v = 0 WHERE id = 1;
+    DELETE FROM t WHERE id > 1;
+    SELECT * FROM t;";
+
+const OUTPUT: &str = "3 rows affected.
+
+1 row affected.
+
+2 rows affected.
+
+| id  | v   |
+| --- | --- |
+| 1   | 0   |";
+
+#[test]
+fn test_show_affected_rows() {
+    let config = SQLExecConfig::new().show_affected_rows(true);
+    run_sql_with_config(config, SQL).expect_normal(OUTPUT);
+}
+
+#[test]
+fn test_affected_rows_cleared_on_parse_error() {
+    let mut executor = SQLExecConfig::new().connect().unwrap();
+    executor
+        .execute_sql("CREATE TABLE t (id INT); INSERT INTO t VALUES (1);")
+        .unwrap();
+    assert_eq!(executor.affected_rows(), [None, Some(1)]);
+    assert!(executor.execute_sql("INSERT INTO t VALUES (").is_err());
+    assert_eq!(executor.affected_rows(), []);
+}
+
+#[test]
+fn test_cli_show_affected_rows() {
+    let sql_path = std::env::temp_dir().join("helidb_test_cli_show_affected_rows.sql");
+    std::fs::write(&sql_path, SQL).unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_helidb"))
+        .arg(&sql_path)
+        .args(["--no-write-back", "--show-affected-rows"])
+        .output()
+        .unwrap();
+    std::fs::remove_file(&sql_path).unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim_end(), OUTPUT);
+}
@@ -1,9 +1,8 @@
 pub mod utils;
 
-pub use utils::{TestResult, run_sql};
+pub use utils::{TestResult, run_sql, run_sql_parallel};
 
-#[test]
-fn test_cases() {
+fn for_each_case(run: impl Fn(&str) -> TestResult) {
     let entries = std::fs::read_dir("./tests/cases")
         .unwrap()
         .map(|res| res.unwrap().path())
@@ -13,6 +12,16 @@ fn test_cases() {
         let sql = std::fs::read_to_string(entry.join("input.txt")).unwrap();
         let expect = std::fs::read_to_string(entry.join("output.txt")).unwrap();
         println!("{}", entry.to_str().unwrap());
-        run_sql(&sql).expect(&expect);
+        run(&sql).expect(&expect);
     }
 }
+
+#[test]
+fn test_cases() {
+    for_each_case(run_sql);
+}
+
+#[test]
+fn test_cases_parallel() {
+    for_each_case(run_sql_parallel);
+}
@@ -0,0 +1,88 @@
+use helidb::SQLExecConfig;
+use helidb::core::data_structure::Value;
+use std::sync::{Arc, Mutex};
+
+const SETUP: &str = "CREATE TABLE t (id INT PRIMARY KEY, v INT NOT NULL);
+    INSERT INTO t VALUES (1, 1), (2, 2), (3, 3);";
+
+/// Checks that a failing statement leaves the table unchanged, and reports
+/// no change to the hooks.
+fn check_failed_change(config: SQLExecConfig, sql: &str) {
+    let mut executor = config.connect().unwrap();
+    executor.execute_sql(SETUP).unwrap();
+    let before = executor.execute_sql("SELECT * FROM t;").unwrap();
+    let events = Arc::new(Mutex::new(0));
+    let counter = events.clone();
+    executor.on_change(move |_| *counter.lock().unwrap() += 1);
+
+    assert!(executor.execute_sql(sql).is_err(), "{} should fail", sql);
+    assert_eq!(*events.lock().unwrap(), 0, "{} reported changes", sql);
+    assert_eq!(executor.execute_sql("SELECT * FROM t;").unwrap(), before);
+    // the unique values of the failed rows are not kept either
+    executor
+        .execute_sql("INSERT INTO t VALUES (4, 4), (5, 5); UPDATE t SET id = id + 10;")
+        .unwrap();
+    assert_eq!(*events.lock().unwrap(), 7);
+}
+
+const FAILED_CHANGES: [&str; 6] = [
+    "UPDATE t SET id = 5 WHERE id <> 2;",
+    "UPDATE t SET id = id + 1;",
+    "INSERT INTO t VALUES (4, 4), (5, NULL);",
+    "INSERT INTO t VALUES (4, 4), (5, 5), (1, 1);",
+    "INSERT INTO t VALUES (2, 2), (1, NULL) ON CONFLICT (id) DO UPDATE SET v = EXCLUDED.v;",
+    "INSERT INTO t VALUES (1, 5), (4, 4), (5, 5), (2, NULL)
+        ON CONFLICT (id) DO UPDATE SET v = EXCLUDED.v;",
+];
+
+#[test]
+fn test_failed_change_is_not_reported() {
+    for sql in FAILED_CHANGES {
+        check_failed_change(SQLExecConfig::new(), sql);
+    }
+}
+
+#[test]
+fn test_failed_change_is_not_reported_parallel() {
+    for sql in FAILED_CHANGES {
+        check_failed_change(SQLExecConfig::new().parallel(true), sql);
+    }
+}
+
+#[test]
+fn test_upsert_reports_rows_as_changed() {
+    let mut executor = SQLExecConfig::new().connect().unwrap();
+    executor
+        .execute_sql("CREATE TABLE t (id INT PRIMARY KEY, v VARCHAR(8));")
+        .unwrap();
+    let events = Arc::new(Mutex::new(vec![]));
+    let recorder = events.clone();
+    executor.on_change(move |event| {
+        let values = |row: Option<&[Value]>| {
+            row.map(|row| {
+                row.iter()
+                    .map(|value| value.to_string().into_owned())
+                    .collect::<Vec<_>>()
+            })
+        };
+        recorder.lock().unwrap().push(format!(
+            "{:?} {:?} {:?}",
+            event.operation,
+            values(event.old_row),
+            values(event.new_row),
+        ));
+    });
+    // the row is inserted, then updated by the second row of the statement
+    executor
+        .execute_sql(
+            "INSERT INTO t VALUES (1, 'a'), (1, 'b') ON CONFLICT (id) DO UPDATE SET v = EXCLUDED.v;",
+        )
+        .unwrap();
+    assert_eq!(
+        *events.lock().unwrap(),
+        [
+            r#"Insert None Some(["1", "a"])"#,
+            r#"Update Some(["1", "a"]) Some(["1", "b"])"#,
+        ]
+    );
+}
@@ -0,0 +1,113 @@
+pub mod utils;
+
+pub use utils::{run_sql, run_sql_parallel};
+
+const SETUP: &str = "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR, age INT);
+INSERT INTO users VALUES (1, 'Alice', 30), (2, 'Bob', 17), (3, 'Carol', 45);";
+
+/// Gets the lines of an output without the table borders and the padding,
+/// with the times of EXPLAIN ANALYZE replaced by `<time>`.
+fn masked_lines(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(|line| {
+            let mut cell = line.trim_matches('|').trim_end().replacen(' ', "", 1);
+            for marker in ["time=", "time: "] {
+                if let Some(start) = cell.find(marker) {
+                    let end = start + cell[start..].find("ms").unwrap() + 2;
+                    cell.replace_range(start..end, "<time>");
+                }
+            }
+            // the width of the table depends on the times
+            if !cell.is_empty() && cell.chars().all(|c| c == '-') {
+                cell = "-".into();
+            }
+            cell
+        })
+        .collect()
+}
+
+#[test]
+fn test_explain() {
+    let sql = format!(
+        "{SETUP}
+        EXPLAIN SELECT name FROM users WHERE age > 18 ORDER BY name DESC LIMIT 2;
+        EXPLAIN DELETE FROM users;
+        SELECT COUNT(*) FROM users;"
+    );
+    for (run, manager) in [
+        (run_sql as fn(&str) -> _, "sequential"),
+        (run_sql_parallel, "parallel"),
+    ] {
+        let output = run(&sql);
+        assert!(!output.is_error(), "{}", output);
+        assert_eq!(
+            masked_lines(output.as_str()),
+            [
+                "QUERY PLAN",
+                "-",
+                &format!("Table manager: {manager}"),
+                "Limit",
+                "  Clause: LIMIT 2",
+                "  -> Sort",
+                "       Sort Key: name DESC",
+                "       -> Project",
+                "            Output: name",
+                "            Filter: age > 18",
+                "            -> Scan users",
+                "",
+                "QUERY PLAN",
+                "-",
+                &format!("Table manager: {manager}"),
+                "Delete on users",
+                "",
+                "COUNT(*)",
+                "-",
+                "3",
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_explain_analyze() {
+    let sql = format!(
+        "{SETUP}
+        EXPLAIN ANALYZE SELECT age, COUNT(*) FROM users WHERE age > 18 GROUP BY age;
+        EXPLAIN ANALYZE UPDATE users SET age = age + 1 WHERE age < 40 RETURNING *;
+        SELECT * FROM users;"
+    );
+    let output = run_sql(&sql);
+    assert!(!output.is_error(), "{}", output);
+    assert_eq!(
+        masked_lines(output.as_str()),
+        [
+            "QUERY PLAN",
+            "-",
+            "Table manager: sequential",
+            "Project (rows=2 <time>)",
+            "  Output: age, COUNT(*)",
+            "  -> Aggregate (rows=2 <time>)",
+            "       Group Key: age",
+            "       Filter: age > 18",
+            "       -> Scan users (rows=3 <time>)",
+            "Execution <time>",
+            "",
+            "QUERY PLAN",
+            "-",
+            "Table manager: sequential",
+            "Project (rows=2 <time>)",
+            "  Output: *",
+            "  -> Update on users (rows=2 <time>)",
+            "       Set: age = age + 1",
+            "       Filter: age < 40",
+            "Execution <time>",
+            "",
+            "id  | name  | age",
+            "--- | ----- | ---",
+            "1   | Alice | 31",
+            "2   | Bob   | 18",
+            "3   | Carol | 45",
+        ]
+    );
+}
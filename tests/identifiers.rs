@@ -0,0 +1,56 @@
+pub mod utils;
+
+pub use utils::{run_sql, run_sql_with_config};
+
+use helidb::SQLExecConfig;
+
+const SETUP: &str = "CREATE TABLE users (id INT, name VARCHAR(32));
+INSERT INTO users VALUES (1, 'bob'), (2, 'alice');";
+
+#[test]
+fn test_strict_identifiers() {
+    run_sql(&format!(
+        "{SETUP} SELECT id FROM users WHERE name = \"bob\";"
+    ))
+    .expect_error("OtherError: column not found: \"bob\"");
+    run_sql(&format!("{SETUP} UPDATE users SET name = nobody;"))
+        .expect_error("OtherError: column not found: nobody");
+    run_sql(&format!(
+        "{SETUP} SELECT \"id\" FROM users WHERE name = 'bob';"
+    ))
+    .expect_normal("| id  |\n| --- |\n| 1   |");
+}
+
+#[test]
+fn test_lenient_identifiers() {
+    let config = SQLExecConfig::new().lenient_identifiers(true);
+    run_sql_with_config(
+        config.clone(),
+        &format!("{SETUP} SELECT id FROM users WHERE name = \"bob\" OR name = alice;"),
+    )
+    .expect_normal("| id  |\n| --- |\n| 1   |\n| 2   |");
+    run_sql_with_config(config, &format!("{SETUP} SELECT \"id\" FROM users;"))
+        .expect_normal("| id  |\n| --- |\n| id  |\n| id  |");
+}
+
+#[test]
+fn test_case_insensitive_identifiers() {
+    let sql = "CREATE TABLE Users (ID INT, \"Name\" VARCHAR(32));
+        INSERT INTO USERS (Id, \"Name\") VALUES (1, 'bob');
+        UPDATE \"users\" SET iD = 2 WHERE \"Name\" = 'bob';
+        SELECT U.id, \"Name\" AS Who FROM users u ORDER BY WHO;";
+    run_sql(sql).expect_normal("| id  | who |\n| --- | --- |\n| 2   | bob |");
+}
+
+#[test]
+fn test_quoted_identifiers_match_case() {
+    run_sql("CREATE TABLE users (id INT); SELECT \"ID\" FROM users;")
+        .expect_error("OtherError: column not found: \"ID\"");
+    run_sql("CREATE TABLE users (id INT); SELECT * FROM \"Users\";")
+        .expect_error("OtherError: table not found: Users");
+    run_sql(
+        "CREATE TABLE \"Users\" (id INT); CREATE TABLE users (id INT);
+        INSERT INTO \"Users\" VALUES (1); SELECT COUNT(*) FROM Users;",
+    )
+    .expect_normal("| COUNT(*) |\n| -------- |\n| 0        |");
+}
@@ -0,0 +1,41 @@
+use helidb::SQLExecConfig;
+
+/// Checks that a materialized view refreshed after a failed UPDATE holds the
+/// rows of its query.
+fn check_refresh_after_failed_update(config: SQLExecConfig) {
+    let mut executor = config.connect().unwrap();
+    executor
+        .execute_sql(
+            "CREATE TABLE t (id INT PRIMARY KEY);
+             INSERT INTO t VALUES (1), (2), (3);
+             CREATE MATERIALIZED VIEW v AS SELECT id FROM t;",
+        )
+        .unwrap();
+    // fails on the second row whose id is set to 5
+    assert!(
+        executor
+            .execute_sql("UPDATE t SET id = 5 WHERE id <> 2;")
+            .is_err()
+    );
+    executor
+        .execute_sql("INSERT INTO t VALUES (4); REFRESH MATERIALIZED VIEW v;")
+        .unwrap();
+    assert_eq!(
+        executor
+            .execute_sql("SELECT * FROM v ORDER BY id;")
+            .unwrap(),
+        executor
+            .execute_sql("SELECT id FROM t ORDER BY id;")
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_refresh_after_failed_update() {
+    check_refresh_after_failed_update(SQLExecConfig::new());
+}
+
+#[test]
+fn test_refresh_after_failed_update_parallel() {
+    check_refresh_after_failed_update(SQLExecConfig::new().parallel(true));
+}
@@ -0,0 +1,190 @@
+use helidb::core::data_structure::{ColumnInfo, ColumnTypeSpecific, Table, Value};
+use helidb::core::parser::SQLParser;
+use helidb::core::plan::{
+    BinaryOperator, BoundExpr, NameResolution, compile, compile_cond, is_row_satisfy_cond,
+};
+
+/// Creates a table with the given INT columns.
+fn table(columns: &[&str]) -> Table {
+    Table::new(
+        columns
+            .iter()
+            .map(|name| ColumnInfo {
+                name: name.to_string(),
+                nullable: true,
+                unique: false,
+                type_specific: ColumnTypeSpecific::Int {
+                    display_width: None,
+                },
+            })
+            .collect(),
+    )
+}
+
+/// Gets the message of an error, as output by the executor.
+fn message(err: impl std::fmt::Display) -> String {
+    err.to_string().trim_end().to_string()
+}
+
+/// Binds an expression to the columns of a table.
+fn bind(sql: &str, table: &Table, resolution: NameResolution) -> Result<BoundExpr, String> {
+    let expr = SQLParser::new().parse_expr(sql).unwrap();
+    BoundExpr::bind(&expr, table, resolution).map_err(message)
+}
+
+/// Compiles an expression against a table, and evaluates it against a row.
+fn eval(sql: &str, table: &Table, row: &[Value]) -> Result<Value, String> {
+    let expr = SQLParser::new().parse_expr(sql).unwrap();
+    compile(&expr, table, NameResolution::Strict)
+        .and_then(|compiled| compiled.eval(row))
+        .map_err(message)
+}
+
+#[test]
+fn test_bind_resolves_columns() {
+    let table = table(&["a", "B"]);
+    assert_eq!(
+        bind("(A + 1) * \"B\"", &table, NameResolution::Strict),
+        Ok(BoundExpr::BinaryOp {
+            left: Box::new(BoundExpr::BinaryOp {
+                left: Box::new(BoundExpr::Column(0)),
+                op: BinaryOperator::Plus,
+                right: Box::new(BoundExpr::Literal(Value::from_int(1))),
+            }),
+            op: BinaryOperator::Multiply,
+            right: Box::new(BoundExpr::Column(1)),
+        })
+    );
+    assert_eq!(
+        bind("a IN (1, NULL)", &table, NameResolution::Strict),
+        Ok(BoundExpr::InList {
+            expr: Box::new(BoundExpr::Column(0)),
+            list: vec![
+                BoundExpr::Literal(Value::from_int(1)),
+                BoundExpr::Literal(Value::from_null()),
+            ],
+            negated: false,
+        })
+    );
+}
+
+#[test]
+fn test_bind_errors() {
+    let table = table(&["a"]);
+    let strict = NameResolution::Strict;
+    assert_eq!(
+        bind("b = 1", &table, strict),
+        Err("OtherError: column not found: b".into())
+    );
+    assert_eq!(
+        bind("\"A\" = 1", &table, strict),
+        Err("OtherError: column not found: \"A\"".into())
+    );
+    assert_eq!(
+        bind("a = 99999999999", &table, strict),
+        Err("OtherError: invalid number 99999999999".into())
+    );
+    assert_eq!(
+        bind("a || 'x'", &table, strict),
+        Err("UnsupportedOPError: unsupported binary operator StringConcat".into())
+    );
+    assert_eq!(
+        bind("MAX(a)", &table, strict),
+        Err("UnsupportedOPError: unsupported function MAX(a)".into())
+    );
+}
+
+#[test]
+fn test_bind_lenient_identifiers() {
+    let table = table(&["a"]);
+    let lenient = NameResolution::Lenient;
+    assert_eq!(bind("a", &table, lenient), Ok(BoundExpr::Column(0)));
+    assert_eq!(
+        bind("b", &table, lenient),
+        Ok(BoundExpr::Literal(Value::from_varchar("b".into())))
+    );
+    assert_eq!(
+        bind("\"a\"", &table, lenient),
+        Ok(BoundExpr::Literal(Value::from_varchar("a".into())))
+    );
+}
+
+#[test]
+fn test_bind_materialized_call() {
+    // an aggregate is computed by an earlier stage, into a column named after the call
+    let table = table(&["k", "COUNT(*)"]);
+    assert_eq!(
+        bind("COUNT(*) > 1", &table, NameResolution::Strict),
+        Ok(BoundExpr::BinaryOp {
+            left: Box::new(BoundExpr::Column(1)),
+            op: BinaryOperator::Gt,
+            right: Box::new(BoundExpr::Literal(Value::from_int(1))),
+        })
+    );
+}
+
+#[test]
+fn test_compile_and_eval() {
+    let table = table(&["a", "b"]);
+    let row = [Value::from_int(7), Value::from_null()];
+    assert_eq!(eval("a % 4 + 1", &table, &row), Ok(Value::from_int(4)));
+    assert_eq!(eval("a + b", &table, &row), Ok(Value::from_null()));
+    assert_eq!(
+        eval("b = 1 OR a > 5", &table, &row),
+        Ok(Value::from_bool(true))
+    );
+    assert_eq!(eval("b IS NULL", &table, &row), Ok(Value::from_bool(true)));
+    assert_eq!(
+        eval("a / (b - b)", &table, &row),
+        Ok(Value::from_null()),
+        "a NULL divisor gives NULL"
+    );
+    assert_eq!(
+        eval("a / (a - 7)", &table, &row),
+        Err("OtherError: division by zero".into())
+    );
+    assert_eq!(
+        eval("a * 2147483647", &table, &row),
+        Err("OtherError: integer overflow".into())
+    );
+}
+
+#[test]
+fn test_compile_folds_constants() {
+    let table = table(&["a"]);
+    let compiled = |sql: &str| {
+        let expr = SQLParser::new().parse_expr(sql).unwrap();
+        format!(
+            "{:?}",
+            compile(&expr, &table, NameResolution::Strict).unwrap()
+        )
+    };
+    assert_eq!(compiled("(1 + 2) * 3"), "Constant(Value(Some(Int(9))))");
+    assert_eq!(compiled("a"), "Column(0)");
+    assert_eq!(compiled("a + 1"), "Computed");
+    // a failing constant is left to fail when a row is evaluated
+    assert_eq!(compiled("1 / 0"), "Computed");
+}
+
+#[test]
+fn test_conditions() {
+    let table = table(&["a"]);
+    let cond = |sql: &str| {
+        let expr = SQLParser::new().parse_expr(sql).unwrap();
+        compile_cond(Some(&expr), &table, NameResolution::Strict).unwrap()
+    };
+    let row = [Value::from_int(1)];
+    assert!(is_row_satisfy_cond(&row, cond("a = 1").as_ref()).unwrap());
+    assert!(!is_row_satisfy_cond(&row, cond("a = 2").as_ref()).unwrap());
+    // a NULL condition is not satisfied
+    assert!(!is_row_satisfy_cond(&row, cond("a = NULL").as_ref()).unwrap());
+    assert!(
+        is_row_satisfy_cond(
+            &row,
+            compile_cond(None, &table, NameResolution::Strict)
+                .unwrap()
+                .as_ref()
+        )
+        .unwrap()
+    );
+}
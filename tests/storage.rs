@@ -0,0 +1,147 @@
+use helidb::SQLExecConfig;
+use helidb::core::data_structure::Database;
+use helidb::core::storage::load_database_from_path;
+use std::path::PathBuf;
+
+/// Gets a path in the temporary directory for a test's storage file,
+/// removing any file left by a previous run.
+fn storage_path(test_name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("helidb_{}.db", test_name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Executes SQL statements against the database stored at a path, writing
+/// it back afterwards, and loads the written database.
+fn execute_and_load(config: SQLExecConfig, path: &PathBuf, sql: &str) -> Database {
+    config
+        .storage_path(Some(path.clone()))
+        .connect()
+        .unwrap()
+        .execute_sql(sql)
+        .unwrap();
+    load_database_from_path(path).unwrap()
+}
+
+#[test]
+fn test_truncate_resets_table_and_shrinks_file() {
+    let path = storage_path("truncate");
+    let rows = (0..1000)
+        .map(|i| format!("({}, 'name{}')", i, i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "CREATE TABLE t (id INT PRIMARY KEY, name VARCHAR(16));
+         INSERT INTO t VALUES {};
+         DELETE FROM t WHERE id < 10;",
+        rows
+    );
+    execute_and_load(SQLExecConfig::new(), &path, &sql);
+    let size_before = std::fs::metadata(&path).unwrap().len();
+
+    let database = execute_and_load(SQLExecConfig::new(), &path, "TRUNCATE TABLE t;");
+    let table = database.get_table("t").unwrap();
+    assert!(table.rows.is_empty());
+    assert_eq!(table.row_idx_acc, 0);
+    assert_eq!(table.row_num, 0);
+    assert!(table.columns_values.iter().all(|values| values.is_empty()));
+    let size_after = std::fs::metadata(&path).unwrap().len();
+    assert!(
+        size_after < size_before / 10,
+        "{} bytes before TRUNCATE, {} after",
+        size_before,
+        size_after
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_vacuum_removes_tombstones() {
+    let path = storage_path("vacuum");
+    let database = execute_and_load(
+        SQLExecConfig::new(),
+        &path,
+        "CREATE TABLE t (id INT PRIMARY KEY);
+         INSERT INTO t VALUES (1), (2), (3), (4);
+         DELETE FROM t WHERE id < 4;",
+    );
+    let table = database.get_table("t").unwrap();
+    assert_eq!(table.get_tombstone_num(), 3);
+
+    let database = execute_and_load(SQLExecConfig::new(), &path, "VACUUM t;");
+    let table = database.get_table("t").unwrap();
+    assert_eq!(table.get_tombstone_num(), 0);
+    assert_eq!(table.rows.len(), table.row_num);
+    assert_eq!(table.row_num, 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_auto_vacuum_removes_tombstones() {
+    let path = storage_path("auto_vacuum");
+    let config = || SQLExecConfig::new().auto_vacuum_threshold(Some(0.5));
+    let database = execute_and_load(
+        config(),
+        &path,
+        "CREATE TABLE t (id INT PRIMARY KEY);
+         INSERT INTO t VALUES (1), (2), (3), (4);
+         DELETE FROM t WHERE id < 3;",
+    );
+    // half of the rows are deleted, which does not exceed the threshold
+    let table = database.get_table("t").unwrap();
+    assert_eq!(table.get_tombstone_num(), 2);
+
+    let database = execute_and_load(config(), &path, "DELETE FROM t WHERE id = 3;");
+    let table = database.get_table("t").unwrap();
+    assert_eq!(table.get_tombstone_num(), 0);
+    assert_eq!(table.rows.len(), table.row_num);
+    assert_eq!(table.row_num, 1);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_baseline_format() {
+    // written by the first version, without format header, and with the
+    // case of the names as written
+    let path = storage_path("baseline_format");
+    std::fs::copy("tests/data/baseline.db", &path).unwrap();
+    let mut executor = SQLExecConfig::new()
+        .storage_path(Some(path.clone()))
+        .connect()
+        .unwrap();
+    let output = executor
+        .execute_sql("SELECT * FROM users; SELECT UserId, Amount FROM Orders;")
+        .unwrap();
+    assert_eq!(
+        output,
+        "| id  | name  |\n| --- | ----- |\n| 1   | Alice |\n| 3   | Carol |\n\n\
+         | userid | amount |\n| ------ | ------ |\n| 1      | 100    |\n| 3      | 70     |\n"
+    );
+    // names which would become the same are kept as written
+    let output = executor
+        .execute_sql(
+            "SELECT \"Code\", \"code\" FROM items; \
+             SELECT * FROM \"Tags\" UNION ALL SELECT * FROM \"tags\";",
+        )
+        .unwrap();
+    assert_eq!(
+        output,
+        "| Code | code |\n| ---- | ---- |\n| 1    | 2    |\n\n\
+         | id  |\n| --- |\n| 1   |\n| 2   |\n"
+    );
+
+    // written back in the current format
+    executor
+        .execute_sql("INSERT INTO users VALUES (2, 'Bob');")
+        .unwrap();
+    assert!(
+        std::fs::read(&path)
+            .unwrap()
+            .starts_with(helidb::core::storage::MAGIC)
+    );
+    let database = load_database_from_path(&path).unwrap();
+    let table = database.get_table("users").unwrap();
+    assert_eq!(table.row_num, 3);
+    assert_eq!(table.get_tombstone_num(), 1);
+    std::fs::remove_file(&path).unwrap();
+}
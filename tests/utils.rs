@@ -46,7 +46,15 @@ impl std::fmt::Display for TestResult {
 }
 
 pub fn run_sql(sql: &str) -> TestResult {
-    let (no_error, output) = SQLExecConfig::new()
+    run_sql_with_config(SQLExecConfig::new(), sql)
+}
+
+pub fn run_sql_parallel(sql: &str) -> TestResult {
+    run_sql_with_config(SQLExecConfig::new().parallel(true), sql)
+}
+
+pub fn run_sql_with_config(config: SQLExecConfig, sql: &str) -> TestResult {
+    let (no_error, output) = config
         .connect()
         .expect("Failed to connect to database")
         .execute_sql_combine_outputs(sql);